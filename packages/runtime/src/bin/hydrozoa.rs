@@ -2,24 +2,80 @@
 #![no_std]
 
 use anyhow::Context;
-use runtime::{platform, sdk, teavm, Data};
-use vexide::{program::exit, prelude::*};
+use runtime::{platform, sdk, teavm, wasi, Data};
+use vexide::{prelude::*, program::exit};
 use vexide_wasm_startup::{startup, CodeSignature, ProgramFlags, ProgramOwner, ProgramType};
-use wasm3::{Environment, Store};
+use wasm3::{Environment, Linker, Store};
 
 extern crate alloc;
 
 fn main(_peripherals: Peripherals) {
     let env = wasm3::Environment::new().expect("Unable to create environment");
     let mut store = env
-        .create_store(8192, Data::default())
+        .create_store_with_memory_limit(
+            8192,
+            platform::max_memory_pages(),
+            Data {
+                teavm: None,
+                wasi: wasi::ctx(),
+                files: Default::default(),
+            },
+        )
         .expect("Unable to create runtime");
+    #[cfg(feature = "profiling")]
+    store.set_profile_clock(profile_clock);
 
     if let Err(mut err) = run(&env, &mut store) {
         if let Some(info) = store.take_error_info() {
             err = err.context(info);
         }
         println!("\nError: {:?}", err);
+        if let Some(wasm3::error::Error::Trap { backtrace, .. }) = err.downcast_ref() {
+            print_backtrace(backtrace);
+        }
+    }
+    #[cfg(feature = "profiling")]
+    print_profile(&store);
+}
+
+fn print_backtrace(backtrace: &[wasm3::error::Frame]) {
+    println!("\nStack backtrace:");
+    for (i, frame) in backtrace.iter().enumerate() {
+        println!(
+            "{:>4}: {} (function {}, offset {:#x})",
+            i,
+            frame.name.as_deref().unwrap_or("<unknown>"),
+            frame.function_index,
+            frame.module_offset
+        );
+    }
+}
+
+#[cfg(feature = "profiling")]
+extern "C" fn profile_clock() -> u64 {
+    unsafe { vex_sdk::vexSystemHighResTimeGet() }
+}
+
+#[cfg(feature = "profiling")]
+fn print_profile(store: &Store<Data>) {
+    const TOP: usize = 20;
+
+    println!("\nProfile (top {} functions):", TOP);
+    println!("{:>12} {:>10}  function", "time (us)", "calls");
+    for profile in store.profile().iter().take(TOP) {
+        println!(
+            "{:>12} {:>10}  {} (function {})",
+            profile.time,
+            profile.calls,
+            profile.name.as_deref().unwrap_or("<unknown>"),
+            profile.function_index
+        );
+    }
+
+    println!("\nProfile (top {} operations):", TOP);
+    println!("{:>12}  operation", "hits");
+    for op in wasm3::profile::op_profile().iter().take(TOP) {
+        println!("{:>12}  {}", op.hits, op.name);
     }
 }
 
@@ -28,11 +84,28 @@ fn run(env: &Environment, store: &mut Store<Data>) -> anyhow::Result<()> {
     let module = env
         .parse_module(wasm_bytes)
         .context("Unable to parse module")?;
+    // programs built for WASI are started through `_start`, everything else is a TeaVM program
+    let is_wasi = module.exports().any(|export| export.name() == "_start");
 
-    let mut instance = store.instantiate(module).context("Unable to load module")?;
+    let mut linker = Linker::new();
+    // fail before the program starts rather than when it first calls a missing function
+    linker.strict(true);
+    teavm::link_teavm(&mut linker).context("Unable to link teavm")?;
+    sdk::link(&mut linker).context("Unable to link sdk")?;
+    wasm3::wasi::add_to_linker(&mut linker, |data| &mut data.wasi)
+        .context("Unable to link wasi")?;
 
-    teavm::link_teavm(&mut *store, &mut instance).context("Unable to link teavm")?;
-    sdk::link(&mut *store, &mut instance).context("Unable to link sdk")?;
+    let mut instance = linker
+        .instantiate(store, module)
+        .context("Unable to load module")?;
+    // compiling on first call would stall the program when it first runs a function
+    instance
+        .compile_all(&mut *store)
+        .context("Unable to compile module")?;
+    if is_wasi {
+        return wasi::run(store, &instance);
+    }
+    teavm::init_teavm(&mut *store, &mut instance).context("Unable to initialize teavm")?;
 
     teavm::teamvm_main(&mut *store, &mut instance, &[]).context("Unable to run main")?;
 
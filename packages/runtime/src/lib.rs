@@ -7,8 +7,11 @@ mod libc_support;
 pub mod platform;
 pub mod sdk;
 pub mod teavm;
+pub mod wasi;
 
 #[derive(Default)]
 pub struct Data {
     pub teavm: Option<teavm::TeaVM>,
+    pub wasi: wasm3::wasi::WasiCtx,
+    pub files: sdk::Files,
 }
@@ -11,6 +11,15 @@ use vexide::{
 
 const LINKED_FILE: *const u32 = 0x7800000 as *const u32;
 
+/// Heap space kept free for the runtime itself, e.g. the wasm3 stack and compiled functions.
+const RUNTIME_HEAP_RESERVE: usize = 8 * 1024 * 1024;
+const WASM_PAGE_SIZE: usize = 64 * 1024;
+
+extern "C" {
+    static mut __heap_start: u8;
+    static mut __heap_end: u8;
+}
+
 pub fn read_user_program() -> &'static [u8] {
     unsafe {
         let len = ptr::read_volatile(LINKED_FILE);
@@ -19,6 +28,14 @@ pub fn read_user_program() -> &'static [u8] {
     }
 }
 
+/// The number of wasm memory pages a user program may grow to without exhausting the heap.
+pub fn max_memory_pages() -> u32 {
+    let heap_len =
+        unsafe { ptr::addr_of!(__heap_end) as usize - ptr::addr_of!(__heap_start) as usize };
+    // growing the memory reallocates it, so the old and the new memory briefly coexist
+    (heap_len.saturating_sub(RUNTIME_HEAP_RESERVE) / 2 / WASM_PAGE_SIZE) as u32
+}
+
 pub fn flush_serial() {
     while unsafe { vex_sdk::vexSerialWriteFree(1) < 2048 } {
         unsafe {
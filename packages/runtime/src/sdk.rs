@@ -1,21 +1,32 @@
 #![allow(non_snake_case)]
 
-use core::ffi::c_double;
+use alloc::{ffi::CString, format, vec::Vec};
+use core::{
+    cell::LazyCell,
+    ffi::{c_double, CStr},
+    mem::{self, MaybeUninit},
+    ptr, slice,
+};
 
+use hashbrown::HashMap;
 use vex_sdk::*;
-use vexide::prelude::Display;
-use wasm3::{error::Trap, store::AsContextMut, Instance, Store};
+use vexide::{prelude::Display, sync::Mutex};
+use wasm3::{
+    error::{HostError, Trap},
+    store::AsContextMut,
+    CallContext, Linker, Memory, WasmPtr, WasmSlice,
+};
 
 use crate::{platform::draw_error, teavm::get_cstring, Data};
 
+// only for arguments passed by value, guest pointers are resolved through `WasmPtr` below
 macro_rules! link {
-    ($instance:ident, $store:ident, mod $module:literal {
+    ($linker:ident, mod $module:literal {
         $( fn $name:ident ( $($arg:ident: $arg_ty:ty $(as $wrapper:expr)? $(,)?),* )  $(-> $ret:ty $(, in .$field:tt)?)?; )*
     }) => {
         {
             $(
-                $instance.link_closure(
-                    &mut *$store,
+                $linker.func_wrap(
                     $module,
                     stringify!($name),
                     #[allow(unused_parens)]
@@ -37,18 +48,17 @@ macro_rules! link {
 }
 
 macro_rules! printf_style {
-    ($instance:ident, $store:ident, mod $module:literal {
+    ($linker:ident, mod $module:literal {
         $( fn $name:ident ( $($arg:ident: $arg_ty:ty,)* @printf@); )*
     }) => {
         {
             $(
-                $instance.link_closure(
-                    &mut *$store,
+                $linker.func_wrap(
                     $module,
                     stringify!($name),
                     #[allow(unused_parens)]
                     |mut ctx, ($($arg,)* string): ($($arg_ty,)* i32)| {
-                        let string = get_cstring(&mut ctx, string);
+                        let string = get_cstring(&mut ctx, string)?;
                         unsafe {
                             vex_sdk::$name(
                                 $($arg,)*
@@ -64,13 +74,172 @@ macro_rules! printf_style {
     };
 }
 
-pub fn link(store: &mut Store<Data>, instance: &mut Instance<Data>) -> anyhow::Result<()> {
-    instance.link_closure(
-        &mut *store,
+macro_rules! link_struct_getters {
+    ($linker:ident, mod $module:literal {
+        $( fn $name:ident (device: u32, data: WasmPtr<$ty:ty>) as [f64; $len:literal]; )*
+    }) => {
+        {
+            $(
+                $linker.func_wrap(
+                    $module,
+                    stringify!($name),
+                    |mut ctx, (device, data): (u32, WasmPtr<$ty>)| {
+                        // the SDK types are plain structs of `f64`s, but can't implement `Pod`
+                        // since they are foreign, so they are copied to the guest as an array
+                        let mut value: $ty = unsafe { core::mem::zeroed() };
+                        unsafe { vex_sdk::$name(device as _, &mut value) };
+                        let fields: [f64; $len] = unsafe { core::mem::transmute(value) };
+                        *data.cast::<[f64; $len]>().deref_mut(&mut ctx.memory_view())? = fields;
+                        Ok(())
+                    }
+                )?;
+            )*
+        }
+    };
+}
+
+macro_rules! link_device {
+    ($linker:ident, mod $module:literal, $device_type:path as $kind:literal {
+        $( fn $name:ident (device: u32 $(, $arg:ident: $arg_ty:ty $(as $wrapper:expr)?)* )  $(-> $ret:ty $(, in .$field:tt)?)?; )*
+    }) => {
+        {
+            $(
+                $linker.func_wrap(
+                    $module,
+                    stringify!($name),
+                    |_ctx, (device, $($arg),*): (u32, $($arg_ty),*)| {
+                        #[inline]
+                        fn inner(device: u32, $($arg: $arg_ty),*) $(-> $ret)? {
+                            unsafe {
+                                vex_sdk::$name(
+                                    device as _,
+                                    $($($wrapper)? ($arg as _)),*
+                                ) $($(.$field)? as $ret)?
+                            }
+                        }
+                        check_device(device, $device_type, $kind)?;
+                        Ok(inner(device, $($arg),*))
+                    }
+                )?;
+            )*
+        }
+    };
+}
+
+/// The port index of every device handle, which the SDK hands out once per port for the whole
+/// lifetime of the program.
+static DEVICE_PORTS: Mutex<LazyCell<HashMap<u32, usize>>> = Mutex::new(LazyCell::new(|| {
+    (0..V5_MAX_DEVICE_PORTS)
+        .map(|port| (unsafe { vexDeviceGetByIndex(port as u32) } as u32, port))
+        .collect()
+}));
+
+/// Makes sure the guest passed the handle of a device of the given type, as the SDK would
+/// happily drive a sensor like a motor or dereference a bogus handle.
+fn check_device(device: u32, device_type: V5_DeviceType, kind: &str) -> Result<(), HostError> {
+    let port = DEVICE_PORTS
+        .try_lock()
+        .unwrap()
+        .get(&device)
+        .copied()
+        .ok_or_else(|| HostError::new(format!("{device:#x} is not a device handle")))?;
+
+    let mut devices = [V5_DeviceType::kDeviceTypeNoSensor; V5_MAX_DEVICE_PORTS];
+    unsafe { vexDeviceGetStatus(devices.as_mut_ptr()) };
+    if devices[port] != device_type {
+        return Err(HostError::new(format!("port {} is not a {kind}", port + 1)));
+    }
+    Ok(())
+}
+
+/// The files opened by the guest, which it refers to by their index plus one instead of the SDK's
+/// pointers, so that 0 still means that opening failed.
+#[derive(Default)]
+pub struct Files(Vec<*mut FIL>);
+
+impl Files {
+    fn insert(&mut self, fd: *mut FIL) -> u32 {
+        if fd.is_null() {
+            return 0;
+        }
+        let index = match self.0.iter().position(|open| open.is_null()) {
+            Some(index) => {
+                self.0[index] = fd;
+                index
+            }
+            None => {
+                self.0.push(fd);
+                self.0.len() - 1
+            }
+        };
+        index as u32 + 1
+    }
+
+    fn get(&self, handle: u32) -> Result<*mut FIL, HostError> {
+        (handle as usize)
+            .checked_sub(1)
+            .and_then(|index| self.0.get(index))
+            .copied()
+            .filter(|fd| !fd.is_null())
+            .ok_or_else(|| HostError::new(format!("{handle} is not an open file")))
+    }
+
+    fn remove(&mut self, handle: u32) -> Result<*mut FIL, HostError> {
+        let fd = self.get(handle)?;
+        self.0[handle as usize - 1] = ptr::null_mut();
+        Ok(fd)
+    }
+}
+
+/// Borrows a null-terminated string from guest memory, the way the SDK takes strings.
+fn cstr<'m>(memory: &'m Memory<'_>, ptr: WasmPtr<u8>) -> Result<&'m CStr, Trap> {
+    let len = u32::try_from(memory.len())
+        .unwrap_or(u32::MAX)
+        .saturating_sub(ptr.offset());
+    CStr::from_bytes_until_nul(memory.bytes(ptr.offset(), len)?)
+        .map_err(|_| Trap::OutOfBoundsMemoryAccess)
+}
+
+/// Lets the SDK fill in a struct, then copies it to the guest.
+///
+/// The guest lays out the SDK's `#[repr(C)]` structs like the brain does, as both are 32-bit and
+/// little-endian, so they are copied byte for byte. The SDK writes to a copy on the host, as guest
+/// memory carries no alignment guarantees.
+fn sdk_out<T, R>(
+    ctx: &mut CallContext<'_, Data>,
+    ptr: WasmPtr<T>,
+    f: impl FnOnce(*mut T) -> R,
+) -> Result<R, HostError> {
+    let mut memory = ctx.memory_view();
+    let dest = memory.bytes_mut(ptr.offset(), mem::size_of::<T>() as u32)?;
+    let mut value = MaybeUninit::<T>::zeroed();
+    let ret = f(value.as_mut_ptr());
+    // SAFETY: every byte was zeroed before the SDK wrote the fields
+    dest.copy_from_slice(unsafe {
+        slice::from_raw_parts(value.as_ptr().cast::<u8>(), mem::size_of::<T>())
+    });
+    Ok(ret)
+}
+
+/// Copies a struct from the guest for the SDK to read, see [`sdk_out`].
+fn sdk_in<T, R>(
+    ctx: &mut CallContext<'_, Data>,
+    ptr: WasmPtr<T>,
+    f: impl FnOnce(*mut T) -> R,
+) -> Result<R, HostError> {
+    let memory = ctx.memory_view();
+    let src = memory.bytes(ptr.offset(), mem::size_of::<T>() as u32)?;
+    let mut value = MaybeUninit::<T>::uninit();
+    unsafe { ptr::copy_nonoverlapping(src.as_ptr(), value.as_mut_ptr().cast::<u8>(), src.len()) };
+    Ok(f(value.as_mut_ptr()))
+}
+
+pub fn link(linker: &mut Linker<Data>) -> anyhow::Result<()> {
+    linker.func_wrap(
         "hydrozoa",
         "panic",
-        |mut ctx, string: i32| -> Result<(), Trap> {
-            let string = get_cstring(&mut ctx, string);
+        |mut ctx, string: i32| -> Result<(), HostError> {
+            let string = get_cstring(&mut ctx, string)?;
             let msg = string.to_string_lossy();
 
             let mut display = unsafe { Display::new() };
@@ -85,19 +254,17 @@ pub fn link(store: &mut Store<Data>, instance: &mut Instance<Data>) -> anyhow::R
         },
     )?;
 
-    instance.link_closure(
-        &mut *store,
+    linker.func_wrap(
         "hydrozoa",
         "getByteArrayPointer",
         |mut ctx, address: i32| {
+            // a guest pointer, which the bindings taking pointers resolve against guest memory
             let teavm = ctx.data().teavm.clone().unwrap();
-            let array_ptr = (teavm.byte_array_data)(ctx.as_context_mut(), address).unwrap();
-            let memory = ctx.memory_mut();
-            Ok(memory.as_mut_ptr().wrapping_offset(array_ptr as isize) as i32)
+            (teavm.byte_array_data)(ctx.as_context_mut(), address).map_err(HostError::new)
         },
     )?;
 
-    link!(instance, store, mod "vex" {
+    link!(linker, mod "vex" {
         // System
         fn vexSystemTimeGet() -> u32;
         fn vexSystemExitRequest();
@@ -139,7 +306,7 @@ pub fn link(store: &mut Store<Data>, instance: &mut Instance<Data>) -> anyhow::R
         // fn vexImagePngRead(ibuf: *const u8, oBuf: *mut v5_image, maxw: u32, maxh: u32, ibuflen: u32) -> u32;
     });
 
-    printf_style!(instance, store, mod "vex" {
+    printf_style!(linker, mod "vex" {
         fn vexDisplayPrintf(xpos: i32, ypos: i32, bOpaque: i32, @printf@);
         fn vexDisplayString(nLineNumber: i32, @printf@);
         fn vexDisplayStringAt(xpos: i32, ypos: i32, @printf@);
@@ -150,7 +317,7 @@ pub fn link(store: &mut Store<Data>, instance: &mut Instance<Data>) -> anyhow::R
         fn vexDisplayBigCenteredString(nLineNumber: i32, @printf@);
     });
 
-    link!(instance, store, mod "vex" {
+    link!(linker, mod "vex" {
     // AbsEnc
     fn vexDeviceAbsEncReset(device: u32);
     fn vexDeviceAbsEncPositionSet(device: u32, position: i32);
@@ -166,38 +333,25 @@ pub fn link(store: &mut Store<Data>, instance: &mut Instance<Data>) -> anyhow::R
     fn vexDeviceAdiPortConfigGet(device: u32, port: u32) -> u32, in .0;
     fn vexDeviceAdiValueSet(device: u32, port: u32, value: i32);
     fn vexDeviceAdiValueGet(device: u32, port: u32) -> i32;
-    fn vexDeviceAdiAddrLedSet(device: u32, port: u32, pData: u32, nOffset: u32, nLength: u32, options: u32);
     fn vexDeviceBumperGet(device: u32) -> u32, in .0;
     fn vexDeviceGyroReset(device: u32);
     fn vexDeviceGyroHeadingGet(device: u32) -> c_double;
     fn vexDeviceGyroDegreesGet(device: u32) -> c_double;
     fn vexDeviceSonarValueGet(device: u32) -> i32;
     // AiVision
-    fn vexDeviceAiVisionClassNameGet(device: u32, id: i32, pName: u32) -> i32;
-    fn vexDeviceAiVisionCodeGet(device: u32, id: u32, pCode: u32) -> bool;
-    fn vexDeviceAiVisionCodeSet(device: u32, pCode: u32);
-    fn vexDeviceAiVisionColorGet(device: u32, id: u32, pColor: u32) -> bool;
-    fn vexDeviceAiVisionColorSet(device: u32, pColor: u32);
     fn vexDeviceAiVisionModeGet(device: u32) -> u32;
     fn vexDeviceAiVisionModeSet(device: u32, mode: u32);
     fn vexDeviceAiVisionObjectCountGet(device: u32) -> i32;
-    fn vexDeviceAiVisionObjectGet(device: u32, indexObj: u32, pObject: u32) -> i32;
     fn vexDeviceAiVisionSensorSet(device: u32, brightness: c_double, contrast: c_double);
     fn vexDeviceAiVisionStatusGet(device: u32) -> u32;
     fn vexDeviceAiVisionTemperatureGet(device: u32) -> c_double;
+    // vexDeviceAiVisionClassNameGet isn't linked, as the SDK doesn't say how long a name it writes
     // Arm
-    fn vexDeviceArmMoveTipCommandLinearAdv(device: u32, position: u32, j6_rotation: c_double, j6_velocity: u32, relative: bool);
-    fn vexDeviceArmMoveTipCommandJointAdv(device: u32, position: u32, j6_rotation: c_double, j6_velocity: u32, relative: bool);
-    fn vexDeviceArmTipPositionGetAdv(device: u32, position: u32);
     fn vexDeviceArmPoseSet(device: u32, pose: u32, velocity: u32);
     fn vexDeviceArmMoveTipCommandLinear(device: u32, x: i32, y: i32, z: i32, pose: u32, velocity: u32, rotation: c_double, rot_velocity: u32, relative: bool);
     fn vexDeviceArmMoveTipCommandJoint(device: u32, x: i32, y: i32, z: i32, pose: u32, velocity: u32, rotation: c_double, rot_velocity: u32, relative: bool);
-    fn vexDeviceArmMoveJointsCommand(device: u32, positions: u32, velocities: u32, j6_rotation: c_double, j6_velocity: u32, j7_volts: c_double, j7_timeout: u32, j7_i_limit: u32, relative: bool);
-    fn vexDeviceArmSpinJoints(device: u32, velocities: u32);
-    fn vexDeviceArmSetJointPositions(device: u32, new_positions: u32);
     fn vexDeviceArmPickUpCommand(device: u32);
     fn vexDeviceArmDropCommand(device: u32);
-    fn vexDeviceArmMoveVoltsCommand(device: u32, voltages: u32);
     fn vexDeviceArmFullStop(device: u32, brakeMode: u32);
     fn vexDeviceArmEnableProfiler(device: u32, enable: u32);
     fn vexDeviceArmProfilerVelocitySet(device: u32, linear_velocity: u32, joint_velocity: u32);
@@ -205,18 +359,16 @@ pub fn link(store: &mut Store<Data>, instance: &mut Instance<Data>) -> anyhow::R
     fn vexDeviceArmForceZeroCommand(device: u32);
     fn vexDeviceArmClearZeroValues(device: u32);
     fn vexDeviceArmBootload(device: u32);
-    fn vexDeviceArmTipPositionGet(device: u32, x: u32, y: u32, z: u32);
-    fn vexDeviceArmJointInfoGet(device: u32, positions: u32, velocities: u32, currents: u32);
     fn vexDeviceArmJ6PositionGet(device: u32) -> c_double;
     fn vexDeviceArmBatteryGet(device: u32) -> i32;
     fn vexDeviceArmServoFlagsGet(device: u32, servoID: u32) -> i32;
     fn vexDeviceArmStatusGet(device: u32) -> u32;
     fn vexDeviceArmDebugGet(device: u32, id: i32) -> u32;
-    fn vexDeviceArmJointErrorsGet(device: u32, errors: u32);
     fn vexDeviceArmJ6PositionSet(device: u32, position: u32);
-    fn vexDeviceArmStopJointsCommand(device: u32, brakeModes: u32);
     fn vexDeviceArmReboot(device: u32);
     fn vexDeviceArmTipOffsetSet(device: u32, x: i32, y: i32, z: i32);
+    // the functions taking pointers to tip positions or to one value per joint aren't linked, as
+    // the SDK doesn't say how many values they read or write
     // Battery
     fn vexBatteryVoltageGet() -> i32;
     fn vexBatteryCurrentGet() -> i32;
@@ -228,13 +380,11 @@ pub fn link(store: &mut Store<Data>, instance: &mut Instance<Data>) -> anyhow::R
     // Controller
     fn vexControllerGet(id: u32 as V5_ControllerId, index: u32 as V5_ControllerIndex) -> i32;
     fn vexControllerConnectionStatusGet(id: u32 as V5_ControllerId) -> u32, in .0;
-    fn vexControllerTextSet(id: u32, line: u32, col: u32, buf: u32) -> u32;
     // Device
     fn vexDevicesGetNumber() -> u32;
     fn vexDevicesGetNumberByType(device_type: u32 as V5_DeviceType) -> u32;
     fn vexDevicesGet() -> u32;
     fn vexDeviceGetByIndex(index: u32) -> u32;
-    fn vexDeviceGetStatus(devices: u32) -> i32;
     fn vexDeviceGetTimestamp(device: u32) -> u32;
     fn vexDeviceGenericValueGet(device: u32) -> c_double;
     fn vexDeviceButtonStateGet() -> i32;
@@ -246,50 +396,29 @@ pub fn link(store: &mut Store<Data>, instance: &mut Instance<Data>) -> anyhow::R
     fn vexDeviceDistanceObjectVelocityGet(device: u32) -> c_double;
     // File
     fn vexFileMountSD() -> u32, in .0;
-    fn vexFileDirectoryGet(path: u32, buffer: u32, len: u32) -> u32, in .0;
-    fn vexFileOpen(filename: u32, mode: u32) -> u32;
-    fn vexFileOpenWrite(filename: u32) -> u32;
-    fn vexFileOpenCreate(filename: u32) -> u32;
-    fn vexFileClose(fdp: u32);
-    fn vexFileWrite(buf: u32, size: u32, nItems: u32, fdp: u32) -> i32;
-    fn vexFileSize(fdp: u32) -> i32;
-    fn vexFileSeek(fdp: u32, offset: u32, whence: i32) -> u32, in .0;
-    fn vexFileRead(buf: u32, size: u32, nItems: u32, fdp: u32) -> i32;
     fn vexFileDriveStatus(drive: u32) -> bool;
-    fn vexFileTell(fdp: u32) -> i32;
-    fn vexFileSync(fdp: u32);
-    fn vexFileStatus(filename: u32) -> u32;
     // GenericRadio
     fn vexDeviceGenericRadioWriteFree(device: u32) -> i32;
-    fn vexDeviceGenericRadioTransmit(device: u32, data: u32, size: u32) -> i32;
     fn vexDeviceGenericRadioReceiveAvail(device: u32) -> i32;
-    fn vexDeviceGenericRadioReceive(device: u32, data: u32, size: u32) -> i32;
     fn vexDeviceGenericRadioLinkStatus(device: u32) -> bool;
     // GenericSerial
     fn vexDeviceGenericSerialEnable(device: u32, options: i32);
     fn vexDeviceGenericSerialBaudrate(device: u32, baudrate: i32);
     fn vexDeviceGenericSerialWriteChar(device: u32, c: u32) -> i32;
     fn vexDeviceGenericSerialWriteFree(device: u32) -> i32;
-    fn vexDeviceGenericSerialTransmit(device: u32, buffer: u32, length: i32) -> i32;
     fn vexDeviceGenericSerialReadChar(device: u32) -> i32;
     fn vexDeviceGenericSerialPeekChar(device: u32) -> i32;
     fn vexDeviceGenericSerialReceiveAvail(device: u32) -> i32;
-    fn vexDeviceGenericSerialReceive(device: u32, buffer: u32, length: i32) -> i32;
     fn vexDeviceGenericSerialFlush(device: u32);
     // Gps
     fn vexDeviceGpsReset(device: u32);
     fn vexDeviceGpsHeadingGet(device: u32) -> c_double;
     fn vexDeviceGpsDegreesGet(device: u32) -> c_double;
-    fn vexDeviceGpsQuaternionGet(device: u32, data: u32);
-    fn vexDeviceGpsAttitudeGet(device: u32, data: u32, bRaw: bool);
-    fn vexDeviceGpsRawGyroGet(device: u32, data: u32);
-    fn vexDeviceGpsRawAccelGet(device: u32, data: u32);
     fn vexDeviceGpsStatusGet(device: u32) -> u32;
     fn vexDeviceGpsModeSet(device: u32, mode: u32);
     fn vexDeviceGpsModeGet(device: u32) -> u32;
     fn vexDeviceGpsDataRateSet(device: u32, rate: u32);
     fn vexDeviceGpsOriginSet(device: u32, ox: c_double, oy: c_double);
-    fn vexDeviceGpsOriginGet(device: u32, ox: u32, oy: u32);
     fn vexDeviceGpsRotationSet(device: u32, value: c_double);
     fn vexDeviceGpsRotationGet(device: u32) -> c_double;
     fn vexDeviceGpsInitialPositionSet(device: u32, initial_x: c_double, initial_y: c_double, initial_rotation: c_double);
@@ -298,10 +427,6 @@ pub fn link(store: &mut Store<Data>, instance: &mut Instance<Data>) -> anyhow::R
     fn vexDeviceImuReset(device: u32);
     fn vexDeviceImuHeadingGet(device: u32) -> c_double;
     fn vexDeviceImuDegreesGet(device: u32) -> c_double;
-    fn vexDeviceImuQuaternionGet(device: u32, data: u32);
-    fn vexDeviceImuAttitudeGet(device: u32, data: u32);
-    fn vexDeviceImuRawGyroGet(device: u32, data: u32);
-    fn vexDeviceImuRawAccelGet(device: u32, data: u32);
     fn vexDeviceImuStatusGet(device: u32) -> u32;
     fn vexDeviceImuModeSet(device: u32, mode: u32);
     fn vexDeviceImuModeGet(device: u32) -> u32;
@@ -327,7 +452,39 @@ pub fn link(store: &mut Store<Data>, instance: &mut Instance<Data>) -> anyhow::R
     fn vexDeviceMagnetTemperatureGet(device: u32) -> c_double;
     fn vexDeviceMagnetCurrentGet(device: u32) -> c_double;
     fn vexDeviceMagnetStatusGet(device: u32) -> u32;
-    // Motor
+    // Optical
+    fn vexDeviceOpticalHueGet(device: u32) -> c_double;
+    fn vexDeviceOpticalSatGet(device: u32) -> c_double;
+    fn vexDeviceOpticalBrightnessGet(device: u32) -> c_double;
+    fn vexDeviceOpticalProximityGet(device: u32) -> i32;
+    fn vexDeviceOpticalLedPwmSet(device: u32, value: i32);
+    fn vexDeviceOpticalLedPwmGet(device: u32) -> i32;
+    fn vexDeviceOpticalStatusGet(device: u32) -> u32;
+    fn vexDeviceOpticalModeSet(device: u32, mode: u32);
+    fn vexDeviceOpticalModeGet(device: u32) -> u32;
+    fn vexDeviceOpticalGestureEnable(device: u32);
+    fn vexDeviceOpticalGestureDisable(device: u32);
+    fn vexDeviceOpticalProximityThreshold(device: u32, value: i32);
+    fn vexDeviceOpticalIntegrationTimeSet(device: u32, timeMs: c_double);
+    fn vexDeviceOpticalIntegrationTimeGet(device: u32) -> c_double;
+    // Pneumatic
+    fn vexDevicePneumaticCompressorSet(device: u32, bState: bool);
+    fn vexDevicePneumaticCylinderPwmSet(device: u32, id: u32, bState: bool, pwm: u32);
+    fn vexDevicePneumaticCylinderSet(device: u32, id: u32, bState: bool);
+    fn vexDevicePneumaticPwmGet(device: u32) -> u32;
+    fn vexDevicePneumaticPwmSet(device: u32, pwm: u32);
+    fn vexDevicePneumaticStatusGet(device: u32) -> u32;
+    // Range
+    fn vexDeviceRangeValueGet(device: u32) -> i32;
+    // Serial
+    fn vexSerialWriteChar(channel: u32, c: u32) -> i32;
+    fn vexSerialReadChar(channel: u32) -> i32;
+    fn vexSerialPeekChar(channel: u32) -> i32;
+    fn vexSerialWriteFree(channel: u32) -> i32;
+    // Touch
+    });
+
+    link_device!(linker, mod "vex", V5_DeviceType::kDeviceTypeMotorSensor as "motor" {
     fn vexDeviceMotorVelocitySet(device: u32, velocity: i32);
     fn vexDeviceMotorVelocityGet(device: u32) -> i32;
     fn vexDeviceMotorActualVelocityGet(device: u32) -> c_double;
@@ -355,7 +512,6 @@ pub fn link(store: &mut Store<Data>, instance: &mut Instance<Data>) -> anyhow::R
     fn vexDeviceMotorBrakeModeGet(device: u32) -> u32, in .0;
     fn vexDeviceMotorPositionSet(device: u32, position: c_double);
     fn vexDeviceMotorPositionGet(device: u32) -> c_double;
-    fn vexDeviceMotorPositionRawGet(device: u32, timestamp: u32) -> i32;
     fn vexDeviceMotorPositionReset(device: u32);
     fn vexDeviceMotorTargetGet(device: u32) -> c_double;
     fn vexDeviceMotorServoTargetSet(device: u32, position: c_double);
@@ -370,97 +526,468 @@ pub fn link(store: &mut Store<Data>, instance: &mut Instance<Data>) -> anyhow::R
     fn vexDeviceMotorVoltageLimitSet(device: u32, limit: i32);
     fn vexDeviceMotorVoltageLimitGet(device: u32) -> i32;
     fn vexDeviceMotorVelocityUpdate(device: u32, velocity: i32);
-    fn vexDeviceMotorPositionPidSet(device: u32, pid: u32);
-    fn vexDeviceMotorVelocityPidSet(device: u32, pid: u32);
     fn vexDeviceMotorExternalProfileSet(device: u32, position: c_double, velocity: i32);
-    // Optical
-    fn vexDeviceOpticalHueGet(device: u32) -> c_double;
-    fn vexDeviceOpticalSatGet(device: u32) -> c_double;
-    fn vexDeviceOpticalBrightnessGet(device: u32) -> c_double;
-    fn vexDeviceOpticalProximityGet(device: u32) -> i32;
-    fn vexDeviceOpticalRgbGet(device: u32, data: u32);
-    fn vexDeviceOpticalLedPwmSet(device: u32, value: i32);
-    fn vexDeviceOpticalLedPwmGet(device: u32) -> i32;
-    fn vexDeviceOpticalStatusGet(device: u32) -> u32;
-    fn vexDeviceOpticalRawGet(device: u32, data: u32);
-    fn vexDeviceOpticalModeSet(device: u32, mode: u32);
-    fn vexDeviceOpticalModeGet(device: u32) -> u32;
-    fn vexDeviceOpticalGestureGet(device: u32, pData: u32) -> u32;
-    fn vexDeviceOpticalGestureEnable(device: u32);
-    fn vexDeviceOpticalGestureDisable(device: u32);
-    fn vexDeviceOpticalProximityThreshold(device: u32, value: i32);
-    fn vexDeviceOpticalIntegrationTimeSet(device: u32, timeMs: c_double);
-    fn vexDeviceOpticalIntegrationTimeGet(device: u32) -> c_double;
-    // Pneumatic
-    fn vexDevicePneumaticActuationStatusGet(device: u32, ac1: u32, ac2: u32, ac3: u32, ac4: u32) -> u32;
-    fn vexDevicePneumaticCompressorSet(device: u32, bState: bool);
-    fn vexDevicePneumaticCtrlSet(device: u32, pCtrl: u32);
-    fn vexDevicePneumaticCylinderPwmSet(device: u32, id: u32, bState: bool, pwm: u32);
-    fn vexDevicePneumaticCylinderSet(device: u32, id: u32, bState: bool);
-    fn vexDevicePneumaticPwmGet(device: u32) -> u32;
-    fn vexDevicePneumaticPwmSet(device: u32, pwm: u32);
-    fn vexDevicePneumaticStatusGet(device: u32) -> u32;
-    // Range
-    fn vexDeviceRangeValueGet(device: u32) -> i32;
-    // Serial
-    fn vexSerialWriteChar(channel: u32, c: u32) -> i32;
-    fn vexSerialWriteBuffer(channel: u32, data: u32, data_len: u32) -> i32;
-    fn vexSerialReadChar(channel: u32) -> i32;
-    fn vexSerialPeekChar(channel: u32) -> i32;
-    fn vexSerialWriteFree(channel: u32) -> i32;
-    // Touch
-    fn vexTouchDataGet(status: u32);
     });
 
-    // instance.link_closure(
-    //     &mut *store,
-    //     "vex",
-    //     "vexDeviceGetStatus",
-    //     |mut ctx, devices| {
-    //         let teavm = ctx.data().teavm.clone().unwrap();
-    //         let array_ptr = (teavm.byte_array_data)(ctx.as_context_mut(), devices).unwrap();
-    //         let memory = ctx.memory_mut();
-    //         let devices = &mut memory
-    //             [array_ptr as usize..(array_ptr as usize + vex_sdk::V5_MAX_DEVICE_PORTS)];
-
-    //         let devices = unsafe {
-    //             // SAFETY: V5_DeviceType is a repr(transparent) struct holding a u8
-    // core::mem::transmute::<*mut u8, *mut V5_DeviceType>(devices.as_mut_ptr())
-    //         };
-    //         Ok(unsafe { vex_sdk::vexDeviceGetStatus(devices) })
-    //     },
-    // )?;
-
-    instance.link_closure(
-        &mut *store,
+    link_struct_getters!(linker, mod "vex" {
+        fn vexDeviceGpsQuaternionGet(device: u32, data: WasmPtr<V5_DeviceGpsQuaternion>) as [f64; 4];
+        fn vexDeviceGpsRawGyroGet(device: u32, data: WasmPtr<V5_DeviceGpsRaw>) as [f64; 4];
+        fn vexDeviceGpsRawAccelGet(device: u32, data: WasmPtr<V5_DeviceGpsRaw>) as [f64; 4];
+        fn vexDeviceImuQuaternionGet(device: u32, data: WasmPtr<V5_DeviceImuQuaternion>) as [f64; 4];
+        fn vexDeviceImuAttitudeGet(device: u32, data: WasmPtr<V5_DeviceImuAttitude>) as [f64; 3];
+        fn vexDeviceImuRawGyroGet(device: u32, data: WasmPtr<V5_DeviceImuRaw>) as [f64; 4];
+        fn vexDeviceImuRawAccelGet(device: u32, data: WasmPtr<V5_DeviceImuRaw>) as [f64; 4];
+    });
+
+    link_pointer_args(linker)?;
+
+    // the guest passes a Java `byte[]` to fill in with the type of the device on each port
+    linker.func_wrap("vex", "vexDeviceGetStatus", |mut ctx, devices: i32| {
+        let teavm = ctx
+            .data()
+            .teavm
+            .clone()
+            .ok_or_else(|| HostError::new("teavm has not been initialized"))?;
+        let len = (teavm.array_length)(ctx.as_context_mut(), devices).map_err(HostError::new)?;
+        if (len as usize) < V5_MAX_DEVICE_PORTS {
+            return Err(HostError::new(format!(
+                "{len} bytes can't hold the devices of {V5_MAX_DEVICE_PORTS} ports"
+            )));
+        }
+        let data =
+            (teavm.byte_array_data)(ctx.as_context_mut(), devices).map_err(HostError::new)?;
+
+        let mut types = [V5_DeviceType::kDeviceTypeNoSensor; V5_MAX_DEVICE_PORTS];
+        let count = unsafe { vex_sdk::vexDeviceGetStatus(types.as_mut_ptr()) };
+        // SAFETY: V5_DeviceType is a repr(transparent) struct holding a u8
+        let types: [u8; V5_MAX_DEVICE_PORTS] = unsafe { mem::transmute(types) };
+        ctx.memory_view().write_slice(data as u32, &types)?;
+        Ok(count)
+    })?;
+
+    linker.func_wrap(
         "vex",
-        "vexDisplayStringWidthGet",
-        |mut ctx, string: i32| {
-            let string = get_cstring(&mut ctx, string);
-            Ok(unsafe { vex_sdk::vexDisplayStringWidthGet(string.as_ptr()) })
+        "vexDeviceGenericRadioTransmit",
+        |mut ctx, (device, data): (u32, WasmSlice<u8>)| {
+            let memory = ctx.memory_view();
+            let data = data.bytes(&memory)?;
+            Ok(unsafe {
+                vex_sdk::vexDeviceGenericRadioTransmit(device as _, data.as_ptr(), data.len() as _)
+            })
+        },
+    )?;
+
+    linker.func_wrap(
+        "vex",
+        "vexDeviceGenericRadioReceive",
+        |mut ctx, (device, data): (u32, WasmSlice<u8>)| {
+            let mut memory = ctx.memory_view();
+            let data = data.bytes_mut(&mut memory)?;
+            Ok(unsafe {
+                vex_sdk::vexDeviceGenericRadioReceive(
+                    device as _,
+                    data.as_mut_ptr(),
+                    data.len() as _,
+                )
+            })
         },
     )?;
 
-    instance.link_closure(
-        &mut *store,
+    linker.func_wrap(
+        "vex",
+        "vexSerialWriteBuffer",
+        |mut ctx, (channel, data): (u32, WasmSlice<u8>)| {
+            let memory = ctx.memory_view();
+            let data = data.bytes(&memory)?;
+            Ok(unsafe { vex_sdk::vexSerialWriteBuffer(channel, data.as_ptr(), data.len() as _) })
+        },
+    )?;
+
+    linker.func_wrap("vex", "vexDisplayStringWidthGet", |mut ctx, string: i32| {
+        let string = get_cstring(&mut ctx, string)?;
+        Ok(unsafe { vex_sdk::vexDisplayStringWidthGet(string.as_ptr()) })
+    })?;
+
+    linker.func_wrap(
         "vex",
         "vexDisplayStringHeightGet",
         |mut ctx, string: i32| {
-            let string = get_cstring(&mut ctx, string);
+            let string = get_cstring(&mut ctx, string)?;
             Ok(unsafe { vex_sdk::vexDisplayStringHeightGet(string.as_ptr()) })
         },
     )?;
 
-    instance.link_closure(
-        &mut *store,
+    linker.func_wrap("vex", "vexDisplayFontNamedSet", |mut ctx, string: i32| {
+        let string = get_cstring(&mut ctx, string)?;
+        unsafe { vex_sdk::vexDisplayFontNamedSet(string.as_ptr()) };
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+/// Links the SDK functions that take pointers, resolving them against guest memory.
+fn link_pointer_args(linker: &mut Linker<Data>) -> anyhow::Result<()> {
+    // Adi
+    linker.func_wrap(
         "vex",
-        "vexDisplayFontNamedSet",
-        |mut ctx, string: i32| {
-            let string = get_cstring(&mut ctx, string);
-            unsafe { vex_sdk::vexDisplayFontNamedSet(string.as_ptr()) };
+        "vexDeviceAdiAddrLedSet",
+        |mut ctx,
+         (device, port, data, offset, len, options): (
+            u32,
+            u32,
+            WasmPtr<u32>,
+            u32,
+            u32,
+            u32,
+        )| {
+            // read along with the leading values, as the offset may index into them
+            let count = offset
+                .checked_add(len)
+                .ok_or(Trap::OutOfBoundsMemoryAccess)?;
+            let mut data = ctx.memory_view().read_slice::<u32>(data.offset(), count)?;
+            unsafe {
+                vex_sdk::vexDeviceAdiAddrLedSet(
+                    device as _,
+                    port as _,
+                    data.as_mut_ptr(),
+                    offset as _,
+                    len as _,
+                    options as _,
+                )
+            };
             Ok(())
         },
     )?;
 
+    // AiVision
+    linker.func_wrap(
+        "vex",
+        "vexDeviceAiVisionCodeGet",
+        |mut ctx, (device, id, code): (u32, u32, WasmPtr<V5_DeviceAiVisionCode>)| {
+            sdk_out(&mut ctx, code, |code| unsafe {
+                vex_sdk::vexDeviceAiVisionCodeGet(device as _, id as _, code)
+            })
+        },
+    )?;
+    linker.func_wrap(
+        "vex",
+        "vexDeviceAiVisionCodeSet",
+        |mut ctx, (device, code): (u32, WasmPtr<V5_DeviceAiVisionCode>)| {
+            sdk_in(&mut ctx, code, |code| unsafe {
+                vex_sdk::vexDeviceAiVisionCodeSet(device as _, code)
+            })
+        },
+    )?;
+    linker.func_wrap(
+        "vex",
+        "vexDeviceAiVisionColorGet",
+        |mut ctx, (device, id, color): (u32, u32, WasmPtr<V5_DeviceAiVisionColor>)| {
+            sdk_out(&mut ctx, color, |color| unsafe {
+                vex_sdk::vexDeviceAiVisionColorGet(device as _, id as _, color)
+            })
+        },
+    )?;
+    linker.func_wrap(
+        "vex",
+        "vexDeviceAiVisionColorSet",
+        |mut ctx, (device, color): (u32, WasmPtr<V5_DeviceAiVisionColor>)| {
+            sdk_in(&mut ctx, color, |color| unsafe {
+                vex_sdk::vexDeviceAiVisionColorSet(device as _, color)
+            })
+        },
+    )?;
+    linker.func_wrap(
+        "vex",
+        "vexDeviceAiVisionObjectGet",
+        |mut ctx, (device, index, object): (u32, u32, WasmPtr<V5_DeviceAiVisionObject>)| {
+            sdk_out(&mut ctx, object, |object| unsafe {
+                vex_sdk::vexDeviceAiVisionObjectGet(device as _, index as _, object)
+            })
+        },
+    )?;
+
+    // Controller
+    linker.func_wrap(
+        "vex",
+        "vexControllerTextSet",
+        |mut ctx, (id, line, col, text): (u32, u32, u32, WasmPtr<u8>)| {
+            let memory = ctx.memory_view();
+            let text = cstr(&memory, text)?;
+            Ok(unsafe {
+                vex_sdk::vexControllerTextSet(id as _, line as _, col as _, text.as_ptr())
+            })
+        },
+    )?;
+
+    // File
+    linker.func_wrap(
+        "vex",
+        "vexFileDirectoryGet",
+        |mut ctx, (path, buffer): (WasmPtr<u8>, WasmSlice<u8>)| {
+            let mut memory = ctx.memory_view();
+            let path = CString::from(cstr(&memory, path)?);
+            let buffer = buffer.bytes_mut(&mut memory)?;
+            let result = unsafe {
+                vex_sdk::vexFileDirectoryGet(
+                    path.as_ptr(),
+                    buffer.as_mut_ptr().cast(),
+                    buffer.len() as u32,
+                )
+            };
+            Ok(result.0 as u32)
+        },
+    )?;
+    linker.func_wrap(
+        "vex",
+        "vexFileOpen",
+        |mut ctx, (filename, mode): (WasmPtr<u8>, WasmPtr<u8>)| {
+            let memory = ctx.memory_view();
+            let fd = unsafe {
+                vex_sdk::vexFileOpen(
+                    cstr(&memory, filename)?.as_ptr(),
+                    cstr(&memory, mode)?.as_ptr(),
+                )
+            };
+            Ok(ctx.data_mut().files.insert(fd))
+        },
+    )?;
+    linker.func_wrap(
+        "vex",
+        "vexFileOpenWrite",
+        |mut ctx, filename: WasmPtr<u8>| {
+            let memory = ctx.memory_view();
+            let fd = unsafe { vex_sdk::vexFileOpenWrite(cstr(&memory, filename)?.as_ptr()) };
+            Ok(ctx.data_mut().files.insert(fd))
+        },
+    )?;
+    linker.func_wrap(
+        "vex",
+        "vexFileOpenCreate",
+        |mut ctx, filename: WasmPtr<u8>| {
+            let memory = ctx.memory_view();
+            let fd = unsafe { vex_sdk::vexFileOpenCreate(cstr(&memory, filename)?.as_ptr()) };
+            Ok(ctx.data_mut().files.insert(fd))
+        },
+    )?;
+    linker.func_wrap("vex", "vexFileClose", |mut ctx, fd: u32| {
+        let fd = ctx.data_mut().files.remove(fd)?;
+        unsafe { vex_sdk::vexFileClose(fd) };
+        Ok(())
+    })?;
+    linker.func_wrap(
+        "vex",
+        "vexFileWrite",
+        |mut ctx, (buf, size, count, fd): (WasmPtr<u8>, u32, u32, u32)| {
+            let fd = ctx.data().files.get(fd)?;
+            let len = size
+                .checked_mul(count)
+                .ok_or(Trap::OutOfBoundsMemoryAccess)?;
+            let memory = ctx.memory_view();
+            let buf = memory.bytes(buf.offset(), len)?;
+            Ok(unsafe { vex_sdk::vexFileWrite(buf.as_ptr().cast_mut().cast(), size, count, fd) })
+        },
+    )?;
+    linker.func_wrap(
+        "vex",
+        "vexFileRead",
+        |mut ctx, (buf, size, count, fd): (WasmPtr<u8>, u32, u32, u32)| {
+            let fd = ctx.data().files.get(fd)?;
+            let len = size
+                .checked_mul(count)
+                .ok_or(Trap::OutOfBoundsMemoryAccess)?;
+            let mut memory = ctx.memory_view();
+            let buf = memory.bytes_mut(buf.offset(), len)?;
+            Ok(unsafe { vex_sdk::vexFileRead(buf.as_mut_ptr().cast(), size, count, fd) })
+        },
+    )?;
+    linker.func_wrap("vex", "vexFileSize", |ctx, fd: u32| {
+        let fd = ctx.data().files.get(fd)?;
+        Ok(unsafe { vex_sdk::vexFileSize(fd) })
+    })?;
+    linker.func_wrap(
+        "vex",
+        "vexFileSeek",
+        |ctx, (fd, offset, whence): (u32, u32, i32)| {
+            let fd = ctx.data().files.get(fd)?;
+            Ok(unsafe { vex_sdk::vexFileSeek(fd, offset as _, whence as _) }.0 as u32)
+        },
+    )?;
+    linker.func_wrap("vex", "vexFileTell", |ctx, fd: u32| {
+        let fd = ctx.data().files.get(fd)?;
+        Ok(unsafe { vex_sdk::vexFileTell(fd) })
+    })?;
+    linker.func_wrap("vex", "vexFileSync", |ctx, fd: u32| {
+        let fd = ctx.data().files.get(fd)?;
+        unsafe { vex_sdk::vexFileSync(fd) };
+        Ok(())
+    })?;
+    linker.func_wrap("vex", "vexFileStatus", |mut ctx, filename: WasmPtr<u8>| {
+        let memory = ctx.memory_view();
+        Ok(unsafe { vex_sdk::vexFileStatus(cstr(&memory, filename)?.as_ptr()) })
+    })?;
+
+    // GenericSerial
+    linker.func_wrap(
+        "vex",
+        "vexDeviceGenericSerialTransmit",
+        |mut ctx, (device, data): (u32, WasmSlice<u8>)| {
+            let memory = ctx.memory_view();
+            let data = data.bytes(&memory)?;
+            Ok(unsafe {
+                vex_sdk::vexDeviceGenericSerialTransmit(
+                    device as _,
+                    data.as_ptr().cast_mut(),
+                    data.len() as _,
+                )
+            })
+        },
+    )?;
+    linker.func_wrap(
+        "vex",
+        "vexDeviceGenericSerialReceive",
+        |mut ctx, (device, data): (u32, WasmSlice<u8>)| {
+            let mut memory = ctx.memory_view();
+            let data = data.bytes_mut(&mut memory)?;
+            Ok(unsafe {
+                vex_sdk::vexDeviceGenericSerialReceive(
+                    device as _,
+                    data.as_mut_ptr(),
+                    data.len() as _,
+                )
+            })
+        },
+    )?;
+
+    // Gps
+    linker.func_wrap(
+        "vex",
+        "vexDeviceGpsAttitudeGet",
+        |mut ctx, (device, data, raw): (u32, WasmPtr<V5_DeviceGpsAttitude>, bool)| {
+            sdk_out(&mut ctx, data, |data| unsafe {
+                vex_sdk::vexDeviceGpsAttitudeGet(device as _, data, raw)
+            })
+        },
+    )?;
+    linker.func_wrap(
+        "vex",
+        "vexDeviceGpsOriginGet",
+        |mut ctx, (device, x, y): (u32, WasmPtr<f64>, WasmPtr<f64>)| {
+            let (mut origin_x, mut origin_y) = (0.0, 0.0);
+            unsafe { vex_sdk::vexDeviceGpsOriginGet(device as _, &mut origin_x, &mut origin_y) };
+            let mut memory = ctx.memory_view();
+            memory.write(x.offset(), origin_x)?;
+            memory.write(y.offset(), origin_y)?;
+            Ok(())
+        },
+    )?;
+
+    // Optical
+    linker.func_wrap(
+        "vex",
+        "vexDeviceOpticalRgbGet",
+        |mut ctx, (device, data): (u32, WasmPtr<V5_DeviceOpticalRgb>)| {
+            sdk_out(&mut ctx, data, |data| unsafe {
+                vex_sdk::vexDeviceOpticalRgbGet(device as _, data)
+            })
+        },
+    )?;
+    linker.func_wrap(
+        "vex",
+        "vexDeviceOpticalRawGet",
+        |mut ctx, (device, data): (u32, WasmPtr<V5_DeviceOpticalRaw>)| {
+            sdk_out(&mut ctx, data, |data| unsafe {
+                vex_sdk::vexDeviceOpticalRawGet(device as _, data)
+            })
+        },
+    )?;
+    linker.func_wrap(
+        "vex",
+        "vexDeviceOpticalGestureGet",
+        |mut ctx, (device, data): (u32, WasmPtr<V5_DeviceOpticalGesture>)| {
+            sdk_out(&mut ctx, data, |data| unsafe {
+                vex_sdk::vexDeviceOpticalGestureGet(device as _, data)
+            })
+        },
+    )?;
+
+    // Pneumatic
+    linker.func_wrap(
+        "vex",
+        "vexDevicePneumaticActuationStatusGet",
+        |mut ctx,
+         (device, ac1, ac2, ac3, ac4): (
+            u32,
+            WasmPtr<u16>,
+            WasmPtr<u16>,
+            WasmPtr<u16>,
+            WasmPtr<u16>,
+        )| {
+            let mut counts = [0u16; 4];
+            let [c1, c2, c3, c4] = &mut counts;
+            let status = unsafe {
+                vex_sdk::vexDevicePneumaticActuationStatusGet(device as _, c1, c2, c3, c4)
+            };
+            let mut memory = ctx.memory_view();
+            for (ptr, count) in [ac1, ac2, ac3, ac4].into_iter().zip(counts) {
+                memory.write(ptr.offset(), count)?;
+            }
+            Ok(status)
+        },
+    )?;
+    linker.func_wrap(
+        "vex",
+        "vexDevicePneumaticCtrlSet",
+        |mut ctx, (device, ctrl): (u32, WasmPtr<V5_DevicePneumaticCtrl>)| {
+            sdk_in(&mut ctx, ctrl, |ctrl| unsafe {
+                vex_sdk::vexDevicePneumaticCtrlSet(device as _, ctrl)
+            })
+        },
+    )?;
+
+    // Touch
+    linker.func_wrap(
+        "vex",
+        "vexTouchDataGet",
+        |mut ctx, status: WasmPtr<V5_TouchStatus>| {
+            sdk_out(&mut ctx, status, |status| unsafe {
+                vex_sdk::vexTouchDataGet(status)
+            })
+        },
+    )?;
+
+    // Motor
+    linker.func_wrap(
+        "vex",
+        "vexDeviceMotorPositionRawGet",
+        |mut ctx, (device, timestamp): (u32, WasmPtr<u32>)| {
+            check_device(device, V5_DeviceType::kDeviceTypeMotorSensor, "motor")?;
+            let mut time = 0;
+            let position = unsafe { vex_sdk::vexDeviceMotorPositionRawGet(device as _, &mut time) };
+            ctx.memory_view().write(timestamp.offset(), time)?;
+            Ok(position)
+        },
+    )?;
+    linker.func_wrap(
+        "vex",
+        "vexDeviceMotorPositionPidSet",
+        |mut ctx, (device, pid): (u32, WasmPtr<V5_DeviceMotorPid>)| {
+            check_device(device, V5_DeviceType::kDeviceTypeMotorSensor, "motor")?;
+            sdk_in(&mut ctx, pid, |pid| unsafe {
+                vex_sdk::vexDeviceMotorPositionPidSet(device as _, pid)
+            })
+        },
+    )?;
+    linker.func_wrap(
+        "vex",
+        "vexDeviceMotorVelocityPidSet",
+        |mut ctx, (device, pid): (u32, WasmPtr<V5_DeviceMotorPid>)| {
+            check_device(device, V5_DeviceType::kDeviceTypeMotorSensor, "motor")?;
+            sdk_in(&mut ctx, pid, |pid| unsafe {
+                vex_sdk::vexDeviceMotorVelocityPidSet(device as _, pid)
+            })
+        },
+    )?;
+
     Ok(())
 }
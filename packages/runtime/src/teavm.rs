@@ -1,7 +1,6 @@
 #![allow(non_snake_case)]
 
-use alloc::{ffi::CString, rc::Rc, string::String};
-use core::str;
+use alloc::{ffi::CString, rc::Rc, string::String, vec::Vec};
 
 use anyhow::{Context, Result};
 use vexide::{
@@ -10,51 +9,31 @@ use vexide::{
     time::Instant,
 };
 use wasm3::{
+    error::HostError,
     store::{AsContextMut, StoreContextMut},
-    Function, Instance, Store,
+    Function, Instance, Linker, Store,
 };
 
 use crate::{platform::flush_serial, Data};
 
-pub fn link_teavm(store: &mut Store<Data>, instance: &mut Instance<Data>) -> Result<()> {
-    let teavm = TeaVM {
-        catch_exception: instance
-            .find_function::<(), i32>(store, "teavm_catchException")
-            .context("finding teavm interop function")?,
-        allocate_string_array: wrap(&mut *store, &mut *instance, "teavm_allocateStringArray")?,
-        object_array_data: wrap(&mut *store, &mut *instance, "teavm_objectArrayData")?,
-        byte_array_data: wrap(&mut *store, &mut *instance, "teavm_byteArrayData")?,
-        allocate_string: wrap(&mut *store, &mut *instance, "teavm_allocateString")?,
-        string_data: wrap(&mut *store, &mut *instance, "teavm_stringData")?,
-        array_length: wrap(&mut *store, &mut *instance, "teavm_arrayLength")?,
-        short_array_data: wrap(&mut *store, &mut *instance, "teavm_shortArrayData")?,
-        char_array_data: wrap(&mut *store, &mut *instance, "teavm_charArrayData")?,
-        int_array_data: wrap(&mut *store, &mut *instance, "teavm_intArrayData")?,
-        long_array_data: wrap(&mut *store, &mut *instance, "teavm_longArrayData")?,
-        float_array_data: wrap(&mut *store, &mut *instance, "teavm_floatArrayData")?,
-        double_array_data: wrap(&mut *store, &mut *instance, "teavm_doubleArrayData")?,
-    };
-    store.data_mut().teavm = Some(teavm);
-
-    instance.link_closure(
-        store,
+pub fn link_teavm(linker: &mut Linker<Data>) -> Result<()> {
+    linker.func_wrap(
         "teavm",
         "putwcharsOut",
         |mut ctx, (chars, count): (u32, u32)| {
-            let mem = ctx.memory_mut();
-            let string = str::from_utf8(&mem[chars as usize..(chars + count) as usize]).unwrap();
+            let memory = ctx.memory_view();
+            let string = memory.read_utf8(chars, count)?;
             print!("{string}");
             Ok(())
         },
     )?;
 
-    instance.link_closure(
-        store,
+    linker.func_wrap(
         "teavm",
         "putwcharsErr",
         |mut ctx, (chars, count): (u32, u32)| {
-            let mem = ctx.memory_mut();
-            let string = str::from_utf8(&mem[chars as usize..(chars + count) as usize]).unwrap();
+            let memory = ctx.memory_view();
+            let string = memory.read_utf8(chars, count)?;
             print!("{string}");
             Ok(())
         },
@@ -62,57 +41,48 @@ pub fn link_teavm(store: &mut Store<Data>, instance: &mut Instance<Data>) -> Res
 
     let epoch = Instant::now();
 
-    instance.link_closure(store, "teavm", "currentTimeMillis", move |_ctx, ()| {
+    linker.func_wrap("teavm", "currentTimeMillis", move |_ctx, ()| {
         let secs = epoch.elapsed().as_secs_f64();
         Ok(secs * 1000.0)
     })?;
 
-    instance.link_closure(store, "teavm", "nanoTime", move |_ctx, ()| {
+    linker.func_wrap("teavm", "nanoTime", move |_ctx, ()| {
         let nanos = epoch.elapsed().as_nanos() as f64 / 1000000.0;
         Ok(nanos)
     })?;
 
-    instance.link_closure(store, "teavmMath", "sin", move |_ctx, a: f64| Ok(a.sin()))?;
-    instance.link_closure(store, "teavmMath", "cos", move |_ctx, a: f64| Ok(a.cos()))?;
-    instance.link_closure(store, "teavmMath", "tan", move |_ctx, a: f64| Ok(a.tan()))?;
-    instance.link_closure(store, "teavmMath", "asin", move |_ctx, a: f64| Ok(a.asin()))?;
-    instance.link_closure(store, "teavmMath", "acos", move |_ctx, a: f64| Ok(a.acos()))?;
-    instance.link_closure(store, "teavmMath", "atan", move |_ctx, a: f64| Ok(a.atan()))?;
-    instance.link_closure(store, "teavmMath", "exp", move |_ctx, a: f64| Ok(a.exp()))?;
-    instance.link_closure(store, "teavmMath", "log", move |_ctx, a: f64| Ok(a.ln()))?;
-    instance.link_closure(store, "teavmMath", "sqrt", move |_ctx, a: f64| Ok(a.sqrt()))?;
-    instance.link_closure(store, "teavmMath", "ceil", move |_ctx, a: f64| Ok(a.ceil()))?;
-    instance.link_closure(store, "teavmMath", "floor", move |_ctx, a: f64| {
-        Ok(a.floor())
+    linker.func_wrap("teavmMath", "sin", move |_ctx, a: f64| Ok(a.sin()))?;
+    linker.func_wrap("teavmMath", "cos", move |_ctx, a: f64| Ok(a.cos()))?;
+    linker.func_wrap("teavmMath", "tan", move |_ctx, a: f64| Ok(a.tan()))?;
+    linker.func_wrap("teavmMath", "asin", move |_ctx, a: f64| Ok(a.asin()))?;
+    linker.func_wrap("teavmMath", "acos", move |_ctx, a: f64| Ok(a.acos()))?;
+    linker.func_wrap("teavmMath", "atan", move |_ctx, a: f64| Ok(a.atan()))?;
+    linker.func_wrap("teavmMath", "exp", move |_ctx, a: f64| Ok(a.exp()))?;
+    linker.func_wrap("teavmMath", "log", move |_ctx, a: f64| Ok(a.ln()))?;
+    linker.func_wrap("teavmMath", "sqrt", move |_ctx, a: f64| Ok(a.sqrt()))?;
+    linker.func_wrap("teavmMath", "ceil", move |_ctx, a: f64| Ok(a.ceil()))?;
+    linker.func_wrap("teavmMath", "floor", move |_ctx, a: f64| Ok(a.floor()))?;
+    linker.func_wrap("teavmMath", "pow", move |_ctx, (x, y): (f64, f64)| {
+        Ok(x.powf(y))
+    })?;
+    linker.func_wrap("teavmMath", "atan2", move |_ctx, (y, x): (f64, f64)| {
+        Ok(y.atan2(x))
     })?;
-    instance.link_closure(
-        store,
-        "teavmMath",
-        "pow",
-        move |_ctx, (x, y): (f64, f64)| Ok(x.powf(y)),
-    )?;
-    instance.link_closure(
-        store,
-        "teavmMath",
-        "atan2",
-        move |_ctx, (y, x): (f64, f64)| Ok(y.atan2(x)),
-    )?;
-
 
-    instance.link_closure(store, "teavm", "logString", move |mut ctx, string: i32| {
-        let string = get_string(&mut ctx, string);
+    linker.func_wrap("teavm", "logString", move |mut ctx, string: i32| {
+        let string = get_string(&mut ctx, string)?;
 
         print!("{string}");
 
         Ok(())
     })?;
 
-    instance.link_closure(store, "teavm", "logInt", move |_ctx, int: i32| {
+    linker.func_wrap("teavm", "logInt", move |_ctx, int: i32| {
         print!("{int}");
         Ok(())
     })?;
 
-    instance.link_closure(store, "teavm", "logOutOfMemory", move |_ctx, ()| {
+    linker.func_wrap("teavm", "logOutOfMemory", move |_ctx, ()| {
         println!("Out of memory");
         Ok(())
     })?;
@@ -120,24 +90,55 @@ pub fn link_teavm(store: &mut Store<Data>, instance: &mut Instance<Data>) -> Res
     Ok(())
 }
 
+/// Looks up the interop functions of the JVM in a module instantiated with the functions of
+/// [`link_teavm`].
+pub fn init_teavm(store: &mut Store<Data>, instance: &mut Instance<Data>) -> Result<()> {
+    let teavm = TeaVM {
+        catch_exception: instance
+            .find_function::<(), i32>(store, "teavm_catchException")
+            .context("finding teavm interop function")?,
+        allocate_string_array: wrap(&mut *store, &mut *instance, "teavm_allocateStringArray")?,
+        object_array_data: wrap(&mut *store, &mut *instance, "teavm_objectArrayData")?,
+        byte_array_data: wrap(&mut *store, &mut *instance, "teavm_byteArrayData")?,
+        allocate_string: wrap(&mut *store, &mut *instance, "teavm_allocateString")?,
+        string_data: wrap(&mut *store, &mut *instance, "teavm_stringData")?,
+        array_length: wrap(&mut *store, &mut *instance, "teavm_arrayLength")?,
+        short_array_data: wrap(&mut *store, &mut *instance, "teavm_shortArrayData")?,
+        char_array_data: wrap(&mut *store, &mut *instance, "teavm_charArrayData")?,
+        int_array_data: wrap(&mut *store, &mut *instance, "teavm_intArrayData")?,
+        long_array_data: wrap(&mut *store, &mut *instance, "teavm_longArrayData")?,
+        float_array_data: wrap(&mut *store, &mut *instance, "teavm_floatArrayData")?,
+        double_array_data: wrap(&mut *store, &mut *instance, "teavm_doubleArrayData")?,
+    };
+    store.data_mut().teavm = Some(teavm);
+
+    Ok(())
+}
+
 /// Copies a UTF16 string out of the JVM's memory and into a Rust [`String`].
-pub fn get_string(ctx: &mut wasm3::CallContext<Data>, string: i32) -> String {
-    let teavm = ctx.data().teavm.clone().unwrap();
-
-    // get pointer & length of the utf16 buffer java stores strings in
-    let array = (teavm.string_data)(ctx.as_context_mut(), string).unwrap();
-    let len = (teavm.array_length)(ctx.as_context_mut(), array).unwrap() as usize;
-    let bytes = len * size_of::<u16>();
-    let array_addr = (teavm.char_array_data)(ctx.as_context_mut(), array).unwrap() as usize;
-
-    let memory = ctx.memory();
-    let array = &memory[array_addr..array_addr + bytes];
-    String::from_utf16_lossy(bytemuck::cast_slice(array))
+pub fn get_string(ctx: &mut wasm3::CallContext<Data>, string: i32) -> Result<String, HostError> {
+    let teavm = ctx
+        .data()
+        .teavm
+        .clone()
+        .ok_or_else(|| HostError::new("teavm has not been initialized"))?;
+
+    // get pointer & length of the utf16 buffer java stores strings in, before looking at memory
+    // as the calls may grow it
+    let array = (teavm.string_data)(ctx.as_context_mut(), string).map_err(HostError::new)?;
+    let len = (teavm.array_length)(ctx.as_context_mut(), array).map_err(HostError::new)? as u32;
+    let array_addr =
+        (teavm.char_array_data)(ctx.as_context_mut(), array).map_err(HostError::new)? as u32;
+
+    Ok(ctx.memory_view().read_utf16(array_addr, len)?)
 }
 
 /// Copies a UTF16 string out of the JVM's memory and into a Rust [`CString`].
-pub fn get_cstring(ctx: &mut wasm3::CallContext<Data>, string: i32) -> CString {
-    CString::new(get_string(ctx, string)).unwrap()
+///
+/// Fails instead of truncating the string if it contains a null character.
+pub fn get_cstring(ctx: &mut wasm3::CallContext<Data>, string: i32) -> Result<CString, HostError> {
+    CString::new(get_string(ctx, string)?)
+        .map_err(|_| HostError::new("string passed to the SDK contains a null character"))
 }
 
 type TeaVMDataGetter = dyn Fn(StoreContextMut<Data>, i32) -> Result<i32>;
@@ -190,27 +191,23 @@ pub fn teamvm_main(
 
     // all this to make a (String[] args)
     let java_args = (teavm.allocate_string_array)(store.as_context_mut(), args.len() as i32)
-        .context("allocating String[]")? as usize;
-    let args_bytes = args.len() * size_of::<i32>();
+        .context("allocating String[]")? as u32;
     for (i, &arg) in args.iter().enumerate() {
         let java_arg = (teavm.allocate_string)(store.as_context_mut(), arg.len() as i32)
             .context("allocating String")?;
         let string_data = (teavm.string_data)(store.as_context_mut(), java_arg)
             .context("getting String bytes")?;
         let arg_address = (teavm.object_array_data)(store.as_context_mut(), string_data)
-            .context("getting data from String bytes")? as usize;
-        let arg_bytes = arg.len() * size_of::<u16>();
-
-        let memory = store.memory_mut();
-        let arg_slice: &mut [u16] =
-            bytemuck::cast_slice_mut(&mut memory[arg_address..arg_address + arg_bytes]);
-        for (i, byte) in arg.encode_utf16().enumerate() {
-            arg_slice[i] = byte;
-        }
-
-        let args_data: &mut [i32] =
-            bytemuck::cast_slice_mut(&mut memory[java_args..java_args + args_bytes]);
-        args_data[i] = java_arg;
+            .context("getting data from String bytes")? as u32;
+
+        let mut memory = store.memory_view();
+        let arg_utf16: Vec<u16> = arg.encode_utf16().collect();
+        memory
+            .write_slice(arg_address, &arg_utf16)
+            .context("writing String bytes")?;
+        memory
+            .write(java_args + (i * size_of::<i32>()) as u32, java_arg)
+            .context("writing String[] element")?;
     }
 
     flush_serial();
@@ -0,0 +1,300 @@
+use alloc::{
+    boxed::Box,
+    ffi::CString,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{ffi::c_char, str};
+
+use anyhow::{bail, Context, Result};
+use vexide::{io::print, time::Instant};
+use wasm3::{
+    error::Error,
+    wasi::{
+        Clock, ClockId, DirEntry, Errno, Exit, File, FileStat, FileSystem, FileType, OpenOptions,
+        Output, SeekFrom, WasiCtx,
+    },
+    Instance, Store,
+};
+
+use crate::Data;
+
+// results of `vexFileStatus`
+const STATUS_MISSING: u32 = 0;
+const STATUS_DIRECTORY: u32 = 3;
+
+const SEEK_SET: i32 = 0;
+
+/// Creates the WASI context of a user program, which prints to the serial port and can access
+/// the SD card if one is inserted.
+pub fn ctx() -> WasiCtx {
+    let mut wasi = WasiCtx::new();
+    wasi.arg("hydrozoa")
+        .stdout(SerialOutput::default())
+        .stderr(SerialOutput::default())
+        .clock(SystemClock {
+            epoch: Instant::now(),
+        });
+    if unsafe { vex_sdk::vexFileDriveStatus(0) } {
+        wasi.file_system(SdCard);
+    }
+    wasi
+}
+
+/// Runs a program built for WASI through its `_start` function.
+pub fn run(store: &mut Store<Data>, instance: &Instance<Data>) -> Result<()> {
+    let start = instance
+        .find_function::<(), ()>(store, "_start")
+        .context("Unable to find `_start`")?;
+    match start.call(&mut *store) {
+        Err(Error::Host { error }) => match error.downcast_ref::<Exit>().copied() {
+            Some(Exit(0)) => Ok(()),
+            Some(Exit(code)) => bail!("Program exited with code {code}"),
+            None => Err(Error::Host { error }.into()),
+        },
+        result => Ok(result?),
+    }
+}
+
+/// Prints to the serial port, holding back a character split across writes until the rest of it
+/// arrives.
+#[derive(Default)]
+struct SerialOutput {
+    pending: Vec<u8>,
+}
+
+impl Output for SerialOutput {
+    fn write(&mut self, bytes: &[u8]) {
+        self.pending.extend_from_slice(bytes);
+        let mut printed = 0;
+        while printed < self.pending.len() {
+            match str::from_utf8(&self.pending[printed..]) {
+                Ok(text) => {
+                    print!("{text}");
+                    printed = self.pending.len();
+                }
+                Err(error) => {
+                    let valid = &self.pending[printed..printed + error.valid_up_to()];
+                    print!("{}", str::from_utf8(valid).unwrap());
+                    printed += error.valid_up_to();
+                    match error.error_len() {
+                        Some(len) => {
+                            print!("{}", char::REPLACEMENT_CHARACTER);
+                            printed += len;
+                        }
+                        // the sequence at the end may still be completed by the next write
+                        None => break,
+                    }
+                }
+            }
+        }
+        self.pending.drain(..printed);
+    }
+}
+
+struct SystemClock {
+    epoch: Instant,
+}
+
+impl Clock for SystemClock {
+    fn now(&mut self, id: ClockId) -> Option<u64> {
+        match id {
+            // there is no wall clock, and programs run on their own
+            ClockId::Monotonic | ClockId::ProcessCputime | ClockId::ThreadCputime => {
+                Some(self.epoch.elapsed().as_nanos() as u64)
+            }
+            ClockId::Realtime => None,
+        }
+    }
+
+    fn resolution(&mut self, _id: ClockId) -> Option<u64> {
+        // `Instant` counts microseconds
+        Some(1000)
+    }
+
+    fn wait(&mut self, _id: ClockId, _deadline: u64) {
+        // keeps the SDK's background work, such as flushing the serial port, going while the
+        // program sleeps
+        unsafe { vex_sdk::vexTasksRun() }
+    }
+}
+
+/// The SD card, whose files can either be read or written, but not both at once. Existing files
+/// can only be appended to or replaced.
+struct SdCard;
+
+fn cstring(path: &str) -> Result<CString, Errno> {
+    CString::new(path).map_err(|_| Errno::Inval)
+}
+
+fn status(path: &CString) -> u32 {
+    unsafe { vex_sdk::vexFileStatus(path.as_ptr()) }
+}
+
+impl FileSystem for SdCard {
+    fn open(&mut self, path: &str, options: OpenOptions) -> Result<Box<dyn File>, Errno> {
+        if options.read && options.write {
+            return Err(Errno::Notsup);
+        }
+        let path = cstring(path)?;
+        let exists = match status(&path) {
+            STATUS_MISSING => false,
+            STATUS_DIRECTORY => return Err(Errno::Isdir),
+            _ => true,
+        };
+        if exists && options.create && options.exclusive {
+            return Err(Errno::Exist);
+        }
+        if !exists && !options.create {
+            return Err(Errno::Noent);
+        }
+        // existing files can only be written to at their end, or replaced
+        if exists && options.write && !options.truncate && !options.append {
+            return Err(Errno::Notsup);
+        }
+
+        let fd = unsafe {
+            if !options.write {
+                vex_sdk::vexFileOpen(path.as_ptr(), c"".as_ptr())
+            } else if options.truncate || !exists {
+                vex_sdk::vexFileOpenWrite(path.as_ptr())
+            } else {
+                vex_sdk::vexFileOpenCreate(path.as_ptr())
+            }
+        };
+        if fd.is_null() {
+            return Err(Errno::Io);
+        }
+        Ok(Box::new(SdFile { fd }))
+    }
+
+    fn stat(&mut self, path: &str) -> Result<FileStat, Errno> {
+        if path.is_empty() {
+            return Ok(FileStat {
+                filetype: FileType::Directory,
+                ..FileStat::default()
+            });
+        }
+        let path_c = cstring(path)?;
+        match status(&path_c) {
+            STATUS_MISSING => Err(Errno::Noent),
+            STATUS_DIRECTORY => Ok(FileStat {
+                filetype: FileType::Directory,
+                ..FileStat::default()
+            }),
+            _ => self
+                .open(
+                    path,
+                    OpenOptions {
+                        read: true,
+                        ..OpenOptions::default()
+                    },
+                )?
+                .stat(),
+        }
+    }
+
+    fn read_dir(&mut self, path: &str) -> Result<Vec<DirEntry>, Errno> {
+        let path_c = cstring(path)?;
+        let mut buffer = vec![0u8; 4096];
+        let result = unsafe {
+            vex_sdk::vexFileDirectoryGet(
+                path_c.as_ptr(),
+                buffer.as_mut_ptr() as *mut c_char,
+                buffer.len() as u32,
+            )
+        };
+        if result != vex_sdk::FRESULT::FR_OK {
+            return Err(Errno::Noent);
+        }
+
+        // the names are separated by newlines
+        let len = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+        let names = String::from_utf8_lossy(&buffer[..len]);
+        let entries = names
+            .split('\n')
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                let entry = if path.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{path}/{name}")
+                };
+                let is_dir = cstring(&entry).is_ok_and(|entry| status(&entry) == STATUS_DIRECTORY);
+                DirEntry {
+                    name: name.to_string(),
+                    filetype: if is_dir {
+                        FileType::Directory
+                    } else {
+                        FileType::RegularFile
+                    },
+                }
+            })
+            .collect();
+        Ok(entries)
+    }
+}
+
+struct SdFile {
+    fd: *mut vex_sdk::FIL,
+}
+
+impl File for SdFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Errno> {
+        let len = unsafe {
+            vex_sdk::vexFileRead(
+                buf.as_mut_ptr() as *mut c_char,
+                1,
+                buf.len() as u32,
+                self.fd,
+            )
+        };
+        usize::try_from(len).map_err(|_| Errno::Io)
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Errno> {
+        let len = unsafe {
+            vex_sdk::vexFileWrite(buf.as_ptr() as *mut c_char, 1, buf.len() as u32, self.fd)
+        };
+        usize::try_from(len).map_err(|_| Errno::Io)
+    }
+
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Errno> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(offset) => (0, i64::try_from(offset).map_err(|_| Errno::Inval)?),
+            SeekFrom::Current(offset) => (unsafe { vex_sdk::vexFileTell(self.fd) }, offset),
+            SeekFrom::End(offset) => (unsafe { vex_sdk::vexFileSize(self.fd) }, offset),
+        };
+        let pos = i64::from(base)
+            .checked_add(offset)
+            .and_then(|pos| u32::try_from(pos).ok())
+            .ok_or(Errno::Inval)?;
+        let result = unsafe { vex_sdk::vexFileSeek(self.fd, pos, SEEK_SET) };
+        if result != vex_sdk::FRESULT::FR_OK {
+            return Err(Errno::Io);
+        }
+        Ok(pos.into())
+    }
+
+    fn stat(&mut self) -> Result<FileStat, Errno> {
+        let size = unsafe { vex_sdk::vexFileSize(self.fd) };
+        Ok(FileStat {
+            filetype: FileType::RegularFile,
+            size: u64::try_from(size).map_err(|_| Errno::Io)?,
+            ..FileStat::default()
+        })
+    }
+
+    fn sync(&mut self) -> Result<(), Errno> {
+        unsafe { vex_sdk::vexFileSync(self.fd) };
+        Ok(())
+    }
+}
+
+impl Drop for SdFile {
+    fn drop(&mut self) {
+        unsafe { vex_sdk::vexFileClose(self.fd) };
+    }
+}
@@ -6,6 +6,8 @@ use std::{
 };
 
 static WASM3_SOURCE: &str = "wasm3/source";
+// our additions to the wasm3 api, see `ext/m3_ext.h`
+static EXT_SOURCE: &str = "ext";
 const WHITELIST_REGEX_FUNCTION: &str = "([A-Z]|m3_).*";
 const WHITELIST_REGEX_TYPE: &str = "(?:I|c_)?[Mm]3.*";
 const WHITELIST_REGEX_VAR: &str = WHITELIST_REGEX_TYPE;
@@ -13,10 +15,60 @@ const PRIMITIVES: &[&str] = &[
     "f64", "f32", "u64", "i64", "u32", "i32", "u16", "i16", "u8", "i8",
 ];
 
+// inserts the interrupt checks from `ext/m3_ext_exec.h` into the interpreter, each after the first
+// match of its anchor
+const INTERRUPT_CHECK: &str = "\n    d_m3CheckInterrupt\n";
+const SOURCE_PATCHES: &[(&str, &str, &str)] = &[
+    (
+        "m3_env.h",
+        "typedef struct M3Runtime {",
+        "\n    struct M3Interrupt *    interrupt;\n",
+    ),
+    (
+        "m3_exec.h",
+        "#define m3_exec_h",
+        "\n\n#include \"m3_ext_exec.h\"\n",
+    ),
+    ("m3_exec.h", "d_m3Op (Entry) {", INTERRUPT_CHECK),
+    ("m3_exec.h", "d_m3Op (ContinueLoop) {", INTERRUPT_CHECK),
+    ("m3_exec.h", "d_m3Op (ContinueLoopIf) {", INTERRUPT_CHECK),
+    ("m3_exec.h", "d_m3Op (CallRawFunction) {", INTERRUPT_CHECK),
+];
+
+// counts the calls of each function and their time, by turning `op_Entry` into a wrapper around
+// its original body, see `d_m3ProfileEntry` in `ext/m3_ext_exec.h`, and exposes the counters of
+// wasm3's op profiler, see `ext/m3_ext_info.h`
+#[cfg(feature = "profiling")]
+const PROFILING_PATCHES: &[(&str, &str, &str)] = &[
+    (
+        "m3_env.h",
+        "typedef struct M3Runtime {",
+        "\n    uint64_t (* profileClock) (void);\n",
+    ),
+    (
+        "m3_function.h",
+        "typedef struct M3Function {",
+        "\n    u64 profileCalls;\n    u64 profileTime;\n",
+    ),
+    (
+        "m3_exec.h",
+        "d_m3Op (Entry) {",
+        "\n    d_m3ProfileEntry\n}\n\nd_m3Op (EntryBody) {\n",
+    ),
+    (
+        "m3_info.c",
+        "slot->hitCount++; }",
+        "\n\n#include \"m3_ext_info.h\"\n",
+    ),
+];
+#[cfg(not(feature = "profiling"))]
+const PROFILING_PATCHES: &[(&str, &str, &str)] = &[];
+
 fn gen_wrapper(out_path: &Path) -> PathBuf {
     let wrapper_file = out_path.join("wrapper.h");
     let header_files = [
         "wasm3.h",
+        "m3_ext.h",
         #[cfg(feature = "wasi")]
         "m3_api_wasi.h",
     ];
@@ -27,6 +79,53 @@ fn gen_wrapper(out_path: &Path) -> PathBuf {
     wrapper_file
 }
 
+/// Finds the end of the first match of `anchor` in `source`,
+/// where whitespace in `anchor` matches any amount of whitespace.
+fn find_anchor(source: &str, anchor: &str) -> Option<usize> {
+    let tokens: Vec<&str> = anchor.split_whitespace().collect();
+    source
+        .char_indices()
+        .map(|(start, _)| start)
+        .find_map(|start| {
+            tokens.iter().try_fold(start, |pos, token| {
+                let rest = source[pos..].trim_start();
+                let pos = source.len() - rest.len();
+                rest.starts_with(token).then(|| pos + token.len())
+            })
+        })
+}
+
+fn copy_dir(from: &Path, to: &Path) {
+    fs::create_dir_all(to).expect("failed to create wasm3 source copy");
+    for entry in fs::read_dir(from).expect("failed to read wasm3 source directory") {
+        let path = entry.unwrap().path();
+        let target = to.join(path.file_name().unwrap());
+        if path.is_dir() {
+            copy_dir(&path, &target);
+        } else {
+            fs::copy(&path, &target).expect("failed to copy wasm3 source file");
+        }
+    }
+}
+
+/// Copies the wasm3 sources to `out_path` and applies [`SOURCE_PATCHES`] and
+/// [`PROFILING_PATCHES`] to them.
+fn patch_source(out_path: &Path) -> PathBuf {
+    let source_dir = out_path.join("wasm3-source");
+    copy_dir(Path::new(WASM3_SOURCE), &source_dir);
+
+    for &(file, anchor, insertion) in SOURCE_PATCHES.iter().chain(PROFILING_PATCHES) {
+        let path = source_dir.join(file);
+        let mut source = fs::read_to_string(&path)
+            .unwrap_or_else(|_| panic!("failed to read {}", path.display()));
+        let end = find_anchor(&source, anchor)
+            .unwrap_or_else(|| panic!("failed to patch {}: `{}` not found", file, anchor));
+        source.insert_str(end, insertion);
+        fs::write(&path, source).unwrap_or_else(|_| panic!("failed to write {}", path.display()));
+    }
+    source_dir
+}
+
 #[cfg(not(feature = "build-bindgen"))]
 fn gen_bindings() {
     let out_path = PathBuf::from(&env::var("OUT_DIR").unwrap());
@@ -66,7 +165,8 @@ fn gen_bindings() {
             }
         ))
         .arg("-Dd_m3LogOutput=0")
-        .arg("-Iwasm3/source");
+        .arg("-Iwasm3/source")
+        .arg("-Iext");
     let status = bindgen.status().expect("Unable to generate bindings");
     if !status.success() {
         panic!("Failed to run bindgen: {:?}", status);
@@ -105,6 +205,7 @@ fn gen_bindings() {
                 "-Dd_m3EnableExceptionBreakpoint=1",
                 "-Dd_m3VerboseErrorMessages=1",
                 "-Iwasm3/source",
+                "-Iext",
             ]
             .iter(),
         )
@@ -117,15 +218,20 @@ fn gen_bindings() {
 fn main() {
     gen_bindings();
 
+    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let wasm3_source = patch_source(&out_path);
+
     let mut cfg = cc::Build::new();
 
-    cfg.files(
-        fs::read_dir(WASM3_SOURCE)
-            .unwrap_or_else(|_| panic!("failed to read {} directory", WASM3_SOURCE))
-            .filter_map(Result::ok)
-            .map(|entry| entry.path())
-            .filter(|p| p.extension().and_then(OsStr::to_str) == Some("c")),
-    );
+    for source_dir in [wasm3_source.as_path(), Path::new(EXT_SOURCE)] {
+        cfg.files(
+            fs::read_dir(source_dir)
+                .unwrap_or_else(|_| panic!("failed to read {} directory", source_dir.display()))
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|p| p.extension().and_then(OsStr::to_str) == Some("c")),
+        );
+    }
 
     cfg.cpp(false)
         .define("d_m3LogOutput", Some("0"))
@@ -134,7 +240,8 @@ fn main() {
         .define("d_m3VerboseErrorMessages", Some("1"))
         .warnings(false)
         .extra_warnings(false)
-        .include(WASM3_SOURCE);
+        .include(&wasm3_source)
+        .include(EXT_SOURCE);
 
     // Add any extra arguments from the environment to the CC command line.
     if let Ok(extra_clang_args) = std::env::var("BINDGEN_EXTRA_CLANG_ARGS") {
@@ -152,6 +259,15 @@ fn main() {
         cfg.define("d_m3HasWASI", None);
     }
 
+    if cfg!(feature = "profiling") {
+        cfg.define("d_m3EnableFunctionProfiling", Some("1"));
+        cfg.define("d_m3EnableOpProfiling", Some("1"));
+    }
+
+    if cfg!(feature = "record-backtraces") {
+        cfg.define("d_m3RecordBacktraces", Some("1"));
+    }
+
     cfg.define(
         "d_m3Use32BitSlots",
         if cfg!(feature = "use-32bit-slots") {
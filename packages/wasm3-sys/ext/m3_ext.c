@@ -0,0 +1,410 @@
+//
+//  m3_ext.c
+//
+//  See m3_ext.h.
+//
+
+#include "m3_ext.h"
+
+#include "m3_compile.h"
+#include "m3_env.h"
+
+#include <string.h>
+
+
+M3Result m3Err_trapInterrupted = "[trap] interrupted";
+M3Result m3Err_hostError = "host function failed";
+
+
+M3Result  m3_UnloadModule  (IM3Runtime io_runtime, IM3Module i_module)
+{
+    IM3Module * link = & io_runtime->modules;
+
+    while (* link and * link != i_module)
+        link = & (* link)->next;
+
+    if (not * link)
+        return m3Err_moduleNotLinked;
+
+    * link = i_module->next;
+    m3_FreeModule (i_module);
+
+    // the error info may point into the module, and so may the backtrace of the last trap
+    m3_ResetErrorInfo (io_runtime);
+#if d_m3RecordBacktraces
+    ClearBacktrace (io_runtime);
+#endif
+
+    if (not io_runtime->modules)
+    {
+        FreeCodePages (& io_runtime->pagesOpen);
+        FreeCodePages (& io_runtime->pagesFull);
+        io_runtime->numCodePages = 0;
+        io_runtime->numActiveCodePages = 0;
+
+        // shrinking to nothing zeroes the memory the next module grows it to
+        return ResizeMemory (io_runtime, 0);
+    }
+
+    return m3Err_none;
+}
+
+
+// the same lookup as m3_FindFunction: exports first, then the names of defined functions
+static
+IM3Function  FindFunctionByName  (IM3Module i_module, const char * const i_name)
+{
+    for (u32 i = 0; i < i_module->numFunctions; ++i)
+    {
+        IM3Function f = & i_module->functions [i];
+
+        if (f->export_name and strcmp (f->export_name, i_name) == 0)
+            return f;
+    }
+
+    for (u32 i = 0; i < i_module->numFunctions; ++i)
+    {
+        IM3Function f = & i_module->functions [i];
+
+        if (f->import.moduleUtf8 or f->import.fieldUtf8)
+            continue;
+
+        for (u32 j = 0; j < f->numNames; ++j)
+        {
+            if (f->names [j] and strcmp (f->names [j], i_name) == 0)
+                return f;
+        }
+    }
+
+    return NULL;
+}
+
+
+M3Result  m3_FindModuleFunction  (IM3Function * o_function, IM3Module i_module, const char * const i_functionName)
+{
+    * o_function = NULL;
+
+    IM3Function function = FindFunctionByName (i_module, i_functionName);
+
+    if (not function)
+        return m3Err_functionLookupFailed;
+
+    if (not function->compiled)
+    {
+        M3Result result = CompileFunction (function);
+
+        if (result)
+            return result;
+    }
+
+    * o_function = function;
+    return m3Err_none;
+}
+
+
+M3Result  m3_FindFunctionWithoutStart  (IM3Function * o_function, IM3Runtime i_runtime, const char * const i_functionName)
+{
+    for (IM3Module module = i_runtime->modules; module; module = module->next)
+    {
+        M3Result result = m3_FindModuleFunction (o_function, module, i_functionName);
+
+        if (result != m3Err_functionLookupFailed)
+            return result;
+    }
+
+    return m3Err_functionLookupFailed;
+}
+
+
+M3Result  m3_LinkFunction  (IM3Function io_import, IM3Function i_function)
+{
+    // function types are shared by all modules of an environment
+    if (io_import->funcType != i_function->funcType)
+        return m3Err_malformedFunctionSignature;
+
+    if (not i_function->compiled)
+    {
+        M3Result result = CompileFunction (i_function);
+
+        if (result)
+            return result;
+    }
+
+    // all modules of a runtime share its stack and memory, so the code runs as is
+    io_import->compiled = i_function->compiled;
+
+    return m3Err_none;
+}
+
+
+uint32_t  m3_GetFunctionCount  (IM3Module i_module)
+{
+    return i_module->numFunctions;
+}
+
+
+IM3Function  m3_GetFunctionByIndex  (IM3Module i_module, uint32_t i_index)
+{
+    if (i_index >= i_module->numFunctions)
+        return NULL;
+
+    return & i_module->functions [i_index];
+}
+
+
+bool  m3_GetFunctionImport  (IM3Function i_function, M3ImportInfo * o_import)
+{
+    IM3Module module = i_function->module;
+    u32 index = (u32) (i_function - module->functions);
+
+    if (index >= module->numFuncImports)
+        return false;
+
+    * o_import = i_function->import;
+    return true;
+}
+
+
+bool  m3_IsFunctionCompiled  (IM3Function i_function)
+{
+    return i_function->compiled != NULL;
+}
+
+
+uint32_t  m3_GetFunctionIndex  (IM3Function i_function)
+{
+    return (u32) (i_function - i_function->module->functions);
+}
+
+
+const char *  m3_GetFunctionDebugName  (IM3Function i_function)
+{
+    u16 numNames = 0;
+    cstr_t * names = GetFunctionNames (i_function, & numNames);
+
+    return numNames ? names [0] : NULL;
+}
+
+
+M3Result  m3_CompileFunction  (IM3Function io_function)
+{
+    if (io_function->compiled or not io_function->wasm)
+        return m3Err_none;
+
+    return CompileFunction (io_function);
+}
+
+
+static
+void  AddCodePages  (IM3CodePage i_page, size_t * io_allocated, size_t * io_used)
+{
+    while (i_page)
+    {
+        * io_allocated += sizeof (M3CodePageHeader) + i_page->info.numLines * sizeof (code_t);
+        * io_used += i_page->info.lineIndex * sizeof (code_t);
+
+        i_page = i_page->info.next;
+    }
+}
+
+
+void  m3_GetCodeMemory  (IM3Runtime i_runtime, size_t * o_allocated, size_t * o_used)
+{
+    * o_allocated = 0;
+    * o_used = 0;
+
+    AddCodePages (i_runtime->pagesOpen, o_allocated, o_used);
+    AddCodePages (i_runtime->pagesFull, o_allocated, o_used);
+}
+
+
+uint32_t  m3_GetGlobalCount  (IM3Module i_module)
+{
+    return i_module->numGlobals;
+}
+
+
+IM3Global  m3_GetGlobalByIndex  (IM3Module i_module, uint32_t i_index)
+{
+    if (i_index >= i_module->numGlobals)
+        return NULL;
+
+    return & i_module->globals [i_index];
+}
+
+
+bool  m3_IsGlobalMutable  (IM3Global i_global)
+{
+    return i_global->isMutable;
+}
+
+
+uint32_t  m3_GetTableSize  (IM3Module i_module)
+{
+    return i_module->table0Size;
+}
+
+
+uint32_t  m3_GetModuleMemoryInitPages  (IM3Module i_module)
+{
+    if (i_module->memoryImported)
+        return 0;
+
+    return i_module->memoryInfo.initPages;
+}
+
+
+uint32_t  m3_GetMemoryPages  (IM3Runtime i_runtime)
+{
+    return i_runtime->memory.numPages;
+}
+
+
+uint32_t  m3_GetMemoryMaxPages  (IM3Runtime i_runtime)
+{
+    return i_runtime->memory.maxPages;
+}
+
+
+void  m3_SetMemoryMaxPages  (IM3Runtime io_runtime, uint32_t i_maxPages)
+{
+    io_runtime->memory.maxPages = i_maxPages;
+}
+
+
+M3Result  m3_GrowMemory  (IM3Runtime io_runtime, uint32_t i_numPages)
+{
+    u32 numPages = io_runtime->memory.numPages;
+    u32 maxPages = io_runtime->memory.maxPages;
+
+    if (numPages > maxPages or i_numPages > maxPages - numPages)
+        return m3Err_wasmMemoryOverflow;
+
+    return ResizeMemory (io_runtime, numPages + i_numPages);
+}
+
+
+M3Result  m3_SetMemoryPages  (IM3Runtime io_runtime, uint32_t i_numPages)
+{
+    if (i_numPages > io_runtime->memory.maxPages)
+        return m3Err_wasmMemoryOverflow;
+
+    return ResizeMemory (io_runtime, i_numPages);
+}
+
+
+void *  m3_SetCallStack  (IM3Runtime io_runtime, void * i_stack)
+{
+    m3slot_t * previous = io_runtime->stack;
+    m3slot_t * end = previous + io_runtime->numStackSlots;
+    m3slot_t * stack = i_stack;
+
+    // a frame reaching past the end leaves no room, so the nested call overflows right away
+    if (stack > end)
+        stack = end;
+
+    // ResizeMemory derives the limit operations check the stack against from both fields
+    io_runtime->stack = stack;
+    io_runtime->numStackSlots = (u32) (end - stack);
+
+    return previous;
+}
+
+
+M3BacktraceInfo  m3_TakeBacktrace  (IM3Runtime io_runtime)
+{
+    M3BacktraceInfo backtrace = { NULL, NULL };
+
+#if d_m3RecordBacktraces
+    backtrace = io_runtime->backtrace;
+    io_runtime->backtrace.frames = NULL;
+    io_runtime->backtrace.lastFrame = NULL;
+#endif
+
+    return backtrace;
+}
+
+
+void  m3_RestoreBacktrace  (IM3Runtime io_runtime, M3BacktraceInfo i_backtrace)
+{
+#if d_m3RecordBacktraces
+    ClearBacktrace (io_runtime);
+    io_runtime->backtrace = i_backtrace;
+#endif
+}
+
+
+M3Result  m3_CheckCallStack  (IM3Function i_function)
+{
+    IM3Runtime runtime = i_function->module->runtime;
+    IM3FuncType type = i_function->funcType;
+
+    // m3_Call and m3_GetResults use a full u64 for every value
+    u64 size = (u64) (type->numRets + type->numArgs) * sizeof (u64);
+
+    if (size > (u64) runtime->numStackSlots * sizeof (m3slot_t))
+        return m3Err_trapStackOverflow;
+
+    return m3Err_none;
+}
+
+
+void  m3_SetInterrupt  (IM3Runtime io_runtime, M3Interrupt * i_interrupt)
+{
+    io_runtime->interrupt = i_interrupt;
+}
+
+
+M3Interrupt *  m3_GetInterrupt  (IM3Runtime i_runtime)
+{
+    return i_runtime->interrupt;
+}
+
+
+#if d_m3EnableFunctionProfiling
+
+void  m3_SetProfileClock  (IM3Runtime io_runtime, uint64_t (* i_clock) (void))
+{
+    io_runtime->profileClock = i_clock;
+}
+
+
+bool  m3_GetFunctionProfile  (IM3Function i_function, M3FunctionProfile * o_profile)
+{
+    o_profile->calls = i_function->profileCalls;
+    o_profile->time = i_function->profileTime;
+    return true;
+}
+
+
+void  m3_ResetFunctionProfile  (IM3Function i_function)
+{
+    i_function->profileCalls = 0;
+    i_function->profileTime = 0;
+}
+
+#else
+
+void  m3_SetProfileClock  (IM3Runtime io_runtime, uint64_t (* i_clock) (void)) {}
+
+bool  m3_GetFunctionProfile  (IM3Function i_function, M3FunctionProfile * o_profile)
+{
+    return false;
+}
+
+void  m3_ResetFunctionProfile  (IM3Function i_function) {}
+
+#endif
+
+
+// the real ones need the op profiler's private counters, see m3_ext_info.h
+#if !d_m3EnableOpProfiling
+
+uint32_t  m3_GetOpProfile  (M3OpProfile * o_ops, uint32_t i_length)
+{
+    return 0;
+}
+
+void  m3_ResetOpProfile  (void) {}
+
+#endif
@@ -0,0 +1,216 @@
+//
+//  m3_ext.h
+//
+//  Additions to the wasm3 API that need access to interpreter internals.
+//  These are compiled alongside the wasm3 sources by `build.rs`.
+//
+
+#ifndef m3_ext_h
+#define m3_ext_h
+
+#include "wasm3.h"
+
+#include <stdbool.h>
+
+#if defined(__cplusplus)
+extern "C" {
+#endif
+
+//-------------------------------------------------------------------------------------------------------------------------------
+//  modules
+//-------------------------------------------------------------------------------------------------------------------------------
+
+    // unlinks a loaded module from its runtime and frees it; once no modules are left, the memory
+    // and the compiled code of the runtime are freed as well
+    M3Result            m3_UnloadModule             (IM3Runtime                 io_runtime,
+                                                     IM3Module                  i_module);
+
+//-------------------------------------------------------------------------------------------------------------------------------
+//  functions
+//-------------------------------------------------------------------------------------------------------------------------------
+
+    // like m3_FindFunction, but leaves running the start function of the function's module to the
+    // embedder, see m3_RunStart
+    M3Result            m3_FindFunctionWithoutStart (IM3Function *              o_function,
+                                                     IM3Runtime                 i_runtime,
+                                                     const char * const         i_functionName);
+
+    // like m3_FindFunctionWithoutStart, but only looks up the functions of one module
+    M3Result            m3_FindModuleFunction       (IM3Function *              o_function,
+                                                     IM3Module                  i_module,
+                                                     const char * const         i_functionName);
+
+    // links an imported function to a function of another module loaded into the same runtime, so
+    // that calls jump straight to its compiled code; fails with m3Err_malformedFunctionSignature
+    // if their types differ
+    M3Result            m3_LinkFunction             (IM3Function                io_import,
+                                                     IM3Function                i_function);
+
+    // the number of functions in a module, including imported ones
+    uint32_t            m3_GetFunctionCount         (IM3Module                  i_module);
+
+    // imported functions come first; returns NULL when out of range
+    IM3Function         m3_GetFunctionByIndex       (IM3Module                  i_module,
+                                                     uint32_t                   i_index);
+
+    // returns false if the function is defined by the module rather than imported
+    bool                m3_GetFunctionImport        (IM3Function                i_function,
+                                                     M3ImportInfo *             o_import);
+
+    // for imported functions this means the import has been linked
+    bool                m3_IsFunctionCompiled       (IM3Function                i_function);
+
+    // the inverse of m3_GetFunctionByIndex
+    uint32_t            m3_GetFunctionIndex         (IM3Function                i_function);
+
+    // the name from the name section, an export or an import; NULL if the function has none
+    const char *        m3_GetFunctionDebugName     (IM3Function                i_function);
+
+    // compiles a function ahead of its first call; does nothing for imported or compiled functions
+    M3Result            m3_CompileFunction          (IM3Function                io_function);
+
+    // the bytes of the code pages holding the compiled functions of a runtime, and how many of them are in use
+    void                m3_GetCodeMemory            (IM3Runtime                 i_runtime,
+                                                     size_t *                   o_allocated,
+                                                     size_t *                   o_used);
+
+//-------------------------------------------------------------------------------------------------------------------------------
+//  globals
+//-------------------------------------------------------------------------------------------------------------------------------
+
+    // the number of globals in a module, including imported ones
+    uint32_t            m3_GetGlobalCount           (IM3Module                  i_module);
+
+    // imported globals come first; returns NULL when out of range
+    IM3Global           m3_GetGlobalByIndex         (IM3Module                  i_module,
+                                                     uint32_t                   i_index);
+
+    bool                m3_IsGlobalMutable          (IM3Global                  i_global);
+
+//-------------------------------------------------------------------------------------------------------------------------------
+//  tables
+//-------------------------------------------------------------------------------------------------------------------------------
+
+    // the number of elements in the module's function table, for bounds-checking m3_GetTableFunction
+    uint32_t            m3_GetTableSize             (IM3Module                  i_module);
+
+//-------------------------------------------------------------------------------------------------------------------------------
+//  memory
+//-------------------------------------------------------------------------------------------------------------------------------
+
+    // the initial size of the memory a module defines; 0 if it imports its memory or has none
+    uint32_t            m3_GetModuleMemoryInitPages (IM3Module                  i_module);
+
+    uint32_t            m3_GetMemoryPages           (IM3Runtime                 i_runtime);
+    uint32_t            m3_GetMemoryMaxPages        (IM3Runtime                 i_runtime);
+
+    // loading a module resets the maximum to the one it declares, so this has to be applied after loading
+    void                m3_SetMemoryMaxPages        (IM3Runtime                 io_runtime,
+                                                     uint32_t                   i_maxPages);
+
+    // fails with m3Err_wasmMemoryOverflow if the maximum would be exceeded
+    M3Result            m3_GrowMemory               (IM3Runtime                 io_runtime,
+                                                     uint32_t                   i_numPages);
+
+    // unlike m3_GrowMemory this may also shrink the memory; fails with m3Err_wasmMemoryOverflow
+    // beyond the maximum
+    M3Result            m3_SetMemoryPages           (IM3Runtime                 io_runtime,
+                                                     uint32_t                   i_numPages);
+
+//-------------------------------------------------------------------------------------------------------------------------------
+//  nested calls
+//-------------------------------------------------------------------------------------------------------------------------------
+
+    // calls always start at the runtime's stack, so a host function calling back into wasm has to move the stack above its
+    // own frame first, or the nested call overwrites the frames of the calls still running. this moves the start of the
+    // stack to i_stack while keeping its end, and returns the previous start to restore once the host function returns
+    void *              m3_SetCallStack             (IM3Runtime                 io_runtime,
+                                                     void *                     i_stack);
+
+    // every call clears the backtrace of the last one and a trap appends its frames to it, so a host function calling back
+    // into wasm takes the backtrace of the call it runs in first, and restores it once it returns, freeing the backtrace
+    // of the nested calls. both do nothing unless built with d_m3RecordBacktraces
+    M3BacktraceInfo     m3_TakeBacktrace            (IM3Runtime                 io_runtime);
+
+    void                m3_RestoreBacktrace         (IM3Runtime                 io_runtime,
+                                                     M3BacktraceInfo            i_backtrace);
+
+    // fails with m3Err_trapStackOverflow if the arguments and results of a call to the function don't fit on the stack,
+    // which m3_Call writes without checking
+    M3Result            m3_CheckCallStack           (IM3Function                i_function);
+
+//-------------------------------------------------------------------------------------------------------------------------------
+//  interrupts
+//-------------------------------------------------------------------------------------------------------------------------------
+
+    // checked by the interpreter at loop back-edges, function entries and host calls, see m3_ext_exec.h
+    typedef struct M3Interrupt
+    {
+        // set by the host to stop the running call; cleared again once the call has trapped.
+        // handles set it from other threads, so it's only ever accessed atomically
+        uint8_t             interrupted;
+
+        // when set, polled every d_m3InterruptPollInterval checks; the call is interrupted once it reaches the deadline
+        uint64_t         (* clock)                      (void);
+        uint64_t            deadline;
+        uint32_t            countdown;
+    }
+    M3Interrupt;
+
+    extern M3Result     m3Err_trapInterrupted;
+
+    // the interrupt has to outlive the runtime; NULL disables the checks
+    void                m3_SetInterrupt             (IM3Runtime                 io_runtime,
+                                                     M3Interrupt *              i_interrupt);
+
+    M3Interrupt *       m3_GetInterrupt             (IM3Runtime                 i_runtime);
+
+    // returned by host functions that failed with an error the embedder keeps for itself,
+    // e.g. in the runtime's userdata
+    extern M3Result     m3Err_hostError;
+
+//-------------------------------------------------------------------------------------------------------------------------------
+//  profiling
+//-------------------------------------------------------------------------------------------------------------------------------
+
+    // only recorded when built with d_m3EnableFunctionProfiling, see m3_ext_exec.h
+    typedef struct M3FunctionProfile
+    {
+        uint64_t            calls;
+
+        // read from the runtime's profile clock, including the time spent in callees
+        uint64_t            time;
+    }
+    M3FunctionProfile;
+
+    // NULL only counts calls
+    void                m3_SetProfileClock          (IM3Runtime                 io_runtime,
+                                                     uint64_t                (* i_clock) (void));
+
+    // returns false if profiling is disabled
+    bool                m3_GetFunctionProfile       (IM3Function                i_function,
+                                                     M3FunctionProfile *        o_profile);
+
+    void                m3_ResetFunctionProfile     (IM3Function                i_function);
+
+    // counted for all runtimes when built with d_m3EnableOpProfiling, see m3_ext_info.h
+    typedef struct M3OpProfile
+    {
+        // the name of the operation's C function, which lives as long as the program
+        const char *        name;
+        uint64_t            hits;
+    }
+    M3OpProfile;
+
+    // copies the counts of up to i_length operations that ran into o_ops and returns how many
+    // there are in total; 0 if op profiling is disabled
+    uint32_t            m3_GetOpProfile             (M3OpProfile *              o_ops,
+                                                     uint32_t                   i_length);
+
+    void                m3_ResetOpProfile           (void);
+
+#if defined(__cplusplus)
+}
+#endif
+
+#endif // m3_ext_h
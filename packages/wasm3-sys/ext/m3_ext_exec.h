@@ -0,0 +1,69 @@
+//
+//  m3_ext_exec.h
+//
+//  Interrupt checks and function profiling for the interpreter. `build.rs` patches the wasm3
+//  sources to include this from m3_exec.h and to expand d_m3CheckInterrupt at the start of the
+//  operations that act as safe points.
+//
+
+#ifndef m3_ext_exec_h
+#define m3_ext_exec_h
+
+#include "m3_ext.h"
+#include "m3_env.h"
+
+#ifndef d_m3InterruptPollInterval
+#   define d_m3InterruptPollInterval    1000
+#endif
+
+static inline
+bool  IsInterrupted  (IM3Runtime i_runtime)
+{
+    M3Interrupt * interrupt = i_runtime->interrupt;
+
+    if (interrupt == NULL)
+        return false;
+
+    if (interrupt->clock and --interrupt->countdown == 0)
+    {
+        interrupt->countdown = d_m3InterruptPollInterval;
+
+        if (interrupt->clock () >= interrupt->deadline)
+            __atomic_store_n (& interrupt->interrupted, 1, __ATOMIC_RELAXED);
+    }
+
+    // checking and clearing in one step, so that an interrupt set in between isn't lost
+    if (M3_UNLIKELY (__atomic_load_n (& interrupt->interrupted, __ATOMIC_RELAXED)))
+        return __atomic_exchange_n (& interrupt->interrupted, 0, __ATOMIC_ACQ_REL) != 0;
+
+    return false;
+}
+
+# define d_m3CheckInterrupt     if (M3_UNLIKELY (IsInterrupted (m3MemRuntime (_mem)))) newTrap (m3Err_trapInterrupted);
+
+#if d_m3EnableFunctionProfiling
+
+#include "m3_exec_defs.h"
+
+// `build.rs` splits op_Entry into a wrapper expanding d_m3ProfileEntry and this, the original body
+d_m3RetSig  op_EntryBody  (d_m3OpSig);
+
+static inline
+uint64_t  ProfileClock  (IM3Runtime i_runtime)
+{
+    return i_runtime->profileClock ? i_runtime->profileClock () : 0;
+}
+
+// the function is still the immediate of the entry operation, which the body consumes
+# define d_m3ProfileEntry \
+    IM3Function profiled = * (IM3Function *) _pc; \
+    IM3Runtime profiledRuntime = m3MemRuntime (_mem); \
+    uint64_t start = ProfileClock (profiledRuntime); \
+    profiled->profileCalls++; \
+    m3ret_t r = op_EntryBody (d_m3OpAllArgs); \
+    profiled->profileTime += ProfileClock (profiledRuntime) - start; \
+    return r;
+
+#endif
+
+#endif // m3_ext_exec_h
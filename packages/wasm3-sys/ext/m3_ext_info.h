@@ -0,0 +1,46 @@
+//
+//  m3_ext_info.h
+//
+//  Accessors for the counters of wasm3's op profiler, which are private to m3_info.c. `build.rs`
+//  includes this in m3_info.c right after ProfileHit when built with d_m3EnableOpProfiling.
+//
+
+#ifndef m3_ext_info_h
+#define m3_ext_info_h
+
+#include "m3_ext.h"
+
+uint32_t  m3_GetOpProfile  (M3OpProfile * o_ops, uint32_t i_length)
+{
+    uint32_t count = 0;
+
+    for (u32 i = 0; i <= d_m3ProfilerSlotMask; ++i)
+    {
+        M3ProfilerSlot * slot = & s_opProfilerCounts [i];
+
+        if (slot->opName)
+        {
+            if (count < i_length)
+            {
+                o_ops [count].name = slot->opName;
+                o_ops [count].hits = slot->hitCount;
+            }
+
+            ++count;
+        }
+    }
+
+    return count;
+}
+
+
+void  m3_ResetOpProfile  (void)
+{
+    for (u32 i = 0; i <= d_m3ProfilerSlotMask; ++i)
+    {
+        s_opProfilerCounts [i].opName = NULL;
+        s_opProfilerCounts [i].hitCount = 0;
+    }
+}
+
+#endif // m3_ext_info_h
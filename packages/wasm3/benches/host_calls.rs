@@ -0,0 +1,127 @@
+//! Measures the overhead of a call from wasm into a host function, and of a call from the host
+//! into wasm.
+//!
+//! Run with `cargo bench -p wasm3 --bench host_calls`. The benchmark only uses API that predates
+//! the current store data access and doesn't share code with the tests, so it can be copied onto
+//! older revisions to compare, along with its `[[bench]]` entry in `Cargo.toml`.
+use std::time::{Duration, Instant};
+
+use wasm3::{Environment, Instance, Linker, Store};
+use wast::{
+    parser::{self, ParseBuffer},
+    Wat,
+};
+
+const GUEST: &str = r#"
+(module
+  (import "host" "count" (func $count (param i32) (result i32)))
+  (func (export "run") (param $n i32) (result i32)
+    (local $acc i32)
+    (block $done
+      (loop $loop
+        (br_if $done (i32.eqz (local.get $n)))
+        (local.set $acc (call $count (local.get $acc)))
+        (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+        (br $loop)))
+    (local.get $acc))
+  (func (export "echo") (param i32) (result i32)
+    (local.get 0))
+)
+"#;
+
+const STACK_SIZE: u32 = 1024 * 60;
+const CALLS: i32 = 1_000_000;
+const ROUNDS: usize = 10;
+
+#[derive(Default)]
+struct Counter {
+    calls: u64,
+}
+
+fn instantiate(env: &Environment, linker: &Linker<Counter>) -> (Store<Counter>, Instance<Counter>) {
+    let buffer = ParseBuffer::new(GUEST).expect("Unable to lex module");
+    let mut wat = parser::parse::<Wat>(&buffer).expect("Unable to parse module");
+    let module = env
+        .parse_module(wat.encode().expect("Unable to encode module"))
+        .expect("Unable to parse module");
+    let mut store = env
+        .create_store(STACK_SIZE, Counter::default())
+        .expect("Unable to create runtime");
+    let instance = linker
+        .instantiate(&mut store, module)
+        .expect("Unable to load module");
+    (store, instance)
+}
+
+/// Returns the fastest time per host call over all rounds.
+fn measure_host_calls(store: &mut Store<Counter>, instance: &Instance<Counter>) -> Duration {
+    let run = instance
+        .find_function::<i32, i32>(store, "run")
+        .expect("Unable to find function");
+    // compiles the guest
+    run.call(&mut *store, 1).unwrap();
+
+    let fastest = (0..ROUNDS)
+        .map(|_| {
+            let start = Instant::now();
+            assert_eq!(run.call(&mut *store, CALLS), Ok(CALLS));
+            start.elapsed()
+        })
+        .min()
+        .unwrap();
+    assert_eq!(store.data().calls, (ROUNDS as u64) * CALLS as u64 + 1);
+    fastest / CALLS as u32
+}
+
+/// Returns the fastest time per call into wasm over all rounds.
+fn measure_guest_calls(store: &mut Store<Counter>, instance: &Instance<Counter>) -> Duration {
+    let echo = instance
+        .find_function::<i32, i32>(store, "echo")
+        .expect("Unable to find function");
+    echo.call(&mut *store, 0).unwrap();
+
+    (0..ROUNDS)
+        .map(|_| {
+            let start = Instant::now();
+            for i in 0..CALLS {
+                assert_eq!(echo.call(&mut *store, i), Ok(i));
+            }
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+        / CALLS as u32
+}
+
+fn main() {
+    let env = Environment::new().expect("Unable to create environment");
+
+    let (mut store, mut instance) = instantiate(&env, &Linker::new());
+    instance
+        .link_closure(&mut store, "host", "count", |mut ctx, acc: i32| {
+            ctx.data_mut().calls += 1;
+            Ok(acc + 1)
+        })
+        .expect("Unable to link closure");
+    println!(
+        "closure: {:?} per call",
+        measure_host_calls(&mut store, &instance)
+    );
+
+    let mut linker = Linker::<Counter>::new();
+    linker
+        .func_wrap("host", "count", |mut ctx, acc: i32| {
+            ctx.data_mut().calls += 1;
+            Ok(acc + 1)
+        })
+        .expect("Unable to define function");
+    let (mut store, instance) = instantiate(&env, &linker);
+    println!(
+        "linker: {:?} per call",
+        measure_host_calls(&mut store, &instance)
+    );
+    println!(
+        "into wasm: {:?} per call",
+        measure_guest_calls(&mut store, &instance)
+    );
+}
@@ -1,25 +1,29 @@
-use wasm3::{Environment, Instance};
+use wasm3::Environment;
 
 const MILLIS: u64 = 500_000;
 
 fn main() {
     let env = Environment::new().expect("Unable to create environment");
-    let rt = env
-        .create_store(1024 * 60)
+    let mut store = env
+        .create_store(1024 * 60, ())
         .expect("Unable to create runtime");
-    let module = Instance::parse(
-        &env,
-        &include_bytes!("wasm/wasm_millis_to_seconds/wasm_millis_to_seconds.wasm")[..],
-    )
-    .expect("Unable to parse module");
+    let module = env
+        .parse_module(
+            &include_bytes!("wasm/wasm_millis_to_seconds/wasm_millis_to_seconds.wasm")[..],
+        )
+        .expect("Unable to parse module");
 
-    let mut module = rt.instantiate(module).expect("Unable to load module");
-    module
-        .link_closure("time", "millis", |_, ()| Ok(MILLIS))
+    let mut instance = store.instantiate(module).expect("Unable to load module");
+    instance
+        .link_closure(&mut store, "time", "millis", |_, ()| Ok(MILLIS))
         .expect("Unable to link closure");
-    let func = module
-        .find_function::<(), u64>("seconds")
+    let func = instance
+        .find_function::<(), u64>(&store, "seconds")
         .expect("Unable to find function");
-    println!("{}ms in seconds is {:?}s.", MILLIS, func.call().unwrap());
-    assert_eq!(func.call(), Ok(MILLIS / 1000));
+    println!(
+        "{}ms in seconds is {:?}s.",
+        MILLIS,
+        func.call(&mut store).unwrap()
+    );
+    assert_eq!(func.call(&mut store), Ok(MILLIS / 1000));
 }
@@ -1,23 +1,29 @@
-use wasm3::{Environment, Instance};
+use std::io::Write;
+
+use wasm3::{
+    wasi::{self, WasiCtx},
+    Environment, Linker,
+};
 
-#[cfg(feature = "wasi")]
 fn main() {
     let env = Environment::new().expect("Unable to create environment");
-    let rt = env
-        .create_runtime(1024 * 60)
+    let mut wasi = WasiCtx::new();
+    wasi.arg("wasm_print")
+        .stdout(|bytes: &[u8]| std::io::stdout().write_all(bytes).unwrap());
+    let mut store = env
+        .create_store(1024 * 60, wasi)
         .expect("Unable to create runtime");
-    let module = Instance::parse(&env, &include_bytes!("wasm/wasm_print/wasm_print.wasm")[..])
+    let module = env
+        .parse_module(&include_bytes!("wasm/wasm_print/wasm_print.wasm")[..])
         .expect("Unable to parse module");
 
-    let mut module = rt.load_module(module).expect("Unable to load module");
-    module.link_wasi().expect("Failed to link wasi");
-    let func = module
-        .find_function::<(), ()>("_start")
+    let mut linker = Linker::new();
+    wasi::add_to_linker(&mut linker, |wasi| wasi).expect("Failed to link wasi");
+    let instance = linker
+        .instantiate(&mut store, module)
+        .expect("Unable to load module");
+    let func = instance
+        .find_function::<(), ()>(&store, "_start")
         .expect("Unable to find function");
-    func.call().unwrap();
-}
-
-#[cfg(not(feature = "wasi"))]
-fn main() {
-    panic!("This example requires the wasi feature");
+    func.call(&mut store).unwrap();
 }
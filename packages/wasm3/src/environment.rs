@@ -43,6 +43,22 @@ impl Environment {
         Store::new(self, stack_size, data)
     }
 
+    /// Creates a new store with the given stack size in slots and memory limit in pages.
+    /// See [`Store::with_memory_limit`].
+    ///
+    /// # Errors
+    ///
+    /// This function will error on memory allocation failure.
+    #[inline]
+    pub fn create_store_with_memory_limit<T>(
+        &self,
+        stack_size: u32,
+        max_memory_pages: u32,
+        data: T,
+    ) -> Result<Store<T>> {
+        Store::with_memory_limit(self, stack_size, max_memory_pages, data)
+    }
+
     /// Parses a wasm module from raw bytes.
     #[inline]
     pub fn parse_module(&self, bytes: impl Into<Cow<'static, [u8]>>) -> Result<Module> {
@@ -1,78 +1,263 @@
 //! Error related functionality of wasm3.
-use alloc::ffi::NulError;
-use core::{cmp, ffi::CStr, fmt, ptr};
+use alloc::{
+    ffi::NulError,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use core::{
+    any::Any,
+    cell::RefCell,
+    cmp,
+    ffi::{c_char, CStr},
+    fmt,
+    ptr::{self, NonNull},
+};
 
 use snafu::Snafu;
 
+use crate::module::{FunctionCompileError, UnresolvedImport};
+
 /// Result alias that uses [`Error`].
 pub type Result<T> = core::result::Result<T, Error>;
 /// Result alias that uses [`Trap`].
 pub type TrappedResult<T> = core::result::Result<T, Trap>;
 
-/// A wasm trap.
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub enum Trap {
-    /// Out of bounds memory access
-    OutOfBoundsMemoryAccess,
-    /// Division by zero
-    DivisionByZero,
-    /// Integer overflow
-    IntegerOverflow,
-    /// Integer conversion
-    IntegerConversion,
-    /// Indirect call type mismatch
-    IndirectCallTypeMismatch,
-    /// Table index out of range
-    TableIndexOutOfRange,
-    /// Exit
-    Exit,
-    /// Abort
-    Abort,
-    /// Unreachable
-    Unreachable,
-    /// Stack overflow
-    StackOverflow,
-}
-
-impl Trap {
-    /// Get the error message as a C string.
-    pub fn as_cstr(self) -> &'static CStr {
-        let ptr = unsafe {
-            match self {
-                Trap::OutOfBoundsMemoryAccess => ffi::m3Err_trapOutOfBoundsMemoryAccess,
-                Trap::DivisionByZero => ffi::m3Err_trapDivisionByZero,
-                Trap::IntegerOverflow => ffi::m3Err_trapIntegerOverflow,
-                Trap::IntegerConversion => ffi::m3Err_trapIntegerConversion,
-                Trap::IndirectCallTypeMismatch => ffi::m3Err_trapIndirectCallTypeMismatch,
-                Trap::TableIndexOutOfRange => ffi::m3Err_trapTableIndexOutOfRange,
-                Trap::Exit => ffi::m3Err_trapExit,
-                Trap::Abort => ffi::m3Err_trapAbort,
-                Trap::Unreachable => ffi::m3Err_trapUnreachable,
-                Trap::StackOverflow => ffi::m3Err_trapStackOverflow,
+macro_rules! wasm3_errors {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $(
+                $(#[$variant_meta:meta])*
+                $variant:ident = $ffi:ident => $hint:literal,
+            )*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $(
+                $(#[$variant_meta])*
+                $variant,
+            )*
+        }
+
+        impl $name {
+            const ALL: &'static [$name] = &[$($name::$variant),*];
+
+            /// Get the error message as a C string.
+            pub fn as_cstr(self) -> &'static CStr {
+                let ptr = unsafe {
+                    match self {
+                        $($name::$variant => ffi::$ffi,)*
+                    }
+                };
+
+                unsafe { CStr::from_ptr(ptr) }
+            }
+
+            /// Get the error message as a string.
+            pub fn as_str(&self) -> &'static str {
+                self.as_cstr()
+                    .to_str()
+                    .expect("expected wasm3 error to be valid utf-8")
             }
-        };
 
-        unsafe { CStr::from_ptr(ptr) }
+            /// A hint on how to fix the cause of this error.
+            pub fn hint(self) -> &'static str {
+                match self {
+                    $($name::$variant => $hint,)*
+                }
+            }
+
+            fn from_wasm3(error: Wasm3Error) -> Option<Self> {
+                Self::ALL
+                    .iter()
+                    .copied()
+                    .find(|kind| ptr::eq(kind.as_cstr().as_ptr(), error.0.as_ptr()))
+            }
+        }
+
+        impl core::error::Error for $name {}
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", self.as_str())
+            }
+        }
+    };
+}
+
+wasm3_errors! {
+    /// A wasm trap.
+    pub enum Trap {
+        /// Out of bounds memory access
+        OutOfBoundsMemoryAccess = m3Err_trapOutOfBoundsMemoryAccess
+            => "An array index or pointer is out of range.",
+        /// Division by zero
+        DivisionByZero = m3Err_trapDivisionByZero => "Check divisors for zero before dividing.",
+        /// Integer overflow
+        IntegerOverflow = m3Err_trapIntegerOverflow
+            => "A division overflowed, e.g. the minimum value divided by -1.",
+        /// Integer conversion
+        IntegerConversion = m3Err_trapIntegerConversion
+            => "A float that is NaN or out of range was converted to an integer.",
+        /// Indirect call type mismatch
+        IndirectCallTypeMismatch = m3Err_trapIndirectCallTypeMismatch
+            => "A function pointer was called with the wrong signature.",
+        /// Table index out of range
+        TableIndexOutOfRange = m3Err_trapTableIndexOutOfRange
+            => "A function pointer is invalid.",
+        /// Table element is null
+        TableElementIsNull = m3Err_trapTableElementIsNull
+            => "A null function pointer was called.",
+        /// Exit
+        Exit = m3Err_trapExit => "The program exited on its own.",
+        /// Abort
+        Abort = m3Err_trapAbort
+            => "The program or a host function aborted, see its output for details.",
+        /// Unreachable
+        Unreachable = m3Err_trapUnreachable
+            => "The program reached code marked as unreachable, e.g. after a panic.",
+        /// Stack overflow
+        StackOverflow = m3Err_trapStackOverflow
+            => "Check for unbounded recursion or increase the stack size of the store.",
+        /// Interrupted through an [`InterruptHandle`](crate::InterruptHandle) or a deadline
+        Interrupted = m3Err_trapInterrupted => "The program ran for too long and was stopped.",
     }
+}
 
-    /// Get the error message as a string.
-    pub fn as_str(&self) -> &'static str {
-        self.as_cstr()
-            .to_str()
-            .expect("expected wasm3 trap to be valid utf-8")
+wasm3_errors! {
+    /// An error in the binary of a module, found while parsing it.
+    pub enum ParseError {
+        /// The module targets another version of wasm
+        IncompatibleVersion = m3Err_incompatibleWasmVersion
+            => "The file is not a wasm module or was built for another wasm version.",
+        /// The module is malformed
+        Malformed = m3Err_wasmMalformed
+            => "The file is not valid wasm, check the compiler that produced it.",
+        /// A section is out of order
+        MisorderedSection = m3Err_misorderedWasmSection
+            => "Check the tool that last rewrote the module, such as an optimizer or linker.",
+        /// The module ended early
+        Underrun = m3Err_wasmUnderrun
+            => "The module is truncated, try uploading it again.",
+        /// The module continues past its end
+        Overrun = m3Err_wasmOverrun
+            => "The file continues after the module, check that only the module was uploaded.",
+        /// An initializer expression is missing
+        MissingInitExpr = m3Err_wasmMissingInitExpr
+            => "A global or data segment has no initializer, check the compiler that produced it.",
+        /// An integer is encoded with too many bytes
+        LebOverflow = m3Err_lebOverflow
+            => "An integer is encoded with too many bytes, check the compiler that produced it.",
+        /// A name is not valid UTF-8
+        MissingUtf8 = m3Err_missingUTF8
+            => "Rename the import, export or function whose name isn't valid UTF-8.",
+        /// A section ended early
+        SectionUnderrun = m3Err_wasmSectionUnderrun
+            => "A section is cut off, the upload was likely interrupted, try uploading it again.",
+        /// A section continues past its end
+        SectionOverrun = m3Err_wasmSectionOverrun
+            => "A section has trailing bytes, check the tool that last rewrote the module.",
+        /// A type index is invalid
+        InvalidTypeId = m3Err_invalidTypeId
+            => "The module uses a type wasm3 doesn't support, such as SIMD vectors or references.",
+        /// The module defines more than one memory
+        TooManyMemorySections = m3Err_tooManyMemorySections
+            => "Build the module without the multi-memory proposal.",
+        /// A function has too many parameters or results
+        TooManyArgsRets = m3Err_tooManyArgsRets
+            => "Reduce the number of parameters or results of the function.",
     }
 }
 
-impl cmp::PartialEq<Wasm3Error> for Trap {
-    fn eq(&self, err: &Wasm3Error) -> bool {
-        ptr::eq(err.0.as_ptr(), self.as_cstr().as_ptr())
+wasm3_errors! {
+    /// An error linking functions into a module or a module into a store.
+    pub enum LinkError {
+        /// The module has not been loaded into a store
+        ModuleNotLinked = m3Err_moduleNotLinked => "Instantiate the module before using it.",
+        /// The module has already been loaded into a store
+        ModuleAlreadyLinked = m3Err_moduleAlreadyLinked
+            => "A module can only be instantiated once, parse it again instead.",
+        /// An imported function has not been linked
+        FunctionImportMissing = m3Err_functionImportMissing
+            => "Link every function the module imports before calling into it.",
+        /// A function signature is malformed or doesn't match the import
+        MalformedFunctionSignature = m3Err_malformedFunctionSignature
+            => "Check that the host function's signature matches the import.",
     }
 }
 
-impl core::error::Error for Trap {}
-impl fmt::Display for Trap {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.as_str())
+wasm3_errors! {
+    /// An error compiling a function of a module.
+    pub enum CompileError {
+        /// No compiler exists for an opcode
+        NoCompiler = m3Err_noCompiler
+            => "The module uses an instruction wasm3 doesn't support, build it for plain wasm32.",
+        /// An opcode is unknown
+        UnknownOpcode = m3Err_unknownOpcode
+            => "The module uses an unknown instruction, disable target features such as simd128.",
+        /// An opcode is not allowed in this context
+        RestrictedOpcode = m3Err_restrictedOpcode
+            => "An instruction is used where it isn't allowed, check the compiler that built it.",
+        /// A function needs too much stack, e.g. because of too many locals
+        FunctionStackOverflow = m3Err_functionStackOverflow
+            => "Split up the function or reduce the number of its locals.",
+        /// A function pops more values than it pushed
+        FunctionStackUnderrun = m3Err_functionStackUnderrun
+            => "A function uses values it never produced, check the compiler that produced it.",
+        /// Compiled code could not be allocated
+        MallocFailedCodePage = m3Err_mallocFailedCodePage
+            => "Free up memory or reduce the size of the module.",
+        /// An immutable global is assigned to
+        SettingImmutableGlobal = m3Err_settingImmutableGlobal
+            => "A function assigns to a constant global, check the compiler that produced it.",
+        /// Operand types don't match an instruction
+        TypeMismatch = m3Err_typeMismatch
+            => "An instruction gets values of the wrong type, check the compiler that produced it.",
+        /// The number of values doesn't match a block's type
+        TypeCountMismatch = m3Err_typeCountMismatch
+            => "A block leaves the wrong number of values, check the compiler that produced it.",
+    }
+}
+
+wasm3_errors! {
+    /// An error of a running store that isn't a trap.
+    pub enum RuntimeError {
+        /// Memory allocation failed
+        MallocFailed = m3Err_mallocFailed => "Free up memory or reduce the memory the module uses.",
+        /// A function has not been compiled
+        MissingCompiledCode = m3Err_missingCompiledCode
+            => "Instantiate the module before calling its functions.",
+        /// Memory can't grow any further
+        WasmMemoryOverflow = m3Err_wasmMemoryOverflow
+            => "The program needs more memory than the store allows.",
+        /// A global's memory has not been allocated
+        GlobalMemoryNotAllocated = m3Err_globalMemoryNotAllocated
+            => "Instantiate the module before accessing its globals.",
+        /// A global index is out of bounds
+        GlobalIndexOutOfBounds = m3Err_globaIndexOutOfBounds
+            => "A function uses a global that doesn't exist, check the compiler that produced it.",
+        /// The wrong number of arguments was passed to a function
+        ArgumentCountMismatch = m3Err_argumentCountMismatch
+            => "Pass as many arguments as the function has parameters.",
+        /// An argument has the wrong type
+        ArgumentTypeMismatch = m3Err_argumentTypeMismatch
+            => "Check the argument types against the function's signature.",
+        /// A global could not be found
+        GlobalLookupFailed = m3Err_globalLookupFailed
+            => "Check the name of the global against the module's exports.",
+        /// A global has another type
+        GlobalTypeMismatch = m3Err_globalTypeMismatch
+            => "Check the type of the global against the module.",
+        /// An immutable global is assigned to
+        GlobalNotMutable = m3Err_globalNotMutable => "Only mutable globals can be changed.",
+    }
+}
+
+impl cmp::PartialEq<Wasm3Error> for Trap {
+    fn eq(&self, err: &Wasm3Error) -> bool {
+        ptr::eq(err.0.as_ptr(), self.as_cstr().as_ptr())
     }
 }
 
@@ -86,6 +271,11 @@ impl Wasm3Error {
         ptr::eq(trap.as_cstr().as_ptr(), self.0.as_ptr())
     }
 
+    /// Returns the trap this error is, if any.
+    pub fn as_trap(self) -> Option<Trap> {
+        Trap::from_wasm3(self)
+    }
+
     /// Get the error message as a string.
     pub fn as_str(&self) -> &'static str {
         self.0
@@ -107,26 +297,327 @@ impl fmt::Display for Wasm3Error {
     }
 }
 
+/// A wasm function that was running when a trap was raised.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    /// The index of the function in its module, counting imported functions first.
+    pub function_index: u32,
+    /// The debug name of the function, taken from the name section or an export or import.
+    pub name: Option<String>,
+    /// The offset in the module's binary of the instruction that was executing.
+    pub module_offset: u32,
+}
+
+/// Details wasm3 recorded about the last error of a store, see [`Store::take_error_info`].
+///
+/// [`Store::take_error_info`]: crate::Store::take_error_info
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ErrorInfo {
+    /// The error that occurred.
+    pub result: Option<Wasm3Error>,
+    /// A description of the error.
+    pub message: Option<String>,
+    /// The name of the module the error occurred in.
+    pub module: Option<String>,
+    /// The index of the function the error occurred in, counting imported functions first.
+    pub function_index: Option<u32>,
+    /// The debug name of the function the error occurred in.
+    pub function_name: Option<String>,
+    /// The file of the wasm3 sources that raised the error.
+    pub file: Option<String>,
+    /// The line in [`Self::file`] that raised the error.
+    pub line: u32,
+}
+
+impl ErrorInfo {
+    /// # Safety
+    ///
+    /// `raw` must have been filled by `m3_GetErrorInfo`.
+    pub(crate) unsafe fn from_raw(raw: &ffi::M3ErrorInfo) -> Option<Self> {
+        let result = NonNull::new(raw.result.cast_mut())
+            .map(|result| Wasm3Error(unsafe { CStr::from_ptr(result.as_ptr()) }));
+        let message = unsafe { string_from_ptr(raw.message) };
+        if result.is_none() && message.is_none() {
+            return None;
+        }
+
+        let function = NonNull::new(raw.function);
+        Some(ErrorInfo {
+            result,
+            message,
+            module: NonNull::new(raw.module).and_then(|module| unsafe {
+                string_from_ptr(ffi::m3_GetModuleName(module.as_ptr()))
+            }),
+            function_index: function
+                .map(|function| unsafe { ffi::m3_GetFunctionIndex(function.as_ptr()) }),
+            function_name: function.and_then(|function| unsafe {
+                string_from_ptr(ffi::m3_GetFunctionDebugName(function.as_ptr()))
+            }),
+            file: unsafe { string_from_ptr(raw.file) },
+            line: raw.line,
+        })
+    }
+}
+
+impl fmt::Display for ErrorInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.message, self.result) {
+            (Some(message), _) => write!(f, "{}", message)?,
+            (None, Some(result)) => write!(f, "{}", result)?,
+            (None, None) => write!(f, "unknown error")?,
+        }
+        if let Some(index) = self.function_index {
+            let name = self.function_name.as_deref().unwrap_or("<unnamed>");
+            write!(f, " in function {} (#{})", name, index)?;
+        }
+        if let Some(module) = &self.module {
+            write!(f, " of module {}", module)?;
+        }
+        if let Some(file) = &self.file {
+            write!(f, " [{}:{}]", file, self.line)?;
+        }
+        Ok(())
+    }
+}
+
+/// An error returned by a host function, which aborts the wasm call that called it.
+///
+/// This is either a [`Trap`] or a custom error such as an `anyhow::Error`, which is handed back
+/// unchanged through [`Error::Host`] by the call into wasm that failed.
+#[derive(Clone)]
+pub struct HostError(HostErrorKind);
+
+#[derive(Clone)]
+enum HostErrorKind {
+    Trap(Trap),
+    Custom(Arc<dyn Payload>),
+}
+
+// `core::error::Error` would be the obvious bound, but `anyhow::Error` doesn't implement it
+trait Payload: Any + fmt::Display + fmt::Debug + Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<E: Any + fmt::Display + fmt::Debug + Send + Sync> Payload for E {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Where a host function leaves its custom error for the call that fails with it.
+/// Every store owns one and passes it to wasm3 as the runtime's userdata.
+pub(crate) type HostErrorSlot = RefCell<Option<HostError>>;
+
+impl HostError {
+    /// Wraps a custom error.
+    pub fn new<E>(error: E) -> Self
+    where
+        E: fmt::Display + fmt::Debug + Send + Sync + 'static,
+    {
+        HostError(HostErrorKind::Custom(Arc::new(error)))
+    }
+
+    /// Returns the trap this error is, if any.
+    pub fn trap(&self) -> Option<Trap> {
+        match self.0 {
+            HostErrorKind::Trap(trap) => Some(trap),
+            HostErrorKind::Custom(_) => None,
+        }
+    }
+
+    /// Returns the custom error if it is of type `E`.
+    pub fn downcast_ref<E: 'static>(&self) -> Option<&E> {
+        match &self.0 {
+            HostErrorKind::Trap(_) => None,
+            HostErrorKind::Custom(payload) => (**payload).as_any().downcast_ref(),
+        }
+    }
+
+    /// Converts the error into the result a host function returns to wasm3, storing custom errors
+    /// in the slot of `runtime`.
+    ///
+    /// # Safety
+    ///
+    /// `runtime` must belong to a [`Store`](crate::Store).
+    pub(crate) unsafe fn into_ffi(self, runtime: ffi::IM3Runtime) -> ffi::M3Result {
+        match self.0 {
+            HostErrorKind::Trap(trap) => trap.as_cstr().as_ptr(),
+            custom => {
+                let slot = unsafe { ffi::m3_GetUserData(runtime) } as *const HostErrorSlot;
+                unsafe { (*slot).replace(Some(HostError(custom))) };
+                unsafe { ffi::m3Err_hostError }
+            }
+        }
+    }
+}
+
+impl From<Trap> for HostError {
+    fn from(trap: Trap) -> Self {
+        HostError(HostErrorKind::Trap(trap))
+    }
+}
+
+/// Keeps the payload of an error returned by a nested call, so it can be passed on with `?`.
+impl From<Error> for HostError {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Trap { trap, .. } => trap.into(),
+            Error::Host { error } => error,
+            error => HostError::new(error),
+        }
+    }
+}
+
+impl PartialEq for HostError {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (HostErrorKind::Trap(a), HostErrorKind::Trap(b)) => a == b,
+            (HostErrorKind::Custom(a), HostErrorKind::Custom(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+impl Eq for HostError {}
+
+impl fmt::Debug for HostError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            HostErrorKind::Trap(trap) => fmt::Debug::fmt(trap, f),
+            HostErrorKind::Custom(payload) => fmt::Debug::fmt(payload, f),
+        }
+    }
+}
+
+impl fmt::Display for HostError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            HostErrorKind::Trap(trap) => fmt::Display::fmt(trap, f),
+            HostErrorKind::Custom(payload) => fmt::Display::fmt(payload, f),
+        }
+    }
+}
+
 /// Error returned by wasm3-rs.
 #[derive(Clone, Debug, Snafu, PartialEq, Eq)]
 #[snafu(visibility(pub(crate)))]
+#[non_exhaustive]
 pub enum Error {
-    /// An error originating from wasm3 itself may or may not be a trap.
-    #[snafu(transparent)]
-    Wasm3 {
-        /// The source of the error.
-        source: Wasm3Error,
+    /// The module could not be parsed.
+    #[snafu(display("failed to parse module: {kind}. {}", kind.hint()))]
+    Parse {
+        /// What went wrong.
+        kind: ParseError,
+    },
+    /// A module or function could not be linked.
+    #[snafu(display("failed to link: {kind}. {}", kind.hint()))]
+    Link {
+        /// What went wrong.
+        kind: LinkError,
+    },
+    /// A function of a module could not be compiled.
+    #[snafu(display("failed to compile function: {kind}. {}", kind.hint()))]
+    Compile {
+        /// What went wrong.
+        kind: CompileError,
+    },
+    /// Functions of a module could not be compiled ahead of time, see
+    /// [`Instance::compile_all`](crate::Instance::compile_all).
+    #[snafu(display("failed to compile functions: {}", join(functions)))]
+    CompileFunctions {
+        /// Every function that could not be compiled, in the order the module defines them.
+        functions: Vec<FunctionCompileError>,
+    },
+    /// The store failed in a way that isn't a trap.
+    #[snafu(display("{kind}. {}", kind.hint()))]
+    Runtime {
+        /// What went wrong.
+        kind: RuntimeError,
+    },
+    /// A call was aborted by a trap.
+    #[snafu(display("{trap}. {}", trap.hint()))]
+    Trap {
+        /// The trap that was raised.
+        trap: Trap,
+        /// The wasm functions that were running, innermost first.
+        /// This is only recorded with the `record-backtraces` feature and empty otherwise.
+        #[snafu(backtrace(false))]
+        backtrace: Vec<Frame>,
     },
+    /// A call was aborted by a host function that returned a custom error.
+    #[snafu(display("{error}"))]
+    Host {
+        /// The error the host function returned.
+        error: HostError,
+    },
+    /// A host function linked through [`Instance::link_closure`] or [`Instance::link_dynamic`]
+    /// was called again while it was still running, e.g. by a guest function it called.
+    ///
+    /// Their closures are `FnMut` and can't be re-entered, unlike the ones defined through a
+    /// [`Linker`](crate::Linker).
+    ///
+    /// [`Instance::link_closure`]: crate::Instance::link_closure
+    /// [`Instance::link_dynamic`]: crate::Instance::link_dynamic
+    HostFunctionReentered,
     /// A function has been found but its signature didn't match.
     InvalidFunctionSignature,
     /// The specified function could not be found.
     FunctionNotFound,
+    /// A global has been found but its type didn't match.
+    InvalidGlobalType,
+    /// The specified global could not be found.
+    GlobalNotFound,
+    /// An attempt was made to change an immutable global.
+    ImmutableGlobal,
+    /// A module's initial memory is larger than the store's memory limit.
+    MemoryLimitExceeded,
     /// The specified module could not be found.
     ModuleNotFound,
+    /// A [`Snapshot`](crate::Snapshot) was restored into a store running other modules than the
+    /// one it was taken of.
+    SnapshotMismatch,
+    /// Bytes could not be decoded as a [`Snapshot`](crate::Snapshot).
+    InvalidSnapshot,
+    /// A [`Linker`](crate::Linker) already defines a function of this name.
+    #[snafu(display("`{module}::{name}` is already defined"))]
+    AlreadyDefined {
+        /// The module of the function.
+        module: String,
+        /// The name of the function.
+        name: String,
+    },
+    /// A module imports functions that have not been linked.
+    #[snafu(display("unresolved imports: {}", join(imports)))]
+    UnresolvedImports {
+        /// Every import that has not been linked, in the order the module declares them.
+        imports: Vec<UnresolvedImport>,
+    },
+    /// A function defined in a [`Linker`](crate::Linker) or exported by another instance could not
+    /// be linked to an import.
+    #[snafu(display("failed to link import `{module}::{name}`"))]
+    LinkImport {
+        /// The module of the import.
+        module: String,
+        /// The name of the import.
+        name: String,
+        /// The source of the error.
+        source: alloc::boxed::Box<Error>,
+    },
     /// The modules environment did not match the runtime's environment.
     ModuleLoadEnvMismatch,
     /// The specified store did not match the store the data was created with.
     StoreMismatch,
+    /// The instance the data belongs to has been [unloaded](crate::Store::unload).
+    InstanceUnloaded,
+    /// An instance can't be [unloaded](crate::Store::unload) while another instance is
+    /// [linked](crate::Instance::link_instance) to it.
+    InstanceInUse,
+    /// An error of wasm3 that none of the other variants cover, such as one raised by a host
+    /// function of wasm3 itself.
+    #[snafu(display("{source}"))]
+    Wasm3 {
+        /// The source of the error.
+        source: Wasm3Error,
+    },
     /// A null byte was found in a string.
     #[snafu(transparent)]
     Nul {
@@ -136,20 +627,256 @@ pub enum Error {
 }
 
 impl Error {
+    /// Returns the trap that aborted a call, if this error is one.
+    pub fn as_trap(&self) -> Option<Trap> {
+        match self {
+            Error::Trap { trap, .. } => Some(*trap),
+            _ => None,
+        }
+    }
+
     /// # Safety
     ///
     /// `ptr` must be a valid pointer to a null-terminated string.
     pub(crate) unsafe fn from_ffi(ptr: ffi::M3Result) -> Result<()> {
         if ptr.is_null() {
             Ok(())
-        } else if unsafe { ptr == ffi::m3Err_functionLookupFailed } {
-            FunctionNotFoundSnafu.fail()
         } else {
             unsafe { Err(Wasm3Error(CStr::from_ptr(ptr)).into()) }
         }
     }
 
+    /// Like [`Self::from_ffi`], but attaches the backtrace of `runtime` to traps and takes the
+    /// error of a failed host function out of its slot.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be the result of the last call in `runtime`, which must belong to a
+    /// [`Store`](crate::Store).
+    pub(crate) unsafe fn from_call(runtime: ffi::IM3Runtime, ptr: ffi::M3Result) -> Result<()> {
+        if ptr::eq(ptr, unsafe { ffi::m3Err_hostError }) {
+            let slot = unsafe { ffi::m3_GetUserData(runtime) } as *const HostErrorSlot;
+            if let Some(error) = unsafe { (*slot).take() } {
+                return Err(Error::Host { error });
+            }
+        }
+        match unsafe { Self::from_ffi(ptr) } {
+            Err(Error::Trap { trap, .. }) => Err(Error::Trap {
+                trap,
+                backtrace: unsafe { backtrace(runtime) },
+            }),
+            result => result,
+        }
+    }
+
     pub(crate) fn malloc_error() -> Self {
         unsafe { Self::from_ffi(ffi::m3Err_mallocFailed).unwrap_err() }
     }
 }
+
+impl From<Wasm3Error> for Error {
+    fn from(error: Wasm3Error) -> Self {
+        if let Some(trap) = Trap::from_wasm3(error) {
+            Error::Trap {
+                trap,
+                backtrace: Vec::new(),
+            }
+        } else if let Some(kind) = ParseError::from_wasm3(error) {
+            Error::Parse { kind }
+        } else if let Some(kind) = LinkError::from_wasm3(error) {
+            Error::Link { kind }
+        } else if let Some(kind) = CompileError::from_wasm3(error) {
+            Error::Compile { kind }
+        } else if let Some(kind) = RuntimeError::from_wasm3(error) {
+            Error::Runtime { kind }
+        } else if ptr::eq(error.0.as_ptr(), unsafe { ffi::m3Err_functionLookupFailed }) {
+            Error::FunctionNotFound
+        } else {
+            Error::Wasm3 { source: error }
+        }
+    }
+}
+
+impl From<Trap> for Error {
+    fn from(trap: Trap) -> Self {
+        Wasm3Error(trap.as_cstr()).into()
+    }
+}
+
+fn join<T: fmt::Display>(items: &[T]) -> String {
+    let items: Vec<_> = items.iter().map(ToString::to_string).collect();
+    items.join(", ")
+}
+
+/// Copies a C string, treating empty strings like null pointers.
+pub(crate) unsafe fn string_from_ptr(ptr: *const c_char) -> Option<String> {
+    if ptr.is_null() {
+        return None;
+    }
+    let string = unsafe { CStr::from_ptr(ptr) }.to_string_lossy();
+    (!string.is_empty()).then(|| string.to_string())
+}
+
+unsafe fn backtrace(runtime: ffi::IM3Runtime) -> Vec<Frame> {
+    let info = unsafe { ffi::m3_GetBacktrace(runtime) };
+    if info.is_null() {
+        return Vec::new();
+    }
+
+    let mut frames = Vec::new();
+    let mut frame = unsafe { (*info).frames };
+    // a truncated backtrace ends at its last frame like any other
+    while !frame.is_null() {
+        let raw = unsafe { &*frame };
+        if !raw.function.is_null() {
+            frames.push(Frame {
+                function_index: unsafe { ffi::m3_GetFunctionIndex(raw.function) },
+                name: unsafe { string_from_ptr(ffi::m3_GetFunctionDebugName(raw.function)) },
+                module_offset: raw.moduleOffset,
+            });
+        }
+        frame = raw.next;
+    }
+    frames
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wasm3_error_as_trap() {
+        for &trap in Trap::ALL.iter() {
+            assert_eq!(Wasm3Error(trap.as_cstr()).as_trap(), Some(trap));
+        }
+        let error = unsafe { Wasm3Error(CStr::from_ptr(ffi::m3Err_mallocFailed)) };
+        assert_eq!(error.as_trap(), None);
+    }
+
+    #[test]
+    fn classify_wasm3_errors() {
+        let error = |ptr| unsafe { Error::from_ffi(ptr).unwrap_err() };
+        unsafe {
+            let trap = error(ffi::m3Err_trapDivisionByZero);
+            assert_eq!(trap.as_trap(), Some(Trap::DivisionByZero));
+            assert_eq!(
+                error(ffi::m3Err_wasmMalformed),
+                Error::Parse {
+                    kind: ParseError::Malformed
+                }
+            );
+            assert_eq!(
+                error(ffi::m3Err_functionImportMissing),
+                Error::Link {
+                    kind: LinkError::FunctionImportMissing
+                }
+            );
+            assert_eq!(
+                error(ffi::m3Err_unknownOpcode),
+                Error::Compile {
+                    kind: CompileError::UnknownOpcode
+                }
+            );
+            assert_eq!(
+                Error::malloc_error(),
+                Error::Runtime {
+                    kind: RuntimeError::MallocFailed
+                }
+            );
+            assert_eq!(
+                error(ffi::m3Err_functionLookupFailed),
+                Error::FunctionNotFound
+            );
+            assert_eq!(error(ffi::m3Err_wasmMalformed).as_trap(), None);
+            assert!(matches!(
+                error(b"custom\0".as_ptr().cast()),
+                Error::Wasm3 { .. }
+            ));
+        }
+    }
+
+    #[test]
+    fn error_display_hint() {
+        let error = Error::from(Trap::DivisionByZero);
+        assert_eq!(
+            error.to_string(),
+            alloc::format!("{}. {}", Trap::DivisionByZero, Trap::DivisionByZero.hint())
+        );
+    }
+
+    #[test]
+    fn host_error_payload() {
+        #[derive(Debug)]
+        struct NotAMotor(u32);
+        impl fmt::Display for NotAMotor {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "port {} is not a motor", self.0)
+            }
+        }
+
+        let error = HostError::new(NotAMotor(7));
+        assert_eq!(error.to_string(), "port 7 is not a motor");
+        assert_eq!(error.downcast_ref::<NotAMotor>().map(|e| e.0), Some(7));
+        assert!(error.downcast_ref::<String>().is_none());
+        assert_eq!(error.trap(), None);
+
+        // passing the error on through a nested call keeps the payload
+        let nested = HostError::from(Error::Host {
+            error: error.clone(),
+        });
+        assert_eq!(nested, error);
+        assert_eq!(
+            HostError::from(Error::from(Trap::Abort)).trap(),
+            Some(Trap::Abort)
+        );
+    }
+
+    #[test]
+    fn unresolved_imports_display() {
+        use crate::{FuncType, ValueType};
+
+        let error = Error::UnresolvedImports {
+            imports: alloc::vec![
+                UnresolvedImport {
+                    module: "vex".into(),
+                    name: "vexDeviceMotorVelocitySet".into(),
+                    ty: Some(FuncType::new([ValueType::I32, ValueType::I32], [])),
+                },
+                UnresolvedImport {
+                    module: "env".into(),
+                    name: "v128".into(),
+                    ty: None,
+                },
+            ],
+        };
+        assert_eq!(
+            error.to_string(),
+            "unresolved imports: `vex::vexDeviceMotorVelocitySet`: (i32, i32) -> (), \
+             `env::v128`: <unsupported signature>"
+        );
+    }
+
+    #[test]
+    fn error_info_display() {
+        let mut info = ErrorInfo {
+            result: Some(Wasm3Error(Trap::Unreachable.as_cstr())),
+            message: None,
+            module: Some("app".into()),
+            function_index: Some(7),
+            function_name: Some("main".into()),
+            file: Some("m3_exec.h".into()),
+            line: 42,
+        };
+        let expected = alloc::format!(
+            "{} in function main (#7) of module app [m3_exec.h:42]",
+            Trap::Unreachable
+        );
+        assert_eq!(info.to_string(), expected);
+
+        info.message = Some("oops".into());
+        info.function_name = None;
+        info.module = None;
+        info.file = None;
+        assert_eq!(info.to_string(), "oops in function <unnamed> (#7)");
+    }
+}
@@ -1,80 +1,159 @@
-use alloc::rc::Rc;
+use alloc::{boxed::Box, vec, vec::Vec};
 use core::{
-    cell::{Ref, RefCell, RefMut},
     cmp::{Eq, PartialEq},
     ffi::{c_void, CStr},
     hash::{Hash, Hasher},
     marker::PhantomData,
-    ptr::NonNull,
+    ptr::{self, NonNull},
     slice, str,
 };
 
 use ffi::{M3Function, M3Module};
 
 use crate::{
-    error::{Error, Result},
-    store::{AsContext, AsContextMut, StoreContext, StoreContextMut, StoredData},
-    WasmArg, WasmArgs, WasmType,
+    error::{Error, HostError, Result},
+    interrupt::{self, Deadline},
+    memory::Memory,
+    module::LoadedModule,
+    store::{self, AsContext, AsContextMut, StoreContext, StoreContextMut, StoredData},
+    FuncType, Value, WasmArg, WasmArgs,
 };
 
+// enough slots for the largest tuple `WasmArgs` is implemented for, even if every value in it
+// takes up two slots like `WasmSlice`
+const MAX_SLOTS: usize = 32;
+
 /// Calling Context for a host function.
+///
+/// A host function may call back into the guest by passing `&mut ctx` to [`Function::call`].
+/// The guest may grow its memory during that call, so the memory views and data references handed
+/// out by the context borrow it and can't be held across the call:
+///
+/// ```compile_fail
+/// # use wasm3::{error::HostError, CallContext, Function};
+/// fn host(mut ctx: CallContext<'_, ()>, guest: Function<(), ()>) -> Result<u8, HostError> {
+///     let memory = ctx.memory();
+///     guest.call(&mut ctx)?;
+///     Ok(memory[0])
+/// }
+/// ```
 pub struct CallContext<'cc, T> {
     raw: NonNull<ffi::M3Runtime>,
-    data: Rc<RefCell<T>>,
     _pd: PhantomData<fn(&'cc ()) -> &'cc ()>,
+    data: PhantomData<&'cc mut T>,
 }
 
 impl<'cc, T> CallContext<'cc, T> {
-    pub(crate) fn from_raw(raw: NonNull<ffi::M3Runtime>, data: Rc<RefCell<T>>) -> Self {
+    /// # Safety
+    ///
+    /// `raw` must be the runtime of a [`Store<T>`](crate::Store) whose call is running the host
+    /// function the context is handed to.
+    pub(crate) unsafe fn from_raw(raw: NonNull<ffi::M3Runtime>) -> Self {
         Self {
             raw,
-            data,
             _pd: PhantomData,
+            data: PhantomData,
         }
     }
 
     /// Returns the raw memory of the runtime associated with this context.
-    ///
-    /// # Safety
-    ///
-    /// The returned pointer may get invalidated when wasm function objects are called due to reallocations.
     pub fn memory(&self) -> &[u8] {
         self.as_context().memory()
     }
 
     /// Returns the raw memory of the runtime associated with this context.
-    ///
-    /// # Safety
-    ///
-    /// The returned pointer may get invalidated when wasm function objects are called due to reallocations.
     pub fn memory_mut(&mut self) -> &mut [u8] {
         let mut memory_size = 0u32;
         let data = unsafe { ffi::m3_GetMemory(self.raw.as_ptr(), &mut memory_size, 0) };
         unsafe { slice::from_raw_parts_mut(data, memory_size as usize) }
     }
 
+    /// Returns a bounds-checked view into the memory of the runtime associated with this context.
+    pub fn memory_view(&mut self) -> Memory<'_> {
+        Memory::new(self.memory_mut())
+    }
+
     /// Returns a reference to the data associated with this context.
-    pub fn data(&self) -> Ref<'_, T> {
-        self.data.borrow()
+    pub fn data(&self) -> &T {
+        unsafe { &*store::data_ptr(self.raw) }
     }
 
     /// Returns a mutable reference to the data associated with this context.
-    pub fn data_mut(&mut self) -> RefMut<'_, T> {
-        self.data.borrow_mut()
+    pub fn data_mut(&mut self) -> &mut T {
+        unsafe { &mut *store::data_ptr(self.raw) }
+    }
+
+    /// Returns a bounds-checked view into the memory together with a mutable reference to the
+    /// data, for host functions that need both at once.
+    pub fn memory_and_data_mut(&mut self) -> (Memory<'_>, &mut T) {
+        let mut memory_size = 0u32;
+        let data = unsafe { ffi::m3_GetMemory(self.raw.as_ptr(), &mut memory_size, 0) };
+        let memory = unsafe { slice::from_raw_parts_mut(data, memory_size as usize) };
+        (Memory::new(memory), unsafe {
+            &mut *store::data_ptr(self.raw)
+        })
     }
 }
 
 impl<T> AsContext for CallContext<'_, T> {
     type Data = T;
     fn as_context(&self) -> StoreContext<'_, T> {
-        StoreContext::new(self.raw, self.data.clone())
+        unsafe { StoreContext::from_raw(self.raw) }
     }
 }
 
 impl<T> AsContextMut for CallContext<'_, T> {
     fn as_context_mut(&mut self) -> StoreContextMut<'_, T> {
-        StoreContextMut::new(self.raw, self.data.clone())
+        unsafe { StoreContextMut::from_raw(self.raw) }
+    }
+}
+
+fn function_module(function: NonNull<M3Function>) -> NonNull<M3Module> {
+    NonNull::new(unsafe { ffi::m3_GetFunctionModule(function.as_ptr()) })
+        .expect("functions belong to a module")
+}
+
+/// Runs the start function of `module` unless it already ran.
+///
+/// Looking up functions leaves this to their first call, as the start function may call the host,
+/// which needs the store borrowed mutably.
+///
+/// # Safety
+///
+/// `module` must be loaded into `runtime`, whose store is borrowed mutably.
+pub(crate) unsafe fn run_start(runtime: ffi::IM3Runtime, module: &LoadedModule) -> Result<()> {
+    if module.started.get() {
+        return Ok(());
+    }
+    unsafe { Error::from_call(runtime, ffi::m3_RunStart(module.inner.as_ptr()))? };
+    module.started.set(true);
+    Ok(())
+}
+
+/// Runs the host function `f`, whose frame on the wasm stack ends at `frame_end`, with calls into
+/// wasm starting above it.
+///
+/// wasm3 starts every call at the beginning of the stack, so calls `f` makes back into wasm would
+/// otherwise overwrite the frames of the calls that are still running. Their backtraces are kept
+/// apart as well, so that a trap of the running call doesn't list the frames of a nested one.
+///
+/// # Safety
+///
+/// `frame_end` must point to the wasm stack of `runtime`, past the frame of the host function
+/// `runtime` is running.
+pub(crate) unsafe fn with_call_stack<R>(
+    runtime: ffi::IM3Runtime,
+    frame_end: *mut u64,
+    f: impl FnOnce() -> R,
+) -> R {
+    let previous = unsafe { ffi::m3_SetCallStack(runtime, frame_end.cast()) };
+    let backtrace = unsafe { ffi::m3_TakeBacktrace(runtime) };
+    let result = f();
+    unsafe {
+        ffi::m3_RestoreBacktrace(runtime, backtrace);
+        ffi::m3_SetCallStack(runtime, previous);
     }
+    result
 }
 
 // redefine of ffi::RawCall without the Option<T> around it
@@ -86,6 +165,17 @@ pub type RawCall = unsafe extern "C" fn(
     _mem: *mut c_void,
 ) -> *const c_void;
 
+/// A boxed host function whose signature is only known at runtime.
+/// It receives the call's parameters and fills in the results, which start out as zero values of
+/// the correct types.
+pub type DynHostFunction<T> = Box<
+    dyn for<'cc> FnMut(
+        CallContext<'cc, T>,
+        &[Value],
+        &mut [Value],
+    ) -> core::result::Result<(), HostError>,
+>;
+
 /// A callable wasm3 function.
 /// This has a generic `call` function for up to 26 parameters emulating an overloading behaviour without having to resort to tuples.
 /// These are hidden to not pollute the documentation.
@@ -111,7 +201,7 @@ impl<Args, Ret> Hash for Function<Args, Ret> {
 impl<Args, Ret> Function<Args, Ret>
 where
     Args: WasmArgs,
-    Ret: WasmType,
+    Ret: WasmArgs,
 {
     /// The name of this function.
     pub fn name(&self, ctx: impl AsContext) -> Result<&str> {
@@ -121,19 +211,12 @@ where
             Ok(cstr.to_str().expect("function name is not valid utf-8"))
         }
     }
-
-    /// The module containing this function.
-    pub(crate) fn instance(&self, ctx: impl AsContext) -> Result<Option<NonNull<M3Module>>> {
-        let ctx = ctx.as_context();
-        let module = unsafe { ffi::m3_GetFunctionModule(self.raw.get(&ctx)?.as_ptr()) };
-        Ok(NonNull::new(module))
-    }
 }
 
 impl<Args, Ret> Function<Args, Ret>
 where
     Args: WasmArgs,
-    Ret: WasmType,
+    Ret: WasmArgs,
 {
     fn validate_sig(raw: NonNull<M3Function>) -> bool {
         let num_args = unsafe { ffi::m3_GetArgCount(raw.as_ptr()) };
@@ -143,14 +226,8 @@ where
         }
 
         let num_rets = unsafe { ffi::m3_GetRetCount(raw.as_ptr()) };
-        match num_rets {
-            0 => Ret::TYPE_INDEX == ffi::M3ValueType::c_m3Type_none,
-            1 => {
-                let ret = unsafe { ffi::m3_GetRetType(raw.as_ptr(), 0) };
-                Ret::TYPE_INDEX == ret
-            }
-            _ => false,
-        }
+        let rets = (0..num_rets).map(|i| unsafe { ffi::m3_GetRetType(raw.as_ptr(), i) });
+        Ret::validate_types(rets)
     }
 
     #[inline]
@@ -162,17 +239,63 @@ where
             return Err(Error::InvalidFunctionSignature);
         }
         Ok(Function {
-            raw: StoredData::new(store, raw),
+            raw: StoredData::new(store, raw, function_module(raw)),
             _pd: PhantomData,
         })
     }
 
+    /// Calls this function with its arguments as a tuple, interrupting it with
+    /// [`Trap::Interrupted`] once `deadline` has passed.
+    ///
+    /// The deadline replaces that of an outer call for as long as this call runs.
+    ///
+    /// [`Trap::Interrupted`]: crate::error::Trap::Interrupted
+    pub fn call_with_deadline(
+        &self,
+        mut ctx: impl AsContextMut,
+        deadline: Deadline,
+        args: Args,
+    ) -> Result<Ret> {
+        let mut ctx = ctx.as_context_mut();
+        let raw = unsafe { ffi::m3_GetInterrupt(ctx.as_ptr()) };
+        unsafe { interrupt::with_deadline(raw, deadline, || self.call_with_args(&mut ctx, args)) }
+    }
+
+    fn call_with_args(&self, mut ctx: impl AsContextMut, args: Args) -> Result<Ret> {
+        let ctx = ctx.as_context_mut();
+        let (raw, module) = self.raw.get_with_module(&ctx.as_context())?;
+
+        // like results, every argument is passed to wasm3 through its own pointer
+        let mut slots = [0u64; MAX_SLOTS];
+        let mut arg_ptrs = [ptr::null::<c_void>(); MAX_SLOTS];
+        unsafe { args.push_on_stack(slots.as_mut_ptr()) };
+        for (arg_ptr, slot) in arg_ptrs.iter_mut().zip(slots.iter()) {
+            *arg_ptr = (slot as *const u64).cast();
+        }
+
+        unsafe {
+            run_start(ctx.as_ptr(), module)?;
+            Error::from_ffi(ffi::m3_CheckCallStack(raw.as_ptr()))?;
+            let result = ffi::m3_Call(raw.as_ptr(), Args::SLOT_COUNT as u32, arg_ptrs.as_mut_ptr());
+            Error::from_call(ctx.as_ptr(), result)?;
+        }
+        self.get_call_result(raw)
+    }
+
     fn get_call_result(&self, raw: NonNull<M3Function>) -> Result<Ret> {
+        // wasm3 writes every result through its own pointer, so give each one a full slot and
+        // read them back the same way closure arguments are read.
+        let mut slots = [0u64; MAX_SLOTS];
+        let mut ret_ptrs = [ptr::null::<c_void>(); MAX_SLOTS];
+        for (ret_ptr, slot) in ret_ptrs.iter_mut().zip(slots.iter_mut()) {
+            *ret_ptr = (slot as *mut u64).cast();
+        }
+
         unsafe {
-            let mut ret = core::mem::MaybeUninit::<Ret>::uninit();
-            let result = ffi::m3_GetResultsV(raw.as_ptr(), ret.as_mut_ptr());
+            let result =
+                ffi::m3_GetResults(raw.as_ptr(), Ret::SLOT_COUNT as u32, ret_ptrs.as_mut_ptr());
             Error::from_ffi(result)?;
-            Ok(ret.assume_init())
+            Ok(Ret::pop_from_stack(slots.as_mut_ptr()))
         }
     }
 }
@@ -189,17 +312,13 @@ macro_rules! func_call_impl {
     #[doc(hidden)] // this really pollutes the documentation
         impl<$($types,)* Ret> Function<($($types,)*), Ret>
         where
-            Ret: WasmType,
+            Ret: WasmArgs,
             ($($types,)*): WasmArgs,
         {
             #[inline]
             #[allow(non_snake_case, clippy::too_many_arguments)]
-            pub fn call(&self, mut ctx: impl AsContextMut, $($types: $types),*) -> Result<Ret> {
-                let ctx = ctx.as_context_mut();
-                let raw = self.raw.get(&ctx.as_context())?;
-                let result = unsafe { ffi::m3_CallV(raw.as_ptr(), $($types,)*) };
-                unsafe { Error::from_ffi(result)?; }
-                self.get_call_result(raw)
+            pub fn call(&self, ctx: impl AsContextMut, $($types: $types),*) -> Result<Ret> {
+                self.call_with_args(ctx, ($($types,)*))
             }
         }
     };
@@ -208,37 +327,131 @@ func_call_impl!(A, B, C, D, E, F, G, H, J, K, L, M, N, O, P, Q);
 
 impl<ARG, Ret> Function<ARG, Ret>
 where
-    Ret: WasmType,
+    Ret: WasmArgs,
     ARG: WasmArg,
 {
     /// Calls this function with the given parameter.
     /// This is implemented with variable arguments depending on the functions Args type.
     #[inline]
-    pub fn call(&self, mut ctx: impl AsContextMut, arg: ARG) -> Result<Ret> {
-        let ctx = ctx.as_context_mut();
-        let raw = self.raw.get(&ctx.as_context())?;
-        let result = unsafe { ffi::m3_CallV(raw.as_ptr(), arg) };
-        unsafe {
-            Error::from_ffi(result)?;
-        }
-        self.get_call_result(raw)
+    pub fn call(&self, ctx: impl AsContextMut, arg: ARG) -> Result<Ret> {
+        self.call_with_args(ctx, arg)
     }
 }
 
 impl<Ret> Function<(), Ret>
 where
-    Ret: WasmType,
+    Ret: WasmArgs,
 {
     /// Calls this function.
     /// This is implemented with variable arguments depending on the functions Args type.
     #[inline]
-    pub fn call(&self, mut ctx: impl AsContextMut) -> Result<Ret> {
+    pub fn call(&self, ctx: impl AsContextMut) -> Result<Ret> {
+        self.call_with_args(ctx, ())
+    }
+}
+
+/// A callable wasm3 function whose signature is only known at runtime.
+/// See [`Function`] for the statically typed equivalent.
+#[derive(Debug, Clone)]
+pub struct DynFunction {
+    raw: StoredData<M3Function>,
+    ty: FuncType,
+}
+
+impl Eq for DynFunction {}
+impl PartialEq for DynFunction {
+    fn eq(&self, other: &Self) -> bool {
+        self.raw == other.raw
+    }
+}
+
+impl Hash for DynFunction {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.raw.hash(state);
+    }
+}
+
+impl DynFunction {
+    #[inline]
+    pub(crate) unsafe fn from_raw<T>(
+        store: &StoreContext<T>,
+        raw: NonNull<M3Function>,
+    ) -> Result<Self> {
+        let ty = FuncType::from_raw(raw).ok_or(Error::InvalidFunctionSignature)?;
+        Ok(DynFunction {
+            raw: StoredData::new(store, raw, function_module(raw)),
+            ty,
+        })
+    }
+
+    /// The name of this function.
+    pub fn name(&self, ctx: impl AsContext) -> Result<&str> {
+        unsafe {
+            let name = ffi::m3_GetFunctionName(self.raw.get(&ctx.as_context())?.as_ptr());
+            let cstr = CStr::from_ptr(name);
+            Ok(cstr.to_str().expect("function name is not valid utf-8"))
+        }
+    }
+
+    /// The signature of this function.
+    pub fn ty(&self) -> &FuncType {
+        &self.ty
+    }
+
+    /// Calls this function with the given parameters, returning its results.
+    ///
+    /// # Errors
+    ///
+    /// Besides errors raised by the call itself, this returns
+    /// [`Error::InvalidFunctionSignature`] if the parameters don't match the function's signature.
+    pub fn call(&self, mut ctx: impl AsContextMut, params: &[Value]) -> Result<Vec<Value>> {
         let ctx = ctx.as_context_mut();
-        let raw = self.raw.get(&ctx.as_context())?;
-        let result = unsafe { ffi::m3_CallV(raw.as_ptr()) };
+        let (raw, module) = self.raw.get_with_module(&ctx.as_context())?;
+
+        if params.len() != self.ty.params().len()
+            || params
+                .iter()
+                .zip(self.ty.params())
+                .any(|(param, &ty)| param.ty() != ty)
+        {
+            return Err(Error::InvalidFunctionSignature);
+        }
+
+        // wasm3 reads and writes every value through its own pointer, one slot per value
+        let mut arg_slots = vec![0u64; params.len()];
+        for (slot, param) in arg_slots.iter_mut().zip(params) {
+            unsafe { param.push_on_stack(slot) };
+        }
+        let mut arg_ptrs: Vec<*const c_void> = arg_slots
+            .iter()
+            .map(|slot| (slot as *const u64).cast())
+            .collect();
         unsafe {
-            Error::from_ffi(result)?;
+            run_start(ctx.as_ptr(), module)?;
+            Error::from_ffi(ffi::m3_CheckCallStack(raw.as_ptr()))?;
+            let result = ffi::m3_Call(raw.as_ptr(), arg_ptrs.len() as u32, arg_ptrs.as_mut_ptr());
+            Error::from_call(ctx.as_ptr(), result)?;
         }
-        self.get_call_result(raw)
+
+        let mut ret_slots = vec![0u64; self.ty.results().len()];
+        let mut ret_ptrs: Vec<*const c_void> = ret_slots
+            .iter_mut()
+            .map(|slot| (slot as *mut u64).cast_const().cast())
+            .collect();
+        unsafe {
+            Error::from_ffi(ffi::m3_GetResults(
+                raw.as_ptr(),
+                ret_ptrs.len() as u32,
+                ret_ptrs.as_mut_ptr(),
+            ))?;
+        }
+
+        Ok(self
+            .ty
+            .results()
+            .iter()
+            .zip(ret_slots.iter_mut())
+            .map(|(&ty, slot)| unsafe { Value::pop_from_stack(ty, slot) })
+            .collect())
     }
 }
@@ -0,0 +1,129 @@
+//! Access to wasm global variables.
+use core::{
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    ptr::{self, NonNull},
+};
+
+use ffi::{M3Global, M3Module};
+
+use crate::{
+    error::{Error, Result},
+    store::{AsContext, AsContextMut, StoreContext, StoredData},
+    GlobalType,
+};
+
+/// An exported wasm global variable holding a value of type `V`.
+#[derive(Debug, Copy, Clone)]
+pub struct Global<V> {
+    raw: StoredData<M3Global>,
+    mutable: bool,
+    _pd: PhantomData<fn() -> V>,
+}
+
+impl<V> Eq for Global<V> {}
+impl<V> PartialEq for Global<V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.raw == other.raw
+    }
+}
+
+impl<V> Hash for Global<V> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.raw.hash(state);
+    }
+}
+
+impl<V> Global<V>
+where
+    V: GlobalType,
+{
+    #[inline]
+    pub(crate) unsafe fn from_raw<T>(
+        store: &StoreContext<T>,
+        raw: NonNull<M3Global>,
+        module: NonNull<M3Module>,
+    ) -> Result<Self> {
+        if unsafe { ffi::m3_GetGlobalType(raw.as_ptr()) } != V::TYPE_INDEX {
+            return Err(Error::InvalidGlobalType);
+        }
+        Ok(Global {
+            raw: StoredData::new(store, raw, module),
+            mutable: unsafe { ffi::m3_IsGlobalMutable(raw.as_ptr()) },
+            _pd: PhantomData,
+        })
+    }
+
+    /// Whether this global can be changed with [`Self::set`].
+    pub fn is_mutable(&self) -> bool {
+        self.mutable
+    }
+
+    /// Reads the current value of this global.
+    pub fn get(&self, ctx: impl AsContext) -> Result<V> {
+        let raw = self.raw.get(&ctx.as_context())?;
+        let mut value = tagged_value::<V>();
+        unsafe {
+            Error::from_ffi(ffi::m3_GetGlobal(raw.as_ptr(), &mut value))?;
+            Ok(V::pop_from_stack(ptr::addr_of_mut!(value.value).cast()))
+        }
+    }
+
+    /// Changes the value of this global.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the global is immutable.
+    pub fn set(&self, mut ctx: impl AsContextMut, val: V) -> Result<()> {
+        let ctx = ctx.as_context_mut();
+        let raw = self.raw.get(&ctx.as_context())?;
+        if !self.mutable {
+            return Err(Error::ImmutableGlobal);
+        }
+        let mut value = tagged_value::<V>();
+        unsafe {
+            val.push_on_stack(ptr::addr_of_mut!(value.value).cast());
+            Error::from_ffi(ffi::m3_SetGlobal(raw.as_ptr(), &value))
+        }
+    }
+}
+
+fn tagged_value<V: GlobalType>() -> ffi::M3TaggedValue {
+    // every member of the value union starts at its beginning, which is where a stack slot holds
+    // its value as well
+    ffi::M3TaggedValue {
+        type_: V::TYPE_INDEX,
+        value: ffi::M3TaggedValue_M3ValueUnion { i64: 0 },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WasmType;
+
+    fn roundtrip<V: GlobalType>(val: V) -> V {
+        let mut value = tagged_value::<V>();
+        unsafe {
+            val.push_on_stack(ptr::addr_of_mut!(value.value).cast());
+            V::pop_from_stack(ptr::addr_of_mut!(value.value).cast())
+        }
+    }
+
+    #[test]
+    fn tagged_value_roundtrip() {
+        assert_eq!(roundtrip(-7i32), -7);
+        assert_eq!(roundtrip(u64::MAX - 1), u64::MAX - 1);
+        assert_eq!(roundtrip(1.5f32), 1.5);
+        assert_eq!(roundtrip(-2.25f64), -2.25);
+    }
+
+    #[test]
+    fn tagged_value_union_layout() {
+        let mut value = tagged_value::<f32>();
+        unsafe {
+            3.0f32.push_on_stack(ptr::addr_of_mut!(value.value).cast());
+            assert_eq!(value.value.f32, 3.0);
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! Stopping wasm calls that run for too long.
+use alloc::sync::Arc;
+use core::{
+    cell::UnsafeCell,
+    fmt, ptr,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+/// A handle that stops the wasm call running in a [`Store`], e.g. from a timer or a button handler.
+///
+/// The interpreter checks for interrupts at loop back-edges, function entries and host calls,
+/// where the running call then fails with [`Trap::Interrupted`]. If no call is running, the next
+/// one is interrupted instead, unless the interrupt is [cancelled](Self::cancel) first.
+///
+/// [`Store`]: crate::Store
+/// [`Trap::Interrupted`]: crate::error::Trap::Interrupted
+#[derive(Clone)]
+pub struct InterruptHandle {
+    raw: Arc<RawInterrupt>,
+}
+
+struct RawInterrupt(UnsafeCell<ffi::M3Interrupt>);
+
+// handles only ever access the `interrupted` flag, which the interpreter accesses atomically as
+// well, every other field is only accessed by the thread running the store
+unsafe impl Send for RawInterrupt {}
+unsafe impl Sync for RawInterrupt {}
+
+impl InterruptHandle {
+    pub(crate) fn new() -> Self {
+        let raw = ffi::M3Interrupt {
+            interrupted: 0,
+            clock: None,
+            deadline: 0,
+            countdown: 0,
+        };
+        InterruptHandle {
+            raw: Arc::new(RawInterrupt(UnsafeCell::new(raw))),
+        }
+    }
+
+    pub(crate) fn as_ptr(&self) -> *mut ffi::M3Interrupt {
+        self.raw.0.get()
+    }
+
+    /// Interrupts the running call of the store.
+    pub fn interrupt(&self) {
+        self.set_interrupted(1);
+    }
+
+    /// Withdraws an interrupt that has not stopped a call yet.
+    pub fn cancel(&self) {
+        self.set_interrupted(0);
+    }
+
+    fn set_interrupted(&self, interrupted: u8) {
+        self.interrupted().store(interrupted, Ordering::Release);
+    }
+
+    fn interrupted(&self) -> &AtomicU8 {
+        // the flag lives as long as the handle and is never accessed non-atomically
+        unsafe { AtomicU8::from_ptr(ptr::addr_of_mut!((*self.as_ptr()).interrupted)) }
+    }
+}
+
+impl fmt::Debug for InterruptHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InterruptHandle").finish_non_exhaustive()
+    }
+}
+
+/// A point in time after which a call is interrupted, see [`Function::call_with_deadline`].
+///
+/// Time is read from a monotonic `clock` in any unit. The interpreter only polls the clock every
+/// thousand checks for interrupts, so calls may run slightly past their deadline.
+///
+/// [`Function::call_with_deadline`]: crate::Function::call_with_deadline
+#[derive(Debug, Clone, Copy)]
+pub struct Deadline {
+    clock: extern "C" fn() -> u64,
+    at: u64,
+}
+
+impl Deadline {
+    /// Creates a deadline at the time `at` of `clock`.
+    pub fn new(clock: extern "C" fn() -> u64, at: u64) -> Self {
+        Deadline { clock, at }
+    }
+
+    /// Creates a deadline `duration` after the current time of `clock`.
+    pub fn after(clock: extern "C" fn() -> u64, duration: u64) -> Self {
+        Self::new(clock, clock().saturating_add(duration))
+    }
+}
+
+/// Runs `f` with `deadline` applied to the interrupt `raw`, restoring the previous deadline
+/// afterwards so that nested calls don't clear the deadline of the call they were made from.
+///
+/// # Safety
+///
+/// `raw` must be null or point to the interrupt of the store `f` calls into.
+pub(crate) unsafe fn with_deadline<R>(
+    raw: *mut ffi::M3Interrupt,
+    deadline: Deadline,
+    f: impl FnOnce() -> R,
+) -> R {
+    if raw.is_null() {
+        return f();
+    }
+    let (clock, at, countdown) = unsafe {
+        (
+            ptr::addr_of_mut!((*raw).clock),
+            ptr::addr_of_mut!((*raw).deadline),
+            ptr::addr_of_mut!((*raw).countdown),
+        )
+    };
+    let previous = unsafe { (clock.read(), at.read(), countdown.read()) };
+    unsafe {
+        clock.write(Some(deadline.clock as unsafe extern "C" fn() -> u64));
+        at.write(deadline.at);
+        // poll at the very first check, the deadline may already have passed
+        countdown.write(1);
+    }
+
+    let result = f();
+
+    unsafe {
+        clock.write(previous.0);
+        at.write(previous.1);
+        countdown.write(previous.2);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern "C" fn clock() -> u64 {
+        100
+    }
+
+    #[test]
+    fn interrupt_and_cancel() {
+        let handle = InterruptHandle::new();
+        handle.clone().interrupt();
+        assert_eq!(handle.interrupted().load(Ordering::Relaxed), 1);
+        handle.cancel();
+        assert_eq!(handle.interrupted().load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn nested_deadlines() {
+        let handle = InterruptHandle::new();
+        let raw = handle.as_ptr();
+        let outer = Deadline::after(clock, 50);
+        let inner = Deadline::new(clock, 120);
+        unsafe {
+            with_deadline(raw, outer, || {
+                assert_eq!((*raw).deadline, 150);
+                with_deadline(raw, inner, || assert_eq!((*raw).deadline, 120));
+                assert_eq!((*raw).deadline, 150);
+                assert_eq!((*raw).countdown, 1);
+            });
+            assert!((*raw).clock.is_none());
+        }
+    }
+}
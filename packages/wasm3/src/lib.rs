@@ -9,13 +9,35 @@ pub mod error;
 pub mod environment;
 pub use self::environment::Environment;
 pub mod function;
-pub use self::function::{CallContext, Function, RawCall};
+pub use self::function::{CallContext, DynFunction, Function, RawCall};
+pub mod global;
+pub use self::global::Global;
+pub mod interrupt;
+pub use self::interrupt::{Deadline, InterruptHandle};
+pub mod linker;
+pub use self::linker::Linker;
 pub mod macros;
+pub mod memory;
+pub use self::memory::Memory;
 mod module;
-pub use self::module::{Instance, Module};
+pub use self::module::{
+    CustomSection, ExportType, ExternKind, FunctionCompileError, ImportType, Instance, Module,
+    NameSection, UnresolvedImport,
+};
+#[cfg(feature = "profiling")]
+pub mod profile;
+#[cfg(feature = "profiling")]
+pub use self::profile::{FunctionProfile, OpProfile};
+mod reader;
+pub mod snapshot;
+pub use self::snapshot::Snapshot;
 pub mod store;
 pub use self::store::Store;
 pub mod ty;
+#[cfg(feature = "wasi")]
+pub mod wasi;
 pub use ffi as wasm3_sys;
 
-pub use self::ty::{WasmArg, WasmArgs, WasmType};
+pub use self::ty::{
+    FuncType, GlobalType, Value, ValueType, WasmArg, WasmArgs, WasmPtr, WasmSlice, WasmType,
+};
@@ -0,0 +1,299 @@
+//! Defining host functions once and linking them into any number of instances.
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt;
+
+use snafu::ensure;
+
+use crate::{
+    error::{AlreadyDefinedSnafu, Error, HostError, Result, UnresolvedImportsSnafu},
+    function::CallContext,
+    module::{ClosureLinkFailed, DynClosure, ExternKind, Instance, Module, Shared},
+    store::Store,
+    FuncType, Value,
+};
+
+type LinkFn<T> = dyn Fn(&mut Instance<T>, &mut Store<T>, &str, &str) -> LinkResult;
+type LinkResult = core::result::Result<(), ClosureLinkFailed>;
+
+/// A registry of host functions, keyed by module and function name, that is linked into every
+/// module instantiated through it.
+///
+/// Each host function is defined once and shared by all instances it is linked into, so linking
+/// only costs a small allocation per import the instance actually uses. Per-store state belongs in
+/// the store's data, which host functions reach through their [`CallContext`]. As they are `Fn`,
+/// host functions may call back into a guest that calls them again before they return.
+///
+/// Host functions must be `'static`: the instances they are linked into hold on to them until
+/// their store is dropped, and [`Store`] has no lifetime parameter that could bound a borrow.
+///
+/// By default, defining a function under a name that is already taken fails with
+/// [`Error::AlreadyDefined`]; see [`Self::allow_shadowing`].
+pub struct Linker<T: 'static> {
+    definitions: BTreeMap<(String, String), Rc<LinkFn<T>>>,
+    allow_shadowing: bool,
+    strict: bool,
+    optional: BTreeSet<(String, String)>,
+}
+
+impl<T: 'static> Linker<T> {
+    /// Creates a linker without any definitions.
+    pub fn new() -> Self {
+        Linker {
+            definitions: BTreeMap::new(),
+            allow_shadowing: false,
+            strict: false,
+            optional: BTreeSet::new(),
+        }
+    }
+
+    /// Whether later definitions may replace earlier ones of the same name.
+    pub fn allow_shadowing(&mut self, allow: bool) -> &mut Self {
+        self.allow_shadowing = allow;
+        self
+    }
+
+    /// Whether instantiating fails with [`Error::UnresolvedImports`] if a module imports functions
+    /// this linker doesn't define, except for the ones marked [optional](Self::optional).
+    ///
+    /// Without this, wasm3 only fails once the guest calls a missing import.
+    pub fn strict(&mut self, strict: bool) -> &mut Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Marks an import that modules may leave unresolved in [strict](Self::strict) mode, e.g.
+    /// because they only call it if it is linked directly through the instance.
+    pub fn optional(&mut self, module: &str, name: &str) -> &mut Self {
+        self.optional.insert((module.to_string(), name.to_string()));
+        self
+    }
+
+    /// Defines a host function, see [`Instance::link_closure`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the name is taken and shadowing isn't allowed.
+    pub fn func_wrap<Args, Ret, F>(
+        &mut self,
+        module: &str,
+        name: &str,
+        func: F,
+    ) -> Result<&mut Self>
+    where
+        Args: crate::WasmArgs,
+        Ret: crate::WasmArgs,
+        F: for<'cc> Fn(CallContext<'cc, T>, Args) -> core::result::Result<Ret, HostError> + 'static,
+    {
+        let func = Rc::new(Shared(func));
+        self.insert(
+            module,
+            name,
+            Rc::new(move |instance, store, module, name| {
+                instance.link_shared_closure::<Args, Ret, F>(store, module, name, func.clone())
+            }),
+        )
+    }
+
+    /// Defines a host function whose signature is only known at runtime, see
+    /// [`Instance::link_dynamic`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the name is taken and shadowing isn't allowed.
+    pub fn func_dynamic<F>(
+        &mut self,
+        module: &str,
+        name: &str,
+        ty: FuncType,
+        func: F,
+    ) -> Result<&mut Self>
+    where
+        F: for<'cc> Fn(
+                CallContext<'cc, T>,
+                &[Value],
+                &mut [Value],
+            ) -> core::result::Result<(), HostError>
+            + 'static,
+    {
+        let func = Rc::new(DynClosure {
+            closure: Shared(func),
+            ty,
+        });
+        self.insert(
+            module,
+            name,
+            Rc::new(move |instance, store, module, name| {
+                instance.link_shared_dynamic(store, module, name, func.clone())
+            }),
+        )
+    }
+
+    /// Makes every function defined in `module` so far available in `as_module` as well.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if a name is taken and shadowing isn't allowed, in which
+    /// case no function is aliased.
+    pub fn alias_module(&mut self, module: &str, as_module: &str) -> Result<&mut Self> {
+        let aliases: Vec<_> = self
+            .definitions
+            .iter()
+            .filter(|((def_module, _), _)| def_module == module)
+            .map(|((_, name), link)| (name.clone(), link.clone()))
+            .collect();
+        if !self.allow_shadowing {
+            for (name, _) in &aliases {
+                self.check_free(as_module, name)?;
+            }
+        }
+        for (name, link) in aliases {
+            self.definitions.insert((as_module.to_string(), name), link);
+        }
+        Ok(self)
+    }
+
+    /// Whether a function is defined under the given name.
+    pub fn contains(&self, module: &str, name: &str) -> bool {
+        self.definitions
+            .contains_key(&(module.to_string(), name.to_string()))
+    }
+
+    /// Instantiates `module` in `store` and links the functions it imports from this linker.
+    ///
+    /// Imports this linker doesn't define are left alone, so they can still be linked directly
+    /// through the returned instance, unless the linker is [strict](Self::strict).
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if instantiating fails, see [`Store::instantiate`], if
+    /// a defined function doesn't match the signature of its import, or if imports are left
+    /// unresolved in strict mode. The instance is unloaded from `store` again in the latter two
+    /// cases.
+    pub fn instantiate(&self, store: &mut Store<T>, module: Module) -> Result<Instance<T>> {
+        let imports: Vec<_> = module
+            .imports()
+            .filter(|import| import.kind() == ExternKind::Function)
+            .filter_map(|import| {
+                let key = (import.module().to_string(), import.name().to_string());
+                let link = self.definitions.get(&key)?.clone();
+                Some((key, link))
+            })
+            .collect();
+
+        let mut instance = store.instantiate(module)?;
+        for ((module, name), link) in imports {
+            if let Err(err) = link(&mut instance, store, &module, &name) {
+                // the caller never gets a handle to unload the instance with, and nothing has been
+                // linked to it yet, so this can't fail
+                let _ = store.unload(instance);
+                return Err(Error::LinkImport {
+                    module,
+                    name,
+                    source: err.into_source().into(),
+                });
+            }
+        }
+
+        if self.strict {
+            if let Err(err) = self.check_resolved(&instance, store) {
+                let _ = store.unload(instance);
+                return Err(err);
+            }
+        }
+        Ok(instance)
+    }
+
+    fn check_resolved(&self, instance: &Instance<T>, store: &Store<T>) -> Result<()> {
+        let imports: Vec<_> = instance
+            .unresolved_imports(store)?
+            .into_iter()
+            .filter(|import| {
+                !self
+                    .optional
+                    .contains(&(import.module.clone(), import.name.clone()))
+            })
+            .collect();
+        ensure!(imports.is_empty(), UnresolvedImportsSnafu { imports });
+        Ok(())
+    }
+
+    fn insert(&mut self, module: &str, name: &str, link: Rc<LinkFn<T>>) -> Result<&mut Self> {
+        if !self.allow_shadowing {
+            self.check_free(module, name)?;
+        }
+        self.definitions
+            .insert((module.to_string(), name.to_string()), link);
+        Ok(self)
+    }
+
+    fn check_free(&self, module: &str, name: &str) -> Result<()> {
+        ensure!(
+            !self.contains(module, name),
+            AlreadyDefinedSnafu { module, name }
+        );
+        Ok(())
+    }
+}
+
+impl<T: 'static> Default for Linker<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: 'static> fmt::Debug for Linker<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Linker")
+            .field("definitions", &self.definitions.keys())
+            .field("allow_shadowing", &self.allow_shadowing)
+            .field("strict", &self.strict)
+            .field("optional", &self.optional)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn linker() -> Linker<()> {
+        let mut linker = Linker::new();
+        linker
+            .func_wrap("env", "add", |_ctx, (a, b): (i32, i32)| Ok(a + b))
+            .unwrap();
+        linker
+    }
+
+    #[test]
+    fn shadowing() {
+        let mut linker = linker();
+        let error = linker
+            .func_wrap("env", "add", |_ctx, ()| Ok(()))
+            .unwrap_err();
+        assert_eq!(
+            error,
+            Error::AlreadyDefined {
+                module: "env".into(),
+                name: "add".into()
+            }
+        );
+
+        linker.allow_shadowing(true);
+        linker.func_wrap("env", "add", |_ctx, ()| Ok(())).unwrap();
+        assert!(linker.contains("env", "add"));
+    }
+
+    #[test]
+    fn alias_module() {
+        let mut linker = linker();
+        linker.alias_module("env", "math").unwrap();
+        assert!(linker.contains("math", "add"));
+        assert!(!linker.contains("math", "sub"));
+        assert!(linker.alias_module("env", "math").is_err());
+    }
+}
@@ -35,7 +35,7 @@ macro_rules! make_func_wrapper {
                     <$rtype as $crate::WasmType>::push_on_stack(ret, sp);
                     $crate::wasm3_sys::m3Err_none as _
                 },
-                Err(trap) => trap.as_ptr() as _
+                Err(trap) => trap.as_cstr().as_ptr() as _
             }
         }
     };
@@ -0,0 +1,234 @@
+//! Bounds-checked access to wasm linear memory.
+use alloc::{borrow::Cow, string::String, vec::Vec};
+use core::{mem, ops::Range};
+
+use crate::error::{Trap, TrappedResult};
+
+/// Plain data that can be copied in and out of wasm memory.
+///
+/// Wasm memory is always little-endian, so implementors convert between that and the host's byte
+/// order. This is implemented for the primitive number types and arrays of them; `#[repr(C)]`
+/// structs can implement it by converting each of their fields.
+pub trait Pod: bytemuck::Pod {
+    /// Converts a value between the host's byte order and wasm's, which is the same operation in
+    /// both directions, as it only swaps bytes on big-endian hosts.
+    fn swap_le(self) -> Self;
+}
+
+macro_rules! int_pod_impl {
+    ($($ty:ty),*) => {
+        $(
+            impl Pod for $ty {
+                fn swap_le(self) -> Self {
+                    <$ty>::from_le(self)
+                }
+            }
+        )*
+    };
+}
+int_pod_impl!(u8, i8, u16, i16, u32, i32, u64, i64);
+
+impl Pod for f32 {
+    fn swap_le(self) -> Self {
+        f32::from_bits(u32::from_le(self.to_bits()))
+    }
+}
+
+impl Pod for f64 {
+    fn swap_le(self) -> Self {
+        f64::from_bits(u64::from_le(self.to_bits()))
+    }
+}
+
+impl<T: Pod, const N: usize> Pod for [T; N] {
+    fn swap_le(self) -> Self {
+        self.map(T::swap_le)
+    }
+}
+
+/// Decodes a value from the little-endian bytes of wasm memory.
+pub(crate) fn read_value<T: Pod>(bytes: &[u8]) -> T {
+    bytemuck::pod_read_unaligned::<T>(bytes).swap_le()
+}
+
+/// Encodes a value into the little-endian bytes of wasm memory.
+pub(crate) fn write_value<T: Pod>(bytes: &mut [u8], val: T) {
+    bytes.copy_from_slice(bytemuck::bytes_of(&val.swap_le()));
+}
+
+/// Decodes consecutive values from the bytes of wasm memory.
+pub(crate) fn read_values<T: Pod>(bytes: &[u8]) -> Vec<T> {
+    if mem::size_of::<T>() == 0 {
+        return Vec::new();
+    }
+    bytes
+        .chunks_exact(mem::size_of::<T>())
+        .map(read_value)
+        .collect()
+}
+
+/// Encodes consecutive values into the bytes of wasm memory.
+pub(crate) fn write_values<T: Pod>(bytes: &mut [u8], values: &[T]) {
+    if mem::size_of::<T>() == 0 {
+        return;
+    }
+    for (bytes, &val) in bytes.chunks_exact_mut(mem::size_of::<T>()).zip(values) {
+        write_value(bytes, val);
+    }
+}
+
+/// A bounds-checked view into the linear memory of a store.
+///
+/// Addresses are guest pointers. Values in wasm memory carry no alignment guarantees, so they are
+/// copied in and out rather than referenced. Every access outside of the memory results in
+/// [`Trap::OutOfBoundsMemoryAccess`] instead of a panic.
+#[derive(Debug)]
+pub struct Memory<'a> {
+    data: &'a mut [u8],
+}
+
+impl<'a> Memory<'a> {
+    pub(crate) fn new(data: &'a mut [u8]) -> Self {
+        Self { data }
+    }
+
+    /// The size of this memory in bytes.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Whether this memory has a size of zero.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn range(&self, offset: u32, len: usize) -> TrappedResult<Range<usize>> {
+        let start = offset as usize;
+        match start.checked_add(len) {
+            Some(end) if end <= self.data.len() => Ok(start..end),
+            _ => Err(Trap::OutOfBoundsMemoryAccess),
+        }
+    }
+
+    pub(crate) fn byte_len<T>(count: u32) -> TrappedResult<usize> {
+        (count as usize)
+            .checked_mul(mem::size_of::<T>())
+            .ok_or(Trap::OutOfBoundsMemoryAccess)
+    }
+
+    /// Borrows `len` bytes starting at `offset`.
+    pub fn bytes(&self, offset: u32, len: u32) -> TrappedResult<&[u8]> {
+        let range = self.range(offset, len as usize)?;
+        Ok(&self.data[range])
+    }
+
+    /// Mutably borrows `len` bytes starting at `offset`.
+    pub fn bytes_mut(&mut self, offset: u32, len: u32) -> TrappedResult<&mut [u8]> {
+        self.range_mut(offset, len as usize)
+    }
+
+    pub(crate) fn range_mut(&mut self, offset: u32, len: usize) -> TrappedResult<&mut [u8]> {
+        let range = self.range(offset, len)?;
+        Ok(&mut self.data[range])
+    }
+
+    /// Reads a value at `offset`.
+    pub fn read<T: Pod>(&self, offset: u32) -> TrappedResult<T> {
+        let range = self.range(offset, mem::size_of::<T>())?;
+        Ok(read_value(&self.data[range]))
+    }
+
+    /// Writes a value at `offset`.
+    pub fn write<T: Pod>(&mut self, offset: u32, val: T) -> TrappedResult<()> {
+        let range = self.range(offset, mem::size_of::<T>())?;
+        write_value(&mut self.data[range], val);
+        Ok(())
+    }
+
+    /// Reads `count` consecutive values starting at `offset`.
+    pub fn read_slice<T: Pod>(&self, offset: u32, count: u32) -> TrappedResult<Vec<T>> {
+        let range = self.range(offset, Self::byte_len::<T>(count)?)?;
+        Ok(read_values(&self.data[range]))
+    }
+
+    /// Writes consecutive values starting at `offset`.
+    pub fn write_slice<T: Pod>(&mut self, offset: u32, values: &[T]) -> TrappedResult<()> {
+        let range = self.range(offset, mem::size_of_val(values))?;
+        write_values(&mut self.data[range], values);
+        Ok(())
+    }
+
+    /// Reads a UTF-8 string of `len` bytes starting at `offset`.
+    /// Invalid sequences are replaced with `U+FFFD REPLACEMENT CHARACTER`.
+    pub fn read_utf8(&self, offset: u32, len: u32) -> TrappedResult<Cow<'_, str>> {
+        Ok(String::from_utf8_lossy(self.bytes(offset, len)?))
+    }
+
+    /// Reads a UTF-16 string of `len` code units starting at `offset`.
+    /// Invalid sequences are replaced with `U+FFFD REPLACEMENT CHARACTER`.
+    pub fn read_utf16(&self, offset: u32, len: u32) -> TrappedResult<String> {
+        Ok(String::from_utf16_lossy(
+            &self.read_slice::<u16>(offset, len)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_write_unaligned() {
+        let mut data = [0u8; 16];
+        let mut memory = Memory::new(&mut data);
+        memory.write(1, 0x1234_5678u32).unwrap();
+        memory.write(5, -1.5f64).unwrap();
+        assert_eq!(memory.read::<u32>(1), Ok(0x1234_5678));
+        assert_eq!(memory.read::<f64>(5), Ok(-1.5));
+        assert_eq!(data[1..5], [0x78, 0x56, 0x34, 0x12]);
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let mut data = [0u8; 8];
+        let mut memory = Memory::new(&mut data);
+        assert_eq!(memory.read::<u32>(4), Ok(0));
+        assert_eq!(memory.read::<u32>(5), Err(Trap::OutOfBoundsMemoryAccess));
+        assert_eq!(
+            memory.read::<u8>(u32::MAX),
+            Err(Trap::OutOfBoundsMemoryAccess)
+        );
+        assert_eq!(memory.write(8, 0u8), Err(Trap::OutOfBoundsMemoryAccess));
+        assert_eq!(
+            memory.read_slice::<u64>(0, u32::MAX),
+            Err(Trap::OutOfBoundsMemoryAccess)
+        );
+        assert_eq!(
+            memory.write_slice(6, &[0u16, 0]),
+            Err(Trap::OutOfBoundsMemoryAccess)
+        );
+        assert_eq!(memory.bytes(8, 0), Ok(&[][..]));
+    }
+
+    #[test]
+    fn slices() {
+        let mut data = [0u8; 9];
+        let mut memory = Memory::new(&mut data);
+        memory.write_slice(1, &[1u16, 2, 3, 4]).unwrap();
+        assert_eq!(memory.read_slice::<u16>(1, 4), Ok(alloc::vec![1, 2, 3, 4]));
+        assert_eq!(memory.read::<[u16; 2]>(3), Ok([2, 3]));
+    }
+
+    #[test]
+    fn strings() {
+        let mut data = [0u8; 16];
+        let mut memory = Memory::new(&mut data);
+        memory.bytes_mut(0, 5).unwrap().copy_from_slice(b"hi\xffyo");
+        assert_eq!(memory.read_utf8(0, 2).unwrap(), "hi");
+        assert_eq!(memory.read_utf8(0, 5).unwrap(), "hi\u{fffd}yo");
+
+        let utf16: Vec<u16> = "héllo".encode_utf16().collect();
+        memory.write_slice(5, &utf16).unwrap();
+        assert_eq!(memory.read_utf16(5, utf16.len() as u32).unwrap(), "héllo");
+    }
+}
@@ -1,19 +1,27 @@
 use alloc::{borrow::Cow, boxed::Box, ffi::CString, rc::Rc, string::String, vec::Vec};
 use core::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     ffi::{c_char, c_void, CStr},
+    fmt,
     marker::PhantomData,
+    mem,
     ptr::{self, NonNull},
 };
 
 use ffi::M3Module;
-use snafu::{IntoError, ResultExt, Snafu};
+use snafu::{ensure, ResultExt, Snafu};
 
 use crate::{
     environment::Environment,
-    error::{Error, Result, Trap},
-    function::{CallContext, Function, RawCall},
-    store::{AsContext, Store, StoreContext, StoredData},
+    error::{
+        string_from_ptr, CompileFunctionsSnafu, Error, HostError, ParseError, Result, Trap,
+        UnresolvedImportsSnafu,
+    },
+    function::{self, CallContext, DynFunction, DynHostFunction, Function, RawCall},
+    global::Global,
+    reader::{self, Reader},
+    store::{AsContext, AsContextMut, Store, StoreContext, StoredData},
+    FuncType, Value,
 };
 
 /// Failed to link a WASM function.
@@ -26,10 +34,49 @@ pub struct ClosureLinkFailed {
     name: String,
 }
 
+impl ClosureLinkFailed {
+    pub(crate) fn into_source(self) -> Error {
+        self.source
+    }
+}
+
+/// The bytes of a parsed module, which wasm3 keeps pointing into for as long as it is loaded.
+pub(crate) type ModuleBytes = Rc<Cow<'static, [u8]>>;
+
+/// A parsed module that hasn't been loaded, which is freed when dropped.
 #[derive(Debug)]
 pub(crate) struct RawModule {
     pub inner: NonNull<ffi::M3Module>,
-    pub data: Cow<'static, [u8]>,
+    pub data: ModuleBytes,
+}
+
+impl RawModule {
+    fn parse(env: &Environment, data: ModuleBytes) -> Result<Self> {
+        assert!(data.len() <= !0u32 as usize);
+        let mut module = ptr::null_mut();
+        unsafe {
+            Error::from_ffi(ffi::m3_ParseModule(
+                env.as_ptr(),
+                &mut module,
+                data.as_ptr(),
+                data.len() as u32,
+            ))?;
+        }
+        let inner = NonNull::new(module)
+            .expect("module pointer is non-null after m3_ParseModule if result is not error");
+        Ok(RawModule { inner, data })
+    }
+
+    /// Hands the module over to the runtime it has been loaded into, which frees it from then on.
+    pub(crate) fn into_loaded(self) -> LoadedModule {
+        let this = mem::ManuallyDrop::new(self);
+        LoadedModule {
+            inner: this.inner,
+            started: Cell::new(false),
+            linked_to: Vec::new(),
+            data: unsafe { ptr::read(&this.data) },
+        }
+    }
 }
 
 impl Drop for RawModule {
@@ -38,52 +85,396 @@ impl Drop for RawModule {
     }
 }
 
-/// A parsed module which can be loaded into a [`Runtime`].
+/// A module loaded into a store, which is freed along with its runtime or when it is unloaded.
+#[derive(Debug)]
+pub(crate) struct LoadedModule {
+    pub inner: NonNull<ffi::M3Module>,
+    /// Whether the start function ran, so that calls don't have to ask wasm3 every time.
+    pub started: Cell<bool>,
+    /// The ids of the instances this one has been linked to, see `Instance::link_instance`.
+    pub linked_to: Vec<usize>,
+    /// The bytes the module was parsed from, which it points into.
+    pub data: ModuleBytes,
+}
+
+/// A parsed module which can be loaded into a [`Store`].
+///
+/// Cloning a module is cheap as the clones share its bytes, so it can be instantiated any number
+/// of times. Every instantiation but the first parses the bytes again.
 pub struct Module {
-    raw: RawModule,
+    data: ModuleBytes,
     env: Environment,
+    // parsed up front to report errors early; taken by the first instantiation
+    parsed: Option<RawModule>,
+}
+
+impl Clone for Module {
+    fn clone(&self) -> Self {
+        Module {
+            data: self.data.clone(),
+            env: self.env.clone(),
+            parsed: None,
+        }
+    }
 }
 
 impl Module {
     /// Parses a wasm module from raw bytes.
     pub fn parse(env: &Environment, data: impl Into<Cow<'static, [u8]>>) -> Result<Self> {
-        let data = data.into();
-        assert!(data.len() <= !0u32 as usize);
-        let mut module = ptr::null_mut();
-        unsafe {
-            Error::from_ffi(ffi::m3_ParseModule(
-                env.as_ptr(),
-                &mut module,
-                data.as_ptr(),
-                data.len() as u32,
-            ))?;
-        }
-        let module = NonNull::new(module)
-            .expect("module pointer is non-null after m3_ParseModule if result is not error");
+        let data = Rc::new(data.into());
+        let parsed = RawModule::parse(env, data.clone())?;
         Ok(Module {
-            raw: RawModule {
-                inner: module,
-                data,
-            },
+            data,
             env: env.clone(),
+            parsed: Some(parsed),
         })
     }
 
-    pub(crate) fn as_ptr(&self) -> ffi::IM3Module {
-        self.raw.inner.as_ptr()
-    }
-
-    pub(crate) fn into_raw(self) -> RawModule {
-        self.raw
+    pub(crate) fn into_raw(self) -> Result<RawModule> {
+        match self.parsed {
+            Some(parsed) => Ok(parsed),
+            None => RawModule::parse(&self.env, self.data),
+        }
     }
 
     /// The environment this module was parsed in.
     pub fn environment(&self) -> &Environment {
         &self.env
     }
+
+    /// Returns an iterator over the imports of this module, in the order they are declared.
+    pub fn imports(&self) -> impl Iterator<Item = ImportType<'_>> + '_ {
+        read_imports(&self.data).into_iter()
+    }
+
+    /// Returns an iterator over the exports of this module, in the order they are declared.
+    pub fn exports(&self) -> impl Iterator<Item = ExportType<'_>> + '_ {
+        read_exports(&self.data).into_iter()
+    }
+
+    /// Returns an iterator over the custom sections of this module, in the order they appear.
+    ///
+    /// This includes sections wasm3 reads itself, such as `name`.
+    pub fn custom_sections(&self) -> impl Iterator<Item = CustomSection<'_>> + '_ {
+        read_custom_sections(&self.data)
+    }
+
+    /// The debug names from the `name` section of this module, or `None` if it has none.
+    pub fn names(&self) -> Option<NameSection<'_>> {
+        self.custom_sections()
+            .find(|section| section.name() == "name")
+            .map(|section| read_names(section.data()))
+    }
+}
+
+/// The kind of item a module imports or exports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExternKind {
+    /// A function.
+    Function,
+    /// A table.
+    Table,
+    /// A linear memory.
+    Memory,
+    /// A global variable.
+    Global,
+}
+
+impl ExternKind {
+    fn from_raw(kind: u8) -> Option<Self> {
+        match kind {
+            reader::KIND_FUNCTION => Some(ExternKind::Function),
+            reader::KIND_TABLE => Some(ExternKind::Table),
+            reader::KIND_MEMORY => Some(ExternKind::Memory),
+            reader::KIND_GLOBAL => Some(ExternKind::Global),
+            _ => None,
+        }
+    }
+}
+
+/// An item a [`Module`] requires from its host, as returned by [`Module::imports`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportType<'a> {
+    module: &'a str,
+    name: &'a str,
+    kind: ExternKind,
+    func_type: Option<FuncType>,
+}
+
+impl<'a> ImportType<'a> {
+    /// The name of the module this item is imported from.
+    pub fn module(&self) -> &'a str {
+        self.module
+    }
+
+    /// The name of the imported item within its module.
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// What kind of item is imported.
+    pub fn kind(&self) -> ExternKind {
+        self.kind
+    }
+
+    /// The type of the imported function.
+    ///
+    /// This is `None` for anything but functions, and for functions using types other than
+    /// the ones described by [`ValueType`](crate::ValueType).
+    pub fn func_type(&self) -> Option<&FuncType> {
+        self.func_type.as_ref()
+    }
+}
+
+/// A function import that has not been linked, as returned by [`Instance::unresolved_imports`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnresolvedImport {
+    /// The name of the module the function is imported from.
+    pub module: String,
+    /// The name of the function within its module.
+    pub name: String,
+    /// The signature the module expects, or `None` if it uses types other than the ones
+    /// described by [`ValueType`](crate::ValueType).
+    pub ty: Option<FuncType>,
+}
+
+impl fmt::Display for UnresolvedImport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}::{}`", self.module, self.name)?;
+        match &self.ty {
+            Some(ty) => write!(f, ": {}", ty),
+            None => write!(f, ": <unsupported signature>"),
+        }
+    }
+}
+
+/// A function that could not be compiled, as reported by [`Instance::compile_all`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionCompileError {
+    /// The index of the function in its module, counting imported functions first.
+    pub function_index: u32,
+    /// The debug name of the function, taken from the name section or an export.
+    pub name: Option<String>,
+    /// Why the function could not be compiled.
+    pub error: Error,
+}
+
+impl fmt::Display for FunctionCompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.name.as_deref().unwrap_or("<unnamed>");
+        write!(f, "{} (#{}): {}", name, self.function_index, self.error)
+    }
+}
+
+/// An item a [`Module`] makes available to its host, as returned by [`Module::exports`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportType<'a> {
+    name: &'a str,
+    kind: ExternKind,
+    func_type: Option<FuncType>,
+}
+
+impl<'a> ExportType<'a> {
+    /// The name the item is exported under.
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// What kind of item is exported.
+    pub fn kind(&self) -> ExternKind {
+        self.kind
+    }
+
+    /// The type of the exported function.
+    ///
+    /// This is `None` for anything but functions, and for functions using types other than
+    /// the ones described by [`ValueType`](crate::ValueType).
+    pub fn func_type(&self) -> Option<&FuncType> {
+        self.func_type.as_ref()
+    }
+}
+
+/// A custom section of a [`Module`], as returned by [`Module::custom_sections`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CustomSection<'a> {
+    name: &'a str,
+    data: &'a [u8],
+}
+
+impl<'a> CustomSection<'a> {
+    /// The name of the section.
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// The contents of the section following its name.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+/// The debug names of a [`Module`], as returned by [`Module::names`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NameSection<'a> {
+    module: Option<&'a str>,
+    functions: Vec<(u32, &'a str)>,
+}
+
+impl<'a> NameSection<'a> {
+    /// The name of the module itself.
+    pub fn module(&self) -> Option<&'a str> {
+        self.module
+    }
+
+    /// The name of the function at `index`, counting imported functions first.
+    pub fn function(&self, index: u32) -> Option<&'a str> {
+        self.functions
+            .iter()
+            .find(|&&(function, _)| function == index)
+            .map(|&(_, name)| name)
+    }
+
+    /// Returns an iterator over the indices and names of all named functions.
+    pub fn functions(&self) -> impl Iterator<Item = (u32, &'a str)> + '_ {
+        self.functions.iter().copied()
+    }
+}
+
+fn read_custom_sections(data: &[u8]) -> impl Iterator<Item = CustomSection<'_>> {
+    reader::sections(data)
+        .filter(|section| section.id == reader::SECTION_CUSTOM)
+        .filter_map(|section| {
+            let mut reader = Reader::new(section.data);
+            let name = reader.read_name()?;
+            Some(CustomSection {
+                name,
+                data: reader.remaining(),
+            })
+        })
+}
+
+/// Reads the module and function names of a `name` section, skipping the other subsections.
+fn read_names(data: &[u8]) -> NameSection<'_> {
+    const SUBSECTION_MODULE: u8 = 0;
+    const SUBSECTION_FUNCTION: u8 = 1;
+
+    let mut names = NameSection::default();
+    let mut reader = Reader::new(data);
+    while let Some(id) = reader.read_u8() {
+        let Some(subsection) = reader
+            .read_u32()
+            .and_then(|len| reader.read_bytes(len as usize))
+        else {
+            break;
+        };
+        let mut reader = Reader::new(subsection);
+        match id {
+            SUBSECTION_MODULE => names.module = reader.read_name(),
+            SUBSECTION_FUNCTION => read_vec(&mut reader, |reader| {
+                let index = reader.read_u32()?;
+                names.functions.push((index, reader.read_name()?));
+                Some(())
+            }),
+            _ => {}
+        }
+    }
+    names
+}
+
+/// Reads the types of all functions of a module, imported ones first, along with its imports.
+fn read_functions(data: &[u8]) -> (Vec<Option<FuncType>>, Vec<ImportType<'_>>) {
+    let mut types = Vec::new();
+    let mut functions = Vec::new();
+    let mut imports = Vec::new();
+    let type_of =
+        |types: &[Option<FuncType>], index: u32| types.get(index as usize).cloned().flatten();
+    for section in reader::sections(data) {
+        let mut reader = Reader::new(section.data);
+        match section.id {
+            reader::SECTION_TYPE => read_vec(&mut reader, |reader| {
+                types.push(reader.read_func_type()?);
+                Some(())
+            }),
+            reader::SECTION_IMPORT => read_vec(&mut reader, |reader| {
+                let module = reader.read_name()?;
+                let name = reader.read_name()?;
+                let kind = ExternKind::from_raw(reader.read_u8()?)?;
+                let mut func_type = None;
+                match kind {
+                    ExternKind::Function => {
+                        func_type = type_of(&types, reader.read_u32()?);
+                        functions.push(func_type.clone());
+                    }
+                    ExternKind::Table => {
+                        reader.read_u8()?;
+                        reader.skip_limits()?;
+                    }
+                    ExternKind::Memory => reader.skip_limits()?,
+                    ExternKind::Global => {
+                        reader.read_value_type()?;
+                        reader.read_u8()?;
+                    }
+                }
+                imports.push(ImportType {
+                    module,
+                    name,
+                    kind,
+                    func_type,
+                });
+                Some(())
+            }),
+            reader::SECTION_FUNCTION => read_vec(&mut reader, |reader| {
+                functions.push(type_of(&types, reader.read_u32()?));
+                Some(())
+            }),
+            _ => {}
+        }
+    }
+    (functions, imports)
+}
+
+fn read_imports(data: &[u8]) -> Vec<ImportType<'_>> {
+    read_functions(data).1
+}
+
+fn read_exports(data: &[u8]) -> Vec<ExportType<'_>> {
+    let (functions, _) = read_functions(data);
+    let mut exports = Vec::new();
+    let section = reader::sections(data).find(|section| section.id == reader::SECTION_EXPORT);
+    if let Some(section) = section {
+        read_vec(&mut Reader::new(section.data), |reader| {
+            let name = reader.read_name()?;
+            let kind = ExternKind::from_raw(reader.read_u8()?)?;
+            let index = reader.read_u32()?;
+            let func_type = match kind {
+                ExternKind::Function => functions.get(index as usize).cloned().flatten(),
+                _ => None,
+            };
+            exports.push(ExportType {
+                name,
+                kind,
+                func_type,
+            });
+            Some(())
+        });
+    }
+    exports
+}
+
+/// Reads a vector of entries, stopping early if an entry can't be read.
+fn read_vec<'a>(reader: &mut Reader<'a>, mut entry: impl FnMut(&mut Reader<'a>) -> Option<()>) {
+    let count = reader.read_u32().unwrap_or(0);
+    for _ in 0..count {
+        if entry(reader).is_none() {
+            break;
+        }
+    }
 }
 
 /// A loaded module belonging to a specific runtime. Allows for linking and looking up functions.
+///
+/// Once the instance has been [unloaded](Store::unload), its handles and those of its functions
+/// and globals fail with [`Error::InstanceUnloaded`].
 // needs no drop as loaded modules will be cleaned up by the runtime
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Instance<T>(StoredData<M3Module>, PhantomData<fn() -> T>);
@@ -112,7 +503,7 @@ impl<T> Instance<T> {
     ) -> Result<()>
     where
         Args: crate::WasmArgs,
-        Ret: crate::WasmType,
+        Ret: crate::WasmArgs,
     {
         let module_name_cstr = CString::new(module_name)?;
         let function_name_cstr = CString::new(function_name)?;
@@ -132,6 +523,13 @@ impl<T> Instance<T> {
     /// Links the given closure to the corresponding module and function name.
     /// This boxes the closure and therefore requires a heap allocation.
     ///
+    /// The closure may return a tuple such as `(i32, f64)` to link a function with multiple results.
+    /// An error returned by the closure aborts the wasm call, see [`HostError`].
+    ///
+    /// The closure may call back into the guest through its [`CallContext`], but as it is `FnMut`,
+    /// a guest calling the function again before it returns fails with
+    /// [`Error::HostFunctionReentered`]. Functions defined through a [`Linker`] can be re-entered.
+    ///
     /// # Errors
     ///
     /// This function will return an error in the following situations:
@@ -139,6 +537,8 @@ impl<T> Instance<T> {
     /// * a memory allocation failed
     /// * no function by the given name in the given module could be found
     /// * the function has been found but the signature did not match
+    ///
+    /// [`Linker`]: crate::Linker
     pub fn link_closure<Args, Ret, F>(
         &mut self,
         store: &mut Store<T>,
@@ -148,15 +548,44 @@ impl<T> Instance<T> {
     ) -> core::result::Result<(), ClosureLinkFailed>
     where
         Args: crate::WasmArgs,
-        Ret: crate::WasmType,
-        F: for<'cc> FnMut(CallContext<'cc, T>, Args) -> core::result::Result<Ret, Trap> + 'static,
+        Ret: crate::WasmArgs,
+        F: for<'cc> FnMut(CallContext<'cc, T>, Args) -> core::result::Result<Ret, HostError>
+            + 'static,
     {
-        struct UserData<T, F> {
-            pub closure: F,
-            pub data: Rc<RefCell<T>>,
-        }
+        let closure = Rc::new(Exclusive(RefCell::new(closure)));
+        self.link_host_closure(store, module_name, function_name, closure)
+    }
+
+    /// Like [`Self::link_closure`], but for a closure that may be re-entered and is shared with
+    /// the other instances it is linked into.
+    pub(crate) fn link_shared_closure<Args, Ret, F>(
+        &mut self,
+        store: &mut Store<T>,
+        module_name: &str,
+        function_name: &str,
+        closure: Rc<Shared<F>>,
+    ) -> core::result::Result<(), ClosureLinkFailed>
+    where
+        Args: crate::WasmArgs,
+        Ret: crate::WasmArgs,
+        F: for<'cc> Fn(CallContext<'cc, T>, Args) -> core::result::Result<Ret, HostError> + 'static,
+    {
+        self.link_host_closure(store, module_name, function_name, closure)
+    }
 
-        unsafe extern "C" fn trampoline<Args, Ret, F, T>(
+    fn link_host_closure<Args, Ret, C>(
+        &mut self,
+        store: &mut Store<T>,
+        module_name: &str,
+        function_name: &str,
+        closure: Rc<C>,
+    ) -> core::result::Result<(), ClosureLinkFailed>
+    where
+        Args: crate::WasmArgs,
+        Ret: crate::WasmArgs,
+        C: HostClosure<T, Args, Ret> + 'static,
+    {
+        unsafe extern "C" fn trampoline<Args, Ret, C, T>(
             runtime: ffi::IM3Runtime,
             ctx: ffi::IM3ImportContext,
             sp: *mut u64,
@@ -164,27 +593,30 @@ impl<T> Instance<T> {
         ) -> *const c_void
         where
             Args: crate::WasmArgs,
-            Ret: crate::WasmType,
-            F: for<'cc> FnMut(CallContext<'cc, T>, Args) -> core::result::Result<Ret, Trap>
-                + 'static,
+            Ret: crate::WasmArgs,
+            C: HostClosure<T, Args, Ret> + 'static,
         {
             let runtime = NonNull::new(runtime)
                 .expect("wasm3 calls imported functions with non-null runtime");
             let ctx = NonNull::new(ctx)
                 .expect("wasm3 calls imported functions with non-null import context");
-            let user_data = NonNull::new(ctx.as_ref().userdata as *mut UserData<T, F>)
+            // shared, as the guest may call this function again before it returns
+            let closure = NonNull::new(ctx.as_ref().userdata as *mut C)
                 .expect("userdata passed to m3_LinkRawFunctionEx is non-null")
-                .as_mut();
+                .as_ref();
 
-            let args = Args::pop_from_stack(sp.add(Ret::SIZE_IN_SLOT_COUNT));
-            let ret =
-                (user_data.closure)(CallContext::from_raw(runtime, user_data.data.clone()), args);
+            // results are laid out in front of the arguments, one slot each
+            let args = Args::pop_from_stack(sp.add(Ret::SLOT_COUNT));
+            let frame_end = sp.add(Ret::SLOT_COUNT + Args::SLOT_COUNT);
+            let ret = function::with_call_stack(runtime.as_ptr(), frame_end, || {
+                closure.call(CallContext::from_raw(runtime), args)
+            });
             let result = match ret {
                 Ok(ret) => {
                     ret.push_on_stack(sp);
                     ffi::m3Err_none
                 }
-                Err(trap) => trap.as_cstr().as_ptr(),
+                Err(error) => error.into_ffi(runtime.as_ptr()),
             };
             result.cast()
         }
@@ -203,10 +635,171 @@ impl<T> Instance<T> {
                 })?;
         let signature = function_signature::<Args, Ret>();
 
-        let mut closure = Box::pin(UserData {
-            closure,
-            data: store.data_ref(),
+        let err = unsafe {
+            Error::from_ffi(ffi::m3_LinkRawFunctionEx(
+                self.0
+                    .get(&store.as_context())
+                    .context(ClosureLinkFailedSnafu {
+                        name: function_name,
+                    })?
+                    .as_ptr(),
+                module_name_cstr.as_ptr(),
+                function_name_cstr.as_ptr(),
+                signature.as_ptr(),
+                Some(trampoline::<Args, Ret, C, T>),
+                Rc::as_ptr(&closure).cast(),
+            ))
+        };
+
+        if let Err(err) = err {
+            if err != Error::FunctionNotFound {
+                Err(err).context(ClosureLinkFailedSnafu {
+                    name: function_name,
+                })?;
+            }
+        }
+
+        store.push_closure(self.0.instance_id(), closure);
+        Ok(())
+    }
+
+    /// Links the given closure to the corresponding module and function name, using a signature
+    /// that is only known at runtime.
+    /// The closure receives the call's parameters and fills in the results, which start out as
+    /// zero values of the types in `ty`. Results of the wrong type trap with [`Trap::Abort`].
+    ///
+    /// Like with [`Self::link_closure`], the closure can't be re-entered.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the following situations:
+    ///
+    /// * a memory allocation failed
+    /// * the function has been found but the signature did not match
+    pub fn link_dynamic<F>(
+        &mut self,
+        store: &mut Store<T>,
+        module_name: &str,
+        function_name: &str,
+        ty: FuncType,
+        closure: F,
+    ) -> core::result::Result<(), ClosureLinkFailed>
+    where
+        F: for<'cc> FnMut(
+                CallContext<'cc, T>,
+                &[Value],
+                &mut [Value],
+            ) -> core::result::Result<(), HostError>
+            + 'static,
+    {
+        let closure = Rc::new(DynClosure {
+            closure: Exclusive(RefCell::new(closure)),
+            ty,
         });
+        self.link_dynamic_host_closure(store, module_name, function_name, closure)
+    }
+
+    /// Like [`Self::link_dynamic`], but for a closure that may be re-entered and is shared with
+    /// the other instances it is linked into.
+    pub(crate) fn link_shared_dynamic<F>(
+        &mut self,
+        store: &mut Store<T>,
+        module_name: &str,
+        function_name: &str,
+        closure: Rc<DynClosure<Shared<F>>>,
+    ) -> core::result::Result<(), ClosureLinkFailed>
+    where
+        F: for<'cc> Fn(
+                CallContext<'cc, T>,
+                &[Value],
+                &mut [Value],
+            ) -> core::result::Result<(), HostError>
+            + 'static,
+    {
+        self.link_dynamic_host_closure(store, module_name, function_name, closure)
+    }
+
+    fn link_dynamic_host_closure<C>(
+        &mut self,
+        store: &mut Store<T>,
+        module_name: &str,
+        function_name: &str,
+        closure: Rc<DynClosure<C>>,
+    ) -> core::result::Result<(), ClosureLinkFailed>
+    where
+        C: DynHostClosure<T> + 'static,
+    {
+        unsafe extern "C" fn trampoline<C, T>(
+            runtime: ffi::IM3Runtime,
+            ctx: ffi::IM3ImportContext,
+            sp: *mut u64,
+            _mem: *mut c_void,
+        ) -> *const c_void
+        where
+            C: DynHostClosure<T> + 'static,
+        {
+            let runtime = NonNull::new(runtime)
+                .expect("wasm3 calls imported functions with non-null runtime");
+            let ctx = NonNull::new(ctx)
+                .expect("wasm3 calls imported functions with non-null import context");
+            // shared, as the guest may call this function again before it returns
+            let user_data = NonNull::new(ctx.as_ref().userdata as *mut DynClosure<C>)
+                .expect("userdata passed to m3_LinkRawFunctionEx is non-null")
+                .as_ref();
+
+            // results are laid out in front of the arguments, one slot each
+            let num_results = user_data.ty.results().len();
+            let params: Vec<Value> = user_data
+                .ty
+                .params()
+                .iter()
+                .enumerate()
+                .map(|(i, &ty)| Value::pop_from_stack(ty, sp.add(num_results + i)))
+                .collect();
+            let mut results: Vec<Value> = user_data
+                .ty
+                .results()
+                .iter()
+                .map(|&ty| Value::zero(ty))
+                .collect();
+
+            let frame_end = sp.add(num_results + params.len());
+            let ret = function::with_call_stack(runtime.as_ptr(), frame_end, || {
+                user_data
+                    .closure
+                    .call(CallContext::from_raw(runtime), &params, &mut results)
+            });
+            let result = match ret {
+                Ok(())
+                    if results
+                        .iter()
+                        .zip(user_data.ty.results())
+                        .all(|(result, &ty)| result.ty() == ty) =>
+                {
+                    for (i, result) in results.into_iter().enumerate() {
+                        result.push_on_stack(sp.add(i));
+                    }
+                    ffi::m3Err_none
+                }
+                Ok(()) => Trap::Abort.as_cstr().as_ptr(),
+                Err(error) => error.into_ffi(runtime.as_ptr()),
+            };
+            result.cast()
+        }
+
+        let module_name_cstr =
+            CString::new(module_name)
+                .map_err(Error::from)
+                .context(ClosureLinkFailedSnafu {
+                    name: function_name,
+                })?;
+        let function_name_cstr =
+            CString::new(function_name)
+                .map_err(Error::from)
+                .context(ClosureLinkFailedSnafu {
+                    name: function_name,
+                })?;
+        let signature = closure.ty.signature();
 
         let err = unsafe {
             Error::from_ffi(ffi::m3_LinkRawFunctionEx(
@@ -219,8 +812,8 @@ impl<T> Instance<T> {
                 module_name_cstr.as_ptr(),
                 function_name_cstr.as_ptr(),
                 signature.as_ptr(),
-                Some(trampoline::<Args, Ret, F, T>),
-                closure.as_mut().get_unchecked_mut() as *const UserData<T, F> as *const c_void,
+                Some(trampoline::<C, T>),
+                Rc::as_ptr(&closure).cast(),
             ))
         };
 
@@ -232,10 +825,183 @@ impl<T> Instance<T> {
             }
         }
 
-        store.push_closure(closure);
+        store.push_closure(self.0.instance_id(), closure);
+        Ok(())
+    }
+
+    /// Offers every function import that is still unresolved to `resolver`, which receives the
+    /// import's module name, field name and signature.
+    /// Imports for which the resolver returns a host function are linked to it, see
+    /// [`Self::link_dynamic`]; the others are left unresolved.
+    ///
+    /// This is meant to be called after all other functions have been linked, as a catch-all.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the following situations:
+    ///
+    /// * a memory allocation failed
+    /// * an import's module or field name is not valid UTF-8
+    pub fn link_unresolved<R>(
+        &mut self,
+        store: &mut Store<T>,
+        mut resolver: R,
+    ) -> core::result::Result<(), ClosureLinkFailed>
+    where
+        R: FnMut(&str, &str, &FuncType) -> Option<DynHostFunction<T>>,
+    {
+        let raw = self
+            .0
+            .get(&store.as_context())
+            .context(ClosureLinkFailedSnafu { name: "*" })?;
+
+        let num_functions = unsafe { ffi::m3_GetFunctionCount(raw.as_ptr()) };
+        for i in 0..num_functions {
+            let Some(function) =
+                NonNull::new(unsafe { ffi::m3_GetFunctionByIndex(raw.as_ptr(), i) })
+            else {
+                continue;
+            };
+
+            // an earlier iteration may have linked an import of the same name
+            let import = unsafe { unresolved_import(function) }
+                .context(ClosureLinkFailedSnafu { name: "*" })?;
+            let Some((module_name, function_name)) = import else {
+                continue;
+            };
+            let Some(ty) = FuncType::from_raw(function) else {
+                continue;
+            };
+
+            if let Some(host_function) = resolver(module_name, function_name, &ty) {
+                self.link_dynamic(store, module_name, function_name, ty, host_function)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Links the unresolved function imports of this instance from the module `module_name` to
+    /// the functions `other` exports under the same names.
+    ///
+    /// Calls from this instance then jump straight into the code of `other`, which has to be
+    /// another instance of the same store. Its start function runs first, so its imports should
+    /// be linked before. It can't be [unloaded](Store::unload) while this instance is loaded.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the following situations:
+    ///
+    /// * either instance doesn't belong to `store`
+    /// * an import's module or field name is not valid UTF-8
+    /// * [`Error::UnresolvedImports`] if `other` doesn't export some of the imported functions, in
+    ///   which case none of the imports are linked
+    /// * [`Error::LinkImport`] if an import's signature differs from the exported function's
+    /// * the start function of `other` failed
+    /// * a function of `other` could not be compiled
+    pub fn link_instance(
+        &mut self,
+        store: &mut Store<T>,
+        module_name: &str,
+        other: &Instance<T>,
+    ) -> Result<()> {
+        let raw = self.0.get(&store.as_context())?;
+        let other_raw = other.0.get(&store.as_context())?;
+
+        let mut links = Vec::new();
+        let mut missing = Vec::new();
+        let num_functions = unsafe { ffi::m3_GetFunctionCount(raw.as_ptr()) };
+        for i in 0..num_functions {
+            let Some(import) = NonNull::new(unsafe { ffi::m3_GetFunctionByIndex(raw.as_ptr(), i) })
+            else {
+                continue;
+            };
+            let Some((import_module, name)) = (unsafe { unresolved_import(import) })? else {
+                continue;
+            };
+            if import_module != module_name {
+                continue;
+            }
+
+            let name_cstr = CString::new(name)?;
+            let mut function = ptr::null_mut();
+            let result = unsafe {
+                Error::from_ffi(ffi::m3_FindModuleFunction(
+                    &mut function,
+                    other_raw.as_ptr(),
+                    name_cstr.as_ptr(),
+                ))
+            };
+            let function = match result {
+                Ok(()) => NonNull::new(function),
+                Err(Error::FunctionNotFound) => None,
+                Err(err) => return Err(err),
+            };
+            match function {
+                Some(function) if FuncType::from_raw(import) != FuncType::from_raw(function) => {
+                    return Err(Error::LinkImport {
+                        module: module_name.into(),
+                        name: name.into(),
+                        source: Box::new(Error::InvalidFunctionSignature),
+                    });
+                }
+                Some(function) => links.push((import, function)),
+                None => missing.push(UnresolvedImport {
+                    module: module_name.into(),
+                    name: name.into(),
+                    ty: FuncType::from_raw(import),
+                }),
+            }
+        }
+        ensure!(
+            missing.is_empty(),
+            UnresolvedImportsSnafu { imports: missing }
+        );
+
+        other.run_start(&mut *store)?;
+        for (import, function) in links {
+            unsafe { Error::from_ffi(ffi::m3_LinkFunction(import.as_ptr(), function.as_ptr()))? };
+        }
+        store.add_instance_link(self.0.instance_id(), other.0.instance_id());
         Ok(())
     }
 
+    /// Returns the function imports of this instance that have not been linked yet.
+    ///
+    /// wasm3 only reports a missing import once it is called, so this can be used to fail early
+    /// instead.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the following situations:
+    ///
+    /// * `ctx` isn't the store of this instance
+    /// * an import's module or field name is not valid UTF-8
+    pub fn unresolved_imports(
+        &self,
+        ctx: impl AsContext<Data = T>,
+    ) -> Result<Vec<UnresolvedImport>> {
+        let raw = self.0.get(&ctx.as_context())?;
+
+        let num_functions = unsafe { ffi::m3_GetFunctionCount(raw.as_ptr()) };
+        let mut imports = Vec::new();
+        for i in 0..num_functions {
+            let Some(function) =
+                NonNull::new(unsafe { ffi::m3_GetFunctionByIndex(raw.as_ptr(), i) })
+            else {
+                continue;
+            };
+            if let Some((module, name)) = unsafe { unresolved_import(function) }? {
+                imports.push(UnresolvedImport {
+                    module: module.into(),
+                    name: name.into(),
+                    ty: FuncType::from_raw(function),
+                });
+            }
+        }
+        Ok(imports)
+    }
+
     /// Looks up a function by the given name in this module.
     ///
     /// # Errors
@@ -252,46 +1018,314 @@ impl<T> Instance<T> {
     ) -> Result<Function<Args, Ret>>
     where
         Args: crate::WasmArgs,
-        Ret: crate::WasmType,
+        Ret: crate::WasmArgs,
     {
-        let function = store.find_function(function_name)?;
-        match function.instance(store)? {
-            Some(instance) if instance == self.0.get(&store.as_context())? => Ok(function),
-            _ => Err(Error::FunctionNotFound),
+        let ctx = store.as_context();
+        let function = self.find_raw_function(&ctx, function_name)?;
+        unsafe { Function::from_raw(&ctx, function) }
+    }
+
+    /// Looks up a function by the given name in this module, without checking its signature
+    /// against static types.
+    /// The signature can be inspected through [`DynFunction::ty`] instead.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the following situations:
+    ///
+    /// * `ctx` isn't the store of this instance
+    /// * no function by the given name in the given module could be found
+    pub fn find_function_dynamic(
+        &self,
+        ctx: impl AsContext<Data = T>,
+        function_name: &str,
+    ) -> Result<DynFunction> {
+        let ctx = ctx.as_context();
+        let function = self.find_raw_function(&ctx, function_name)?;
+        unsafe { DynFunction::from_raw(&ctx, function) }
+    }
+
+    fn find_raw_function(
+        &self,
+        ctx: &StoreContext<'_, T>,
+        function_name: &str,
+    ) -> Result<NonNull<ffi::M3Function>> {
+        let raw = self.0.get(ctx)?;
+        let mut function = ptr::null_mut();
+        let function_name = CString::new(function_name)?;
+        unsafe {
+            // only looks in this instance, as other instances of the same module export the same
+            // names
+            Error::from_ffi(ffi::m3_FindModuleFunction(
+                &mut function,
+                raw.as_ptr(),
+                function_name.as_ptr(),
+            ))?;
+        }
+        NonNull::new(function).ok_or(Error::FunctionNotFound)
+    }
+
+    /// Compiles every function of this module now instead of on its first call, so that calls
+    /// don't stall while wasm3 compiles them.
+    ///
+    /// See [`Store::code_memory`] for the memory the compiled code takes up.
+    ///
+    /// # Errors
+    ///
+    /// This function will return [`Error::CompileFunctions`] listing every function that could not
+    /// be compiled, or an error if `ctx` isn't the store of this instance. The functions that
+    /// did compile stay compiled.
+    pub fn compile_all(&self, mut ctx: impl AsContextMut<Data = T>) -> Result<()> {
+        let ctx = ctx.as_context_mut();
+        let raw = self.0.get(&ctx.as_context())?;
+
+        let num_functions = unsafe { ffi::m3_GetFunctionCount(raw.as_ptr()) };
+        let functions: Vec<_> = (0..num_functions)
+            .filter_map(|i| NonNull::new(unsafe { ffi::m3_GetFunctionByIndex(raw.as_ptr(), i) }))
+            .filter_map(|function| {
+                let result = unsafe { ffi::m3_CompileFunction(function.as_ptr()) };
+                let error = unsafe { Error::from_ffi(result) }.err()?;
+                Some(FunctionCompileError {
+                    function_index: unsafe { ffi::m3_GetFunctionIndex(function.as_ptr()) },
+                    name: unsafe {
+                        string_from_ptr(ffi::m3_GetFunctionDebugName(function.as_ptr()))
+                    },
+                    error,
+                })
+            })
+            .collect();
+        ensure!(functions.is_empty(), CompileFunctionsSnafu { functions });
+        Ok(())
+    }
+
+    /// Runs the start function of this module unless it already ran.
+    ///
+    /// Otherwise it runs before the first call into the module, as looking up a function only
+    /// borrows the store and the start function may call host functions, which get its data.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `ctx` isn't the store of this instance, or the
+    /// error the start function failed with.
+    pub fn run_start(&self, mut ctx: impl AsContextMut<Data = T>) -> Result<()> {
+        let ctx = ctx.as_context_mut();
+        let loaded = self.0.module(&ctx.as_context())?;
+        unsafe { function::run_start(ctx.as_ptr(), loaded) }
+    }
+
+    /// Looks up the function at `index` in the function table of this module.
+    ///
+    /// This is the index a guest uses as a function pointer, so a host function can take one as
+    /// an `i32` argument and call the guest back with it later.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the following situations:
+    ///
+    /// * the index is out of range of the table, see [`Trap::TableIndexOutOfRange`]
+    /// * the table element at the index is null, see [`Trap::TableElementIsNull`]
+    /// * the function could not be compiled
+    /// * the function has been found but the signature did not match
+    pub fn table_function<Args, Ret>(
+        &self,
+        ctx: impl AsContext<Data = T>,
+        index: u32,
+    ) -> Result<Function<Args, Ret>>
+    where
+        Args: crate::WasmArgs,
+        Ret: crate::WasmArgs,
+    {
+        let ctx = ctx.as_context();
+        let raw = self.0.get(&ctx)?;
+        if index >= unsafe { ffi::m3_GetTableSize(raw.as_ptr()) } {
+            return Err(Trap::TableIndexOutOfRange.into());
+        }
+
+        let mut function = ptr::null_mut();
+        unsafe {
+            Error::from_ffi(ffi::m3_GetTableFunction(&mut function, raw.as_ptr(), index))?;
+        }
+        let function = NonNull::new(function).ok_or(Trap::TableElementIsNull)?;
+        unsafe { Function::from_raw(&ctx, function) }
+    }
+
+    /// Looks up an exported global of this module by its name.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the following situations:
+    ///
+    /// * no global is exported under the given name
+    /// * the global has been found but its type did not match `V`
+    pub fn find_global<V>(&self, ctx: impl AsContext<Data = T>, name: &str) -> Result<Global<V>>
+    where
+        V: crate::GlobalType,
+    {
+        let ctx = ctx.as_context();
+        let name = CString::new(name)?;
+        let raw = self.0.get(&ctx)?;
+        let global = unsafe { ffi::m3_FindGlobal(raw.as_ptr(), name.as_ptr()) };
+        match NonNull::new(global) {
+            Some(global) => unsafe { Global::from_raw(&ctx, global, raw) },
+            None => Err(Error::GlobalNotFound),
         }
     }
 
     /// The name of this module.
     pub fn name<'a>(&self, ctx: impl AsContext) -> Result<&'a str> {
-        Ok(unsafe {
-            CStr::from_ptr(ffi::m3_GetModuleName(
-                self.0.get(&ctx.as_context())?.as_ptr(),
-            ))
-            .to_str()
-            .expect("module name is not valid utf-8")
-        })
+        let raw = self.0.get(&ctx.as_context())?;
+        unsafe { utf8_name(ffi::m3_GetModuleName(raw.as_ptr())) }
     }
-
-    //     /// Links wasi to this module.
-    //     #[cfg(feature = "wasi")]
-    //     pub fn link_wasi(&mut self) -> Result<()> {
-    //         unsafe { Error::from_ffi_res(ffi::m3_LinkWASI(self.raw)) }
-    //     }
 }
 
 impl<T> Instance<T> {
     pub(crate) unsafe fn from_raw(store: &StoreContext<T>, raw: NonNull<M3Module>) -> Self {
-        Instance(StoredData::new(store, raw), PhantomData)
+        Instance(StoredData::new(store, raw, raw), PhantomData)
+    }
+
+    pub(crate) fn stored(&self) -> StoredData<M3Module> {
+        self.0
+    }
+}
+
+/// The closure of a host function as its trampoline calls it.
+///
+/// A guest may call back into the host function that called it, so trampolines only ever borrow
+/// their closure immutably.
+trait HostClosure<T, Args, Ret> {
+    fn call(&self, ctx: CallContext<'_, T>, args: Args) -> core::result::Result<Ret, HostError>;
+}
+
+/// Like [`HostClosure`], for a signature that is only known at runtime.
+trait DynHostClosure<T> {
+    fn call(
+        &self,
+        ctx: CallContext<'_, T>,
+        params: &[Value],
+        results: &mut [Value],
+    ) -> core::result::Result<(), HostError>;
+}
+
+/// A `FnMut` closure, which fails calls that re-enter it.
+struct Exclusive<F>(RefCell<F>);
+
+/// A `Fn` closure, which may be re-entered.
+pub(crate) struct Shared<F>(pub F);
+
+/// A closure along with the signature it is called with, see [`Instance::link_dynamic`].
+pub(crate) struct DynClosure<C> {
+    pub closure: C,
+    pub ty: FuncType,
+}
+
+impl<T, Args, Ret, F> HostClosure<T, Args, Ret> for Exclusive<F>
+where
+    F: for<'cc> FnMut(CallContext<'cc, T>, Args) -> core::result::Result<Ret, HostError>,
+{
+    fn call(&self, ctx: CallContext<'_, T>, args: Args) -> core::result::Result<Ret, HostError> {
+        let mut closure = self
+            .0
+            .try_borrow_mut()
+            .map_err(|_| Error::HostFunctionReentered)?;
+        closure(ctx, args)
     }
 }
 
+impl<T, Args, Ret, F> HostClosure<T, Args, Ret> for Shared<F>
+where
+    F: for<'cc> Fn(CallContext<'cc, T>, Args) -> core::result::Result<Ret, HostError>,
+{
+    fn call(&self, ctx: CallContext<'_, T>, args: Args) -> core::result::Result<Ret, HostError> {
+        (self.0)(ctx, args)
+    }
+}
+
+impl<T, F> DynHostClosure<T> for Exclusive<F>
+where
+    F: for<'cc> FnMut(
+        CallContext<'cc, T>,
+        &[Value],
+        &mut [Value],
+    ) -> core::result::Result<(), HostError>,
+{
+    fn call(
+        &self,
+        ctx: CallContext<'_, T>,
+        params: &[Value],
+        results: &mut [Value],
+    ) -> core::result::Result<(), HostError> {
+        let mut closure = self
+            .0
+            .try_borrow_mut()
+            .map_err(|_| Error::HostFunctionReentered)?;
+        closure(ctx, params, results)
+    }
+}
+
+impl<T, F> DynHostClosure<T> for Shared<F>
+where
+    F: for<'cc> Fn(
+        CallContext<'cc, T>,
+        &[Value],
+        &mut [Value],
+    ) -> core::result::Result<(), HostError>,
+{
+    fn call(
+        &self,
+        ctx: CallContext<'_, T>,
+        params: &[Value],
+        results: &mut [Value],
+    ) -> core::result::Result<(), HostError> {
+        (self.0)(ctx, params, results)
+    }
+}
+
+/// Returns the module and field name of `function` if it is an import that hasn't been linked.
+///
+/// # Errors
+///
+/// Fails with [`ParseError::Malformed`] if either name is not valid UTF-8.
+///
+/// # Safety
+///
+/// `function` must be a function of a loaded module, which the names borrow from.
+unsafe fn unresolved_import<'a>(
+    function: NonNull<ffi::M3Function>,
+) -> Result<Option<(&'a str, &'a str)>> {
+    let mut import = unsafe { mem::zeroed::<ffi::M3ImportInfo>() };
+    if !unsafe { ffi::m3_GetFunctionImport(function.as_ptr(), &mut import) }
+        || unsafe { ffi::m3_IsFunctionCompiled(function.as_ptr()) }
+    {
+        return Ok(None);
+    }
+
+    let module = unsafe { utf8_name(import.moduleUtf8) }?;
+    let field = unsafe { utf8_name(import.fieldUtf8) }?;
+    Ok(Some((module, field)))
+}
+
+/// # Safety
+///
+/// `name` must be a valid pointer to a null-terminated string that outlives `'a`.
+unsafe fn utf8_name<'a>(name: *const c_char) -> Result<&'a str> {
+    unsafe { CStr::from_ptr(name) }
+        .to_str()
+        .map_err(|_| Error::Parse {
+            kind: ParseError::Malformed,
+        })
+}
+
 fn function_signature<Args, Ret>() -> Vec<c_char>
 where
     Args: crate::WasmArgs,
-    Ret: crate::WasmType,
+    Ret: crate::WasmArgs,
 {
     let mut signature = <Vec<c_char>>::new();
-    signature.push(Ret::SIGNATURE as c_char);
+    if Ret::SLOT_COUNT == 0 {
+        signature.push(b'v' as c_char);
+    } else {
+        Ret::append_signature(&mut signature);
+    }
     signature.push(b'(' as c_char);
     Args::append_signature(&mut signature);
     signature.push(b')' as c_char);
@@ -302,7 +1336,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{error::TrappedResult, make_func_wrapper};
+    use crate::{error::TrappedResult, make_func_wrapper, ValueType};
 
     make_func_wrapper!(mul_u32_and_f32_wrap: mul_u32_and_f32(a: u32, b: f32) -> f64);
     fn mul_u32_and_f32(a: u32, b: f32) -> f64 {
@@ -317,6 +1351,98 @@ mod tests {
     const TEST_BIN: &[u8] = include_bytes!("../tests/wasm_test_bins/wasm_test_bins.wasm");
     const STACK_SIZE: u32 = 1_000;
 
+    #[test]
+    fn multi_value_signature() {
+        let signature = function_signature::<(u32, f32), (i32, f64)>();
+        let signature: Vec<u8> = signature.into_iter().map(|c| c as u8).collect();
+        assert_eq!(signature, b"iF(if)\0");
+
+        let signature = function_signature::<i64, ()>();
+        let signature: Vec<u8> = signature.into_iter().map(|c| c as u8).collect();
+        assert_eq!(signature, b"v(I)\0");
+    }
+
+    #[test]
+    fn imports_and_exports() {
+        let module = [
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x06, 0x01, 0x60, 0x01, 0x7f,
+            0x01, 0x7f, 0x02, 0x1c, 0x03, 0x03, 0x65, 0x6e, 0x76, 0x01, 0x66, 0x00, 0x00, 0x03,
+            0x65, 0x6e, 0x76, 0x01, 0x74, 0x01, 0x70, 0x00, 0x00, 0x03, 0x65, 0x6e, 0x76, 0x01,
+            0x6d, 0x02, 0x00, 0x01, 0x03, 0x02, 0x01, 0x00, 0x07, 0x09, 0x02, 0x01, 0x67, 0x00,
+            0x01, 0x01, 0x6d, 0x02, 0x00,
+        ];
+        let func_type = FuncType::new([ValueType::I32], [ValueType::I32]);
+
+        let imports = read_imports(&module);
+        let imports: Vec<_> = imports
+            .iter()
+            .map(|import| {
+                (
+                    import.module(),
+                    import.name(),
+                    import.kind(),
+                    import.func_type(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            imports,
+            [
+                ("env", "f", ExternKind::Function, Some(&func_type)),
+                ("env", "t", ExternKind::Table, None),
+                ("env", "m", ExternKind::Memory, None),
+            ]
+        );
+
+        let exports = read_exports(&module);
+        let exports: Vec<_> = exports
+            .iter()
+            .map(|export| (export.name(), export.kind(), export.func_type()))
+            .collect();
+        assert_eq!(
+            exports,
+            [
+                ("g", ExternKind::Function, Some(&func_type)),
+                ("m", ExternKind::Memory, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn non_utf8_names_are_malformed() {
+        let name = unsafe { utf8_name(b"env\0".as_ptr().cast()) };
+        assert_eq!(name, Ok("env"));
+        let name = unsafe { utf8_name(b"\xffenv\0".as_ptr().cast()) };
+        assert_eq!(
+            name,
+            Err(Error::Parse {
+                kind: ParseError::Malformed
+            })
+        );
+    }
+
+    #[test]
+    fn custom_sections_and_names() {
+        let module = [
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x00, 0x07, 0x04, 0x6d, 0x65, 0x74,
+            0x61, 0x01, 0x02, 0x00, 0x12, 0x04, 0x6e, 0x61, 0x6d, 0x65, 0x00, 0x02, 0x01, 0x6d,
+            0x01, 0x04, 0x01, 0x00, 0x01, 0x66, 0x02, 0x01, 0x00,
+        ];
+
+        let sections: Vec<_> = read_custom_sections(&module)
+            .map(|section| (section.name(), section.data()))
+            .collect();
+        assert_eq!(sections[0], ("meta", &[0x01, 0x02][..]));
+        assert_eq!(sections[1].0, "name");
+
+        // the local names are skipped over
+        let names = read_names(sections[1].1);
+        assert_eq!(names.module(), Some("m"));
+        assert_eq!(names.function(0), Some("f"));
+        assert_eq!(names.function(1), None);
+        assert_eq!(names.functions().collect::<Vec<_>>(), [(0, "f")]);
+    }
+
     #[test]
     fn module_parse() {
         let env = Environment::new().expect("env alloc failure");
@@ -327,40 +1453,51 @@ mod tests {
             0x00, 0x0f, 0x0b, 0x20, 0x00, 0x41, 0x02, 0x6b, 0x10, 0x00, 0x20, 0x00, 0x41, 0x01,
             0x6b, 0x10, 0x00, 0x6a, 0x0f, 0x0b,
         ];
-        let _ = Instance::parse(&env, &fib32[..]).unwrap();
+        let _ = Module::parse(&env, fib32.to_vec()).unwrap();
     }
 
     #[test]
     fn test_link_functions() {
         let env = Environment::new().expect("env alloc failure");
-        let runtime = Store::new(&env, STACK_SIZE, ()).expect("runtime init failure");
-        let mut module = runtime.parse_and_instantiate(TEST_BIN).unwrap();
-        module
-            .link_function::<(u32, f32), f64>("env", "mul_u32_and_f32", mul_u32_and_f32_wrap)
+        let mut store = Store::new(&env, STACK_SIZE, ()).expect("runtime init failure");
+        let module = Module::parse(&env, TEST_BIN).unwrap();
+        let mut instance = store.instantiate(module).unwrap();
+        instance
+            .link_function::<(u32, f32), f64>(
+                &mut store,
+                "env",
+                "mul_u32_and_f32",
+                mul_u32_and_f32_wrap,
+            )
             .unwrap();
-        module
-            .link_function::<(), ()>("env", "hello", hello_wrap)
+        instance
+            .link_function::<(), ()>(&mut store, "env", "hello", hello_wrap)
             .unwrap();
     }
 
     #[test]
     fn test_link_closures() {
         let env = Environment::new().expect("env alloc failure");
-        let runtime = Store::new(&env, STACK_SIZE, ()).expect("runtime init failure");
-        let mut module = runtime.parse_and_instantiate(TEST_BIN).unwrap();
-        module
+        let mut store = Store::new(&env, STACK_SIZE, ()).expect("runtime init failure");
+        let module = Module::parse(&env, TEST_BIN).unwrap();
+        let mut instance = store.instantiate(module).unwrap();
+        instance
             .link_closure(
+                &mut store,
                 "env",
                 "mul_u32_and_f32",
-                |_ctx, args: (u32, f32)| -> TrappedResult<f64> {
+                |_ctx, args: (u32, f32)| -> core::result::Result<f64, HostError> {
                     Ok(mul_u32_and_f32(args.0, args.1))
                 },
             )
             .unwrap();
-        module
-            .link_closure("env", "hello", |_ctx, _args: ()| -> TrappedResult<()> {
-                hello()
-            })
+        instance
+            .link_closure(
+                &mut store,
+                "env",
+                "hello",
+                |_ctx, _args: ()| -> core::result::Result<(), HostError> { Ok(hello()?) },
+            )
             .unwrap();
     }
 }
@@ -0,0 +1,118 @@
+//! Finding out where the interpreter spends its time.
+use alloc::{string::String, vec, vec::Vec};
+use core::{
+    ffi::CStr,
+    ptr::{self, NonNull},
+};
+
+use crate::{error::string_from_ptr, store::Store};
+
+/// How often a wasm function was called and how long it ran, see [`Store::profile`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FunctionProfile {
+    /// The index of the function in its module, counting imported functions first.
+    pub function_index: u32,
+    /// The debug name of the function, taken from the name section or an export or import.
+    pub name: Option<String>,
+    /// The number of calls to the function.
+    pub calls: u64,
+    /// The time spent in the function in units of the [profile clock](Store::set_profile_clock),
+    /// including the time spent in the functions it called.
+    ///
+    /// Recursive calls are counted once for each call, so this can exceed the total run time.
+    pub time: u64,
+}
+
+/// How often an interpreter operation ran, see [`op_profile`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OpProfile {
+    /// The name of the C function implementing the operation, e.g. `op_i32_Add_rs`.
+    pub name: &'static str,
+    /// The number of times the operation ran.
+    pub hits: u64,
+}
+
+impl<T> Store<T> {
+    /// Sets the monotonic `clock` in any unit the time of profiled functions is read from.
+    ///
+    /// Until a clock is set, only calls are counted.
+    pub fn set_profile_clock(&mut self, clock: extern "C" fn() -> u64) {
+        unsafe {
+            ffi::m3_SetProfileClock(self.as_ptr(), Some(clock as unsafe extern "C" fn() -> u64))
+        };
+    }
+
+    /// Returns the profiles of the wasm functions of this store that have been called since it was
+    /// created or [reset](Self::reset_profile), those that took the most time first.
+    ///
+    /// Host functions aren't profiled, their time counts towards the wasm functions calling them.
+    pub fn profile(&self) -> Vec<FunctionProfile> {
+        let mut profiles: Vec<_> = self
+            .functions()
+            .filter_map(|function| {
+                let mut raw = ffi::M3FunctionProfile { calls: 0, time: 0 };
+                unsafe { ffi::m3_GetFunctionProfile(function.as_ptr(), &mut raw) };
+                (raw.calls > 0).then(|| FunctionProfile {
+                    function_index: unsafe { ffi::m3_GetFunctionIndex(function.as_ptr()) },
+                    name: unsafe {
+                        string_from_ptr(ffi::m3_GetFunctionDebugName(function.as_ptr()))
+                    },
+                    calls: raw.calls,
+                    time: raw.time,
+                })
+            })
+            .collect();
+        profiles.sort_by(|a, b| b.time.cmp(&a.time).then(b.calls.cmp(&a.calls)));
+        profiles
+    }
+
+    /// Clears the profiles of all functions of this store.
+    pub fn reset_profile(&mut self) {
+        for function in self.functions() {
+            unsafe { ffi::m3_ResetFunctionProfile(function.as_ptr()) };
+        }
+    }
+
+    fn functions(&self) -> impl Iterator<Item = NonNull<ffi::M3Function>> + '_ {
+        self.modules().values().flat_map(|module| {
+            let raw = module.inner.as_ptr();
+            let num_functions = unsafe { ffi::m3_GetFunctionCount(raw) };
+            (0..num_functions)
+                .filter_map(move |i| NonNull::new(unsafe { ffi::m3_GetFunctionByIndex(raw, i) }))
+        })
+    }
+}
+
+/// Returns how often each interpreter operation ran in all stores since the program started or
+/// [`reset_op_profile`] was called, the most frequent ones first.
+///
+/// Counting operations slows down the interpreter, which the time of [function
+/// profiles](Store::profile) includes.
+pub fn op_profile() -> Vec<OpProfile> {
+    let len = unsafe { ffi::m3_GetOpProfile(ptr::null_mut(), 0) };
+    let mut raw = vec![
+        ffi::M3OpProfile {
+            name: ptr::null(),
+            hits: 0
+        };
+        len as usize
+    ];
+    let len = unsafe { ffi::m3_GetOpProfile(raw.as_mut_ptr(), len) }.min(len);
+    let mut profiles: Vec<_> = raw[..len as usize]
+        .iter()
+        .map(|op| OpProfile {
+            // the names are string literals in the interpreter
+            name: unsafe { CStr::from_ptr(op.name) }
+                .to_str()
+                .unwrap_or("<invalid>"),
+            hits: op.hits,
+        })
+        .collect();
+    profiles.sort_by(|a, b| b.hits.cmp(&a.hits).then(a.name.cmp(b.name)));
+    profiles
+}
+
+/// Clears the counts of all interpreter operations.
+pub fn reset_op_profile() {
+    unsafe { ffi::m3_ResetOpProfile() };
+}
@@ -0,0 +1,183 @@
+//! A minimal reader for the parts of the wasm binary format that wasm3 doesn't expose.
+//!
+//! Modules are only read after wasm3 has successfully parsed them, so malformed input simply ends
+//! the read early instead of producing detailed errors.
+use alloc::vec::Vec;
+use core::str;
+
+use crate::{FuncType, ValueType};
+
+const HEADER_LEN: usize = 8;
+
+pub(crate) const SECTION_CUSTOM: u8 = 0;
+pub(crate) const SECTION_TYPE: u8 = 1;
+pub(crate) const SECTION_IMPORT: u8 = 2;
+pub(crate) const SECTION_FUNCTION: u8 = 3;
+pub(crate) const SECTION_EXPORT: u8 = 7;
+
+pub(crate) const KIND_FUNCTION: u8 = 0x00;
+pub(crate) const KIND_TABLE: u8 = 0x01;
+pub(crate) const KIND_MEMORY: u8 = 0x02;
+pub(crate) const KIND_GLOBAL: u8 = 0x03;
+
+#[derive(Clone)]
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    pub fn read_u8(&mut self) -> Option<u8> {
+        let (&byte, rest) = self.bytes.split_first()?;
+        self.bytes = rest;
+        Some(byte)
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if len > self.bytes.len() {
+            return None;
+        }
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Some(bytes)
+    }
+
+    /// The bytes that haven't been read yet.
+    pub fn remaining(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Reads an unsigned LEB128 integer, rejecting encodings of values that don't fit in 32 bits.
+    pub fn read_u32(&mut self) -> Option<u32> {
+        let mut result = 0u32;
+        for shift in (0..35).step_by(7) {
+            let byte = self.read_u8()?;
+            // the fifth byte only holds the top 4 bits
+            if shift == 28 && byte & 0x70 != 0 {
+                return None;
+            }
+            result |= ((byte & 0x7f) as u32) << shift;
+            if byte & 0x80 == 0 {
+                return Some(result);
+            }
+        }
+        None
+    }
+
+    pub fn read_name(&mut self) -> Option<&'a str> {
+        let len = self.read_u32()? as usize;
+        str::from_utf8(self.read_bytes(len)?).ok()
+    }
+
+    pub fn read_value_type(&mut self) -> Option<Option<ValueType>> {
+        Some(match self.read_u8()? {
+            0x7f => Some(ValueType::I32),
+            0x7e => Some(ValueType::I64),
+            0x7d => Some(ValueType::F32),
+            0x7c => Some(ValueType::F64),
+            // vectors and references have no `ValueType`
+            _ => None,
+        })
+    }
+
+    /// Reads a function type, returning `None` inside if it uses types without a [`ValueType`].
+    pub fn read_func_type(&mut self) -> Option<Option<FuncType>> {
+        if self.read_u8()? != 0x60 {
+            return None;
+        }
+        let params = self.read_value_types()?;
+        let results = self.read_value_types()?;
+        Some(
+            params
+                .zip(results)
+                .map(|(params, results)| FuncType::new(params, results)),
+        )
+    }
+
+    fn read_value_types(&mut self) -> Option<Option<Vec<ValueType>>> {
+        let count = self.read_u32()?;
+        let mut types = Some(Vec::new());
+        for _ in 0..count {
+            match (self.read_value_type()?, types.as_mut()) {
+                (Some(ty), Some(types)) => types.push(ty),
+                _ => types = None,
+            }
+        }
+        Some(types)
+    }
+
+    pub fn skip_limits(&mut self) -> Option<()> {
+        let flags = self.read_u8()?;
+        self.read_u32()?;
+        if flags & 0x01 != 0 {
+            self.read_u32()?;
+        }
+        Some(())
+    }
+}
+
+/// A section of a wasm module.
+#[derive(Clone, Copy)]
+pub(crate) struct Section<'a> {
+    pub id: u8,
+    pub data: &'a [u8],
+}
+
+/// Iterates over the sections of a wasm module, skipping its header.
+pub(crate) fn sections(module: &[u8]) -> impl Iterator<Item = Section<'_>> {
+    let mut reader = Reader::new(module.get(HEADER_LEN..).unwrap_or_default());
+    core::iter::from_fn(move || {
+        let id = reader.read_u8()?;
+        let len = reader.read_u32()? as usize;
+        let data = reader.read_bytes(len)?;
+        Some(Section { id, data })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_leb128() {
+        let mut reader = Reader::new(&[0xe5, 0x8e, 0x26, 0x7f]);
+        assert_eq!(reader.read_u32(), Some(624485));
+        assert_eq!(reader.read_u32(), Some(127));
+        assert_eq!(reader.read_u32(), None);
+    }
+
+    #[test]
+    fn read_leb128_overflow() {
+        let mut reader = Reader::new(&[0xff, 0xff, 0xff, 0xff, 0xff, 0x01]);
+        assert_eq!(reader.read_u32(), None);
+
+        let mut reader = Reader::new(&[0xff, 0xff, 0xff, 0xff, 0x0f]);
+        assert_eq!(reader.read_u32(), Some(u32::MAX));
+        // bits above 32 in the fifth byte
+        let mut reader = Reader::new(&[0x80, 0x80, 0x80, 0x80, 0x10]);
+        assert_eq!(reader.read_u32(), None);
+        let mut reader = Reader::new(&[0xff, 0xff, 0xff, 0xff, 0x7f]);
+        assert_eq!(reader.read_u32(), None);
+    }
+
+    #[test]
+    fn read_func_type() {
+        let mut reader = Reader::new(&[0x60, 0x02, 0x7f, 0x7d, 0x01, 0x7c]);
+        assert_eq!(
+            reader.read_func_type(),
+            Some(Some(FuncType::new(
+                [ValueType::I32, ValueType::F32],
+                [ValueType::F64]
+            )))
+        );
+        assert_eq!(reader.read_u8(), None);
+
+        // unsupported types are skipped over rather than ending the read
+        let mut reader = Reader::new(&[0x60, 0x01, 0x7b, 0x00, 0x2a]);
+        assert_eq!(reader.read_func_type(), Some(None));
+        assert_eq!(reader.read_u8(), Some(0x2a));
+    }
+}
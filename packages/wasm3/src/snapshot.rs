@@ -0,0 +1,359 @@
+//! Capturing the state of a store to rewind it to later.
+use alloc::vec::Vec;
+use core::ptr::{self, NonNull};
+
+use snafu::ensure;
+
+use crate::{
+    error::{Error, InvalidSnapshotSnafu, MemoryLimitExceededSnafu, Result, SnapshotMismatchSnafu},
+    reader::Reader,
+    store::Store,
+    Value, ValueType,
+};
+
+const MAGIC: &[u8; 4] = b"w3ss";
+const VERSION: u32 = 3;
+const PAGE_SIZE: usize = 64 * 1024;
+
+/// The linear memory and mutable globals of a store and which start functions ran, see
+/// [`Store::snapshot`].
+///
+/// A snapshot can be written out with [`Self::to_bytes`] and read back with
+/// [`Self::from_bytes`]. It records a hash of the modules of its store, so that it is only
+/// restored into stores running the same program.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    modules_hash: u64,
+    memory_pages: u32,
+    // without trailing zeros, which restoring fills in
+    memory: Vec<u8>,
+    globals: Vec<Value>,
+    // whether the start function of each module ran, in the order they were instantiated in
+    started: Vec<bool>,
+}
+
+impl Snapshot {
+    /// The size of the captured memory in pages of 64KiB.
+    pub fn memory_pages(&self) -> u32 {
+        self.memory_pages
+    }
+
+    /// The hash of the modules of the store this snapshot was taken of.
+    ///
+    /// It covers the bytes of every module in the order they were instantiated in.
+    pub fn modules_hash(&self) -> u64 {
+        self.modules_hash
+    }
+
+    /// Encodes this snapshot, leaving out the zeroed memory at its end.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(
+            self.memory.len() + 9 * self.globals.len() + self.started.len() + 40,
+        );
+        bytes.extend_from_slice(MAGIC);
+        write_u32(&mut bytes, VERSION);
+        bytes.extend_from_slice(&self.modules_hash.to_le_bytes());
+        write_u32(&mut bytes, self.memory_pages);
+        write_u32(&mut bytes, self.globals.len() as u32);
+        for global in &self.globals {
+            let (ty, bits) = match *global {
+                Value::I32(val) => (0x7f, val as u32 as u64),
+                Value::I64(val) => (0x7e, val as u64),
+                Value::F32(val) => (0x7d, val.to_bits() as u64),
+                Value::F64(val) => (0x7c, val.to_bits()),
+            };
+            bytes.push(ty);
+            bytes.extend_from_slice(&bits.to_le_bytes());
+        }
+        write_u32(&mut bytes, self.started.len() as u32);
+        bytes.extend(self.started.iter().map(|&started| started as u8));
+        write_u32(&mut bytes, self.memory.len() as u32);
+        bytes.extend_from_slice(&self.memory);
+        bytes
+    }
+
+    /// Decodes a snapshot encoded by [`Self::to_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return [`Error::InvalidSnapshot`] if `bytes` isn't an encoded snapshot,
+    /// or was encoded by a version of this crate whose snapshots don't record their modules and
+    /// which of their start functions ran.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Self::read(&mut Reader::new(bytes)).ok_or_else(|| InvalidSnapshotSnafu.build())
+    }
+
+    /// Decodes a snapshot like [`Self::from_bytes`], making sure it was taken of a store running
+    /// the same modules as `store`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return [`Error::InvalidSnapshot`] if `bytes` isn't an encoded snapshot,
+    /// or [`Error::SnapshotMismatch`] if it was taken of a store running other modules.
+    pub fn from_bytes_for<T>(bytes: &[u8], store: &Store<T>) -> Result<Self> {
+        let snapshot = Self::from_bytes(bytes)?;
+        ensure!(
+            snapshot.modules_hash == store.modules_hash(),
+            SnapshotMismatchSnafu
+        );
+        Ok(snapshot)
+    }
+
+    fn read(reader: &mut Reader<'_>) -> Option<Self> {
+        if reader.read_bytes(MAGIC.len())? != MAGIC || reader.read_u32()? != VERSION {
+            return None;
+        }
+        let mut modules_hash = [0; 8];
+        modules_hash.copy_from_slice(reader.read_bytes(8)?);
+        let modules_hash = u64::from_le_bytes(modules_hash);
+        let memory_pages = reader.read_u32()?;
+        let num_globals = reader.read_u32()?;
+        let globals = (0..num_globals)
+            .map(|_| {
+                let ty = reader.read_value_type()??;
+                let mut bits = [0; 8];
+                bits.copy_from_slice(reader.read_bytes(8)?);
+                let bits = u64::from_le_bytes(bits);
+                Some(match ty {
+                    ValueType::I32 => Value::I32(bits as u32 as i32),
+                    ValueType::I64 => Value::I64(bits as i64),
+                    ValueType::F32 => Value::F32(f32::from_bits(bits as u32)),
+                    ValueType::F64 => Value::F64(f64::from_bits(bits)),
+                })
+            })
+            .collect::<Option<_>>()?;
+        let num_modules = reader.read_u32()?;
+        let started = (0..num_modules)
+            .map(|_| match reader.read_u8()? {
+                0 => Some(false),
+                1 => Some(true),
+                _ => None,
+            })
+            .collect::<Option<_>>()?;
+        let memory_len = reader.read_u32()? as usize;
+        if memory_len > (memory_pages as usize).saturating_mul(PAGE_SIZE) {
+            return None;
+        }
+        let memory = reader.read_bytes(memory_len)?.to_vec();
+        reader.read_u8().is_none().then_some(Snapshot {
+            modules_hash,
+            memory_pages,
+            memory,
+            globals,
+            started,
+        })
+    }
+}
+
+fn write_u32(bytes: &mut Vec<u8>, mut val: u32) {
+    loop {
+        let byte = (val & 0x7f) as u8;
+        val >>= 7;
+        if val == 0 {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+impl<T> Store<T> {
+    /// Captures the linear memory, its size, the mutable globals of every module of this store and
+    /// whether their start functions ran.
+    ///
+    /// Everything else, such as the data of the store, is left out.
+    pub fn snapshot(&self) -> Snapshot {
+        let memory = self.memory();
+        let len = memory
+            .iter()
+            .rposition(|&byte| byte != 0)
+            .map_or(0, |i| i + 1);
+        Snapshot {
+            modules_hash: self.modules_hash(),
+            memory_pages: self.memory_pages(),
+            memory: memory[..len].to_vec(),
+            globals: self
+                .mutable_globals()
+                .map(|global| unsafe { global_value(global) })
+                .collect(),
+            started: self
+                .modules()
+                .values()
+                .map(|module| module.started.get())
+                .collect(),
+        }
+    }
+
+    /// Rewinds the memory and mutable globals of this store to a snapshot, shrinking the memory if
+    /// it grew since.
+    ///
+    /// The start functions that ran before the snapshot was taken don't run again, e.g. when it is
+    /// restored into a fresh store. Those that ran since aren't undone and don't run again either.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the following situations:
+    ///
+    /// * [`Error::SnapshotMismatch`] if the snapshot was taken of a store running other modules
+    /// * [`Error::MemoryLimitExceeded`] if the captured memory is larger than this store may grow
+    ///   its memory to
+    /// * a memory allocation failed
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<()> {
+        let globals: Vec<_> = self.mutable_globals().collect();
+        ensure!(
+            snapshot.modules_hash == self.modules_hash()
+                && globals.len() == snapshot.globals.len()
+                && globals
+                    .iter()
+                    .zip(&snapshot.globals)
+                    .all(|(&global, value)| { global_type(global) == Some(value.ty()) })
+                && self.modules().len() == snapshot.started.len(),
+            SnapshotMismatchSnafu
+        );
+        if snapshot.memory_pages != self.memory_pages() {
+            ensure!(
+                snapshot.memory_pages <= self.max_memory_pages(),
+                MemoryLimitExceededSnafu
+            );
+            unsafe {
+                Error::from_ffi(ffi::m3_SetMemoryPages(self.as_ptr(), snapshot.memory_pages))?
+            };
+        }
+
+        let memory = self.memory_mut();
+        let (captured, zeroed) = memory.split_at_mut(snapshot.memory.len());
+        captured.copy_from_slice(&snapshot.memory);
+        zeroed.fill(0);
+
+        for (global, &value) in globals.into_iter().zip(&snapshot.globals) {
+            let mut tagged = ffi::M3TaggedValue {
+                type_: unsafe { ffi::m3_GetGlobalType(global.as_ptr()) },
+                value: ffi::M3TaggedValue_M3ValueUnion { i64: 0 },
+            };
+            unsafe {
+                value.push_on_stack(ptr::addr_of_mut!(tagged.value).cast());
+                Error::from_ffi(ffi::m3_SetGlobal(global.as_ptr(), &tagged))?;
+            }
+        }
+        // wasm3 can't be made to run a start function again
+        for (module, &started) in self.modules().values().zip(&snapshot.started) {
+            if started {
+                module.started.set(true);
+            }
+        }
+        Ok(())
+    }
+
+    /// Hashes the bytes of every module of this store in the order they were instantiated in, with
+    /// 64-bit FNV-1a.
+    fn modules_hash(&self) -> u64 {
+        const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+        let mut hash = OFFSET_BASIS;
+        for module in self.modules().values() {
+            // the length keeps the bytes of consecutive modules apart
+            let len = (module.data.len() as u64).to_le_bytes();
+            for &byte in len.iter().chain(module.data.iter()) {
+                hash = (hash ^ u64::from(byte)).wrapping_mul(PRIME);
+            }
+        }
+        hash
+    }
+
+    fn mutable_globals(&self) -> impl Iterator<Item = NonNull<ffi::M3Global>> + '_ {
+        self.modules().values().flat_map(|module| {
+            let raw = module.inner.as_ptr();
+            let num_globals = unsafe { ffi::m3_GetGlobalCount(raw) };
+            (0..num_globals)
+                .filter_map(move |i| NonNull::new(unsafe { ffi::m3_GetGlobalByIndex(raw, i) }))
+                .filter(|global| unsafe { ffi::m3_IsGlobalMutable(global.as_ptr()) })
+        })
+    }
+}
+
+fn global_type(global: NonNull<ffi::M3Global>) -> Option<ValueType> {
+    ValueType::from_raw(unsafe { ffi::m3_GetGlobalType(global.as_ptr()) })
+}
+
+/// # Safety
+///
+/// `global` must belong to a loaded module.
+unsafe fn global_value(global: NonNull<ffi::M3Global>) -> Value {
+    let ty = global_type(global).expect("wasm3 only supports globals of value types");
+    let mut tagged = ffi::M3TaggedValue {
+        type_: unsafe { ffi::m3_GetGlobalType(global.as_ptr()) },
+        value: ffi::M3TaggedValue_M3ValueUnion { i64: 0 },
+    };
+    unsafe {
+        ffi::m3_GetGlobal(global.as_ptr(), &mut tagged);
+        Value::pop_from_stack(ty, ptr::addr_of_mut!(tagged.value).cast())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot() -> Snapshot {
+        Snapshot {
+            modules_hash: 0x0123_4567_89ab_cdef,
+            memory_pages: 2,
+            memory: [0u8, 1, 2, 3].repeat(30_000),
+            globals: alloc::vec![
+                Value::I32(-1),
+                Value::I64(i64::MIN),
+                Value::F32(1.5),
+                Value::F64(-0.25),
+            ],
+            started: alloc::vec![true, false],
+        }
+    }
+
+    #[test]
+    fn bytes_roundtrip() {
+        let snapshot = snapshot();
+        assert_eq!(Snapshot::from_bytes(&snapshot.to_bytes()), Ok(snapshot));
+
+        let empty = Snapshot {
+            modules_hash: 0,
+            memory_pages: 0,
+            memory: Vec::new(),
+            globals: Vec::new(),
+            started: Vec::new(),
+        };
+        assert_eq!(Snapshot::from_bytes(&empty.to_bytes()), Ok(empty));
+    }
+
+    #[test]
+    fn invalid_bytes() {
+        let bytes = snapshot().to_bytes();
+        assert_eq!(
+            Snapshot::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(Error::InvalidSnapshot)
+        );
+        assert_eq!(
+            Snapshot::from_bytes(&[&bytes[..], &[0]].concat()),
+            Err(Error::InvalidSnapshot)
+        );
+        assert_eq!(
+            Snapshot::from_bytes(b"\0asm\x01\0\0\0"),
+            Err(Error::InvalidSnapshot)
+        );
+
+        // snapshots of older versions don't record their modules or which of them started
+        for version in 1..VERSION {
+            let mut old = bytes.clone();
+            old[MAGIC.len()] = version as u8;
+            assert_eq!(Snapshot::from_bytes(&old), Err(Error::InvalidSnapshot));
+        }
+
+        // more memory than the pages hold
+        let mut snapshot = snapshot();
+        snapshot.memory_pages = 1;
+        assert_eq!(
+            Snapshot::from_bytes(&snapshot.to_bytes()),
+            Err(Error::InvalidSnapshot)
+        );
+    }
+}
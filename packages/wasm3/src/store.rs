@@ -1,37 +1,37 @@
-use alloc::{
-    borrow::Cow,
-    boxed::Box,
-    ffi::CString,
-    rc::Rc,
-    string::{String, ToString},
-    vec::Vec,
-};
+use alloc::{boxed::Box, collections::BTreeMap, ffi::CString, rc::Rc, vec::Vec};
 use core::{
-    cell::{Ref, RefCell, RefMut},
-    ffi::CStr,
+    ffi::c_void,
     hash::Hash,
     marker::PhantomData,
     mem,
-    pin::Pin,
     ptr::{self, NonNull},
     slice,
+    sync::atomic::{AtomicUsize, Ordering},
 };
 
 use snafu::ensure;
 
 use crate::{
     environment::Environment,
-    error::{Error, ModuleLoadEnvMismatchSnafu, Result, StoreMismatchSnafu},
-    function::Function,
-    module::{Instance, Module, RawModule},
+    error::{
+        Error, ErrorInfo, HostErrorSlot, InstanceInUseSnafu, InstanceUnloadedSnafu,
+        MemoryLimitExceededSnafu, ModuleLoadEnvMismatchSnafu, Result, StoreMismatchSnafu,
+    },
+    function::{DynFunction, Function},
+    interrupt::InterruptHandle,
+    memory::Memory,
+    module::{Instance, LoadedModule, Module},
 };
 
-type PinnedAnyClosure = Pin<Box<dyn core::any::Any + 'static>>;
+// shared, as the closures defined through a `Linker` are linked into every instance by reference
+type AnyClosure = Rc<dyn core::any::Any + 'static>;
 
 #[derive(Debug)]
 pub(crate) struct StoredData<T> {
     raw: NonNull<T>,
     store_id: usize,
+    // the id of the instance `raw` belongs to, see `RuntimeHeader::modules`
+    instance_id: usize,
 }
 
 impl<T> Clone for StoredData<T> {
@@ -43,7 +43,9 @@ impl<T> Copy for StoredData<T> {}
 
 impl<T> PartialEq for StoredData<T> {
     fn eq(&self, other: &Self) -> bool {
-        self.raw == other.raw && self.store_id == other.store_id
+        self.raw == other.raw
+            && self.store_id == other.store_id
+            && self.instance_id == other.instance_id
     }
 }
 impl<T> Eq for StoredData<T> {}
@@ -52,21 +54,53 @@ impl<T> Hash for StoredData<T> {
     fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
         self.raw.hash(state);
         self.store_id.hash(state);
+        self.instance_id.hash(state);
     }
 }
 
 impl<T> StoredData<T> {
-    pub fn new<D>(store: &StoreContext<D>, raw: NonNull<T>) -> Self {
+    /// `module` is the loaded module `raw` belongs to.
+    pub fn new<D>(
+        store: &StoreContext<D>,
+        raw: NonNull<T>,
+        module: NonNull<ffi::M3Module>,
+    ) -> Self {
+        let (&instance_id, _) = store
+            .modules()
+            .iter()
+            .find(|(_, loaded)| loaded.inner == module)
+            .expect("stored data belongs to a loaded module");
         Self {
             raw,
             store_id: store.id(),
+            instance_id,
         }
     }
 
     pub fn get<D>(&self, ctx: &StoreContext<D>) -> Result<NonNull<T>> {
-        ensure!(ctx.id() == self.store_id, StoreMismatchSnafu);
+        self.module(ctx)?;
         Ok(self.raw)
     }
+
+    /// Like [`Self::get`], also returning the loaded module of the instance `raw` belongs to.
+    pub fn get_with_module<'a, D>(
+        &self,
+        ctx: &StoreContext<'a, D>,
+    ) -> Result<(NonNull<T>, &'a LoadedModule)> {
+        Ok((self.raw, self.module(ctx)?))
+    }
+
+    /// Returns the loaded module of the instance `raw` belongs to.
+    pub fn module<'a, D>(&self, ctx: &StoreContext<'a, D>) -> Result<&'a LoadedModule> {
+        ensure!(ctx.id() == self.store_id, StoreMismatchSnafu);
+        ctx.modules()
+            .get(&self.instance_id)
+            .ok_or_else(|| InstanceUnloadedSnafu.build())
+    }
+
+    pub fn instance_id(&self) -> usize {
+        self.instance_id
+    }
 }
 
 pub trait AsContextMut: AsContext {
@@ -98,16 +132,72 @@ impl<T: AsContext> AsContext for &mut T {
     }
 }
 
+/// The memory compiled code takes up in a store, see [`Store::code_memory`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CodeMemory {
+    /// The bytes allocated for compiled code.
+    pub allocated: usize,
+    /// The bytes of the allocated memory that are in use.
+    pub used: usize,
+}
+
+/// The userdata of a store's runtime, through which host functions reach the store's data.
+///
+/// The header comes first so that it can be found without knowing `T`.
+#[repr(C)]
+struct RuntimeData<T> {
+    header: RuntimeHeader,
+    data: T,
+}
+
+#[repr(C)]
+struct RuntimeHeader {
+    // first, so that `HostError` finds it at the start of the userdata
+    host_error: HostErrorSlot,
+    // the modules loaded into the runtime along with the bytes they point into, by the id of their
+    // instance, which is unique among all instances so that the handles of an unloaded instance
+    // stay invalid even when wasm3 reuses its memory
+    modules: BTreeMap<usize, LoadedModule>,
+}
+
+/// Hands out the ids of instances, which are never reused.
+static NEXT_INSTANCE_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns the modules loaded into the store `raw` belongs to.
+///
+/// # Safety
+///
+/// `raw` must be the runtime of a [`Store`], which must not load or unload modules while the
+/// returned slice is alive.
+unsafe fn loaded_modules<'a>(raw: NonNull<ffi::M3Runtime>) -> &'a BTreeMap<usize, LoadedModule> {
+    let header = unsafe { ffi::m3_GetUserData(raw.as_ptr()) } as *const RuntimeHeader;
+    unsafe { &(*header).modules }
+}
+
+/// Returns a pointer to the data of the store `raw` belongs to.
+///
+/// # Safety
+///
+/// `raw` must be the runtime of a [`Store<T>`].
+pub(crate) unsafe fn data_ptr<T>(raw: NonNull<ffi::M3Runtime>) -> *mut T {
+    let user_data = unsafe { ffi::m3_GetUserData(raw.as_ptr()) } as *mut RuntimeData<T>;
+    unsafe { ptr::addr_of_mut!((*user_data).data) }
+}
+
 /// A runtime context for wasm3 modules.
 #[derive(Debug)]
 pub struct Store<T: 'static> {
     raw: NonNull<ffi::M3Runtime>,
-    data: Rc<RefCell<T>>,
+    // installed as the runtime's userdata, which only holds a pointer to it; a raw pointer rather
+    // than a box as host functions borrow the data through the runtime while a call borrows `self`
+    user_data: NonNull<RuntimeData<T>>,
     environment: Environment,
-    // holds all linked closures so that they properly get disposed of when runtime drops
-    closures: Vec<PinnedAnyClosure>,
-    // holds all backing data of loaded modules as they have to be kept alive for the module's lifetime
-    pub(crate) modules: Vec<RawModule>,
+    max_memory_pages: Option<u32>,
+    // installed into the runtime, which only holds a pointer to it
+    interrupt: InterruptHandle,
+    // holds all linked closures so that they properly get disposed of when runtime drops or
+    // their instance is unloaded, along with the id of that instance
+    closures: Vec<(usize, AnyClosure)>,
 }
 
 impl<T> Store<T> {
@@ -117,47 +207,121 @@ impl<T> Store<T> {
     ///
     /// This function will error on memory allocation failure.
     pub fn new(environment: &Environment, stack_size: u32, data: T) -> Result<Self> {
-        unsafe {
+        Self::with_memory_limit(environment, stack_size, None, data)
+    }
+
+    /// Creates a new runtime with the given stack size in slots, whose memory may never grow
+    /// beyond `max_memory_pages` pages of 64KiB.
+    ///
+    /// Once the limit is reached, `memory.grow` fails by returning -1 to the module.
+    ///
+    /// # Errors
+    ///
+    /// This function will error on memory allocation failure.
+    pub fn with_memory_limit(
+        environment: &Environment,
+        stack_size: u32,
+        max_memory_pages: impl Into<Option<u32>>,
+        data: T,
+    ) -> Result<Self> {
+        let user_data = Box::new(RuntimeData {
+            header: RuntimeHeader {
+                host_error: HostErrorSlot::new(None),
+                modules: BTreeMap::new(),
+            },
+            data,
+        });
+        let user_data = NonNull::from(Box::leak(user_data));
+        let raw = unsafe {
             NonNull::new(ffi::m3_NewRuntime(
                 environment.as_ptr(),
                 stack_size,
-                ptr::null_mut(),
+                user_data.as_ptr() as *mut c_void,
             ))
-        }
-        .ok_or_else(Error::malloc_error)
-        .map(|raw| Store {
+        };
+        let Some(raw) = raw else {
+            drop(unsafe { Box::from_raw(user_data.as_ptr()) });
+            return Err(Error::malloc_error());
+        };
+        let interrupt = InterruptHandle::new();
+        unsafe { ffi::m3_SetInterrupt(raw.as_ptr(), interrupt.as_ptr()) };
+        Ok(Store {
             raw,
-            data: Rc::new(RefCell::new(data)),
+            user_data,
             environment: environment.clone(),
+            max_memory_pages: max_memory_pages.into(),
+            interrupt,
             closures: Vec::new(),
-            modules: Vec::new(),
         })
     }
 
     /// Loads a parsed module, returning its instance if successful.
     ///
+    /// A module can be instantiated again by cloning it first.
+    ///
     /// # Errors
     ///
-    /// This function will error if the module's environment differs from the one this runtime uses.
+    /// This function will error if the module's environment differs from the one this runtime uses,
+    /// or if its initial memory is larger than the memory limit of this runtime.
     pub fn instantiate(&mut self, module: Module) -> Result<Instance<T>> {
         if &self.environment != module.environment() {
             ModuleLoadEnvMismatchSnafu.fail()
         } else {
-            let raw_mod = module.into_raw();
+            let raw_mod = module.into_raw()?;
+            if let Some(limit) = self.max_memory_pages {
+                let init_pages =
+                    unsafe { ffi::m3_GetModuleMemoryInitPages(raw_mod.inner.as_ptr()) };
+                ensure!(init_pages <= limit, MemoryLimitExceededSnafu);
+            }
             unsafe {
                 Error::from_ffi(ffi::m3_LoadModule(
                     self.raw.as_ptr(),
                     raw_mod.inner.as_ptr(),
                 ))?
             };
+            if let Some(limit) = self.max_memory_pages {
+                unsafe {
+                    let max_pages = ffi::m3_GetMemoryMaxPages(self.as_ptr());
+                    ffi::m3_SetMemoryMaxPages(self.as_ptr(), max_pages.min(limit));
+                }
+            }
 
-            let instance = unsafe { Instance::from_raw(&self.as_context(), raw_mod.inner) };
-
-            self.modules.push(raw_mod);
-            Ok(instance)
+            let inner = raw_mod.inner;
+            let id = NEXT_INSTANCE_ID.fetch_add(1, Ordering::Relaxed);
+            self.header_mut().modules.insert(id, raw_mod.into_loaded());
+            Ok(unsafe { Instance::from_raw(&self.as_context(), inner) })
         }
     }
 
+    /// Unloads an instance, freeing its module and the closures linked to it.
+    ///
+    /// The memory and the compiled code are shared by all instances of a store and stay as they
+    /// are, unless this was the last instance. Then they are freed as well, so that instantiating
+    /// a module afterwards starts from scratch.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the instance belongs to another store or has already been
+    /// unloaded, or with [`Error::InstanceInUse`] if another instance has been
+    /// [linked](Instance::link_instance) to it.
+    pub fn unload(&mut self, instance: Instance<T>) -> Result<()> {
+        let stored = instance.stored();
+        let raw = stored.get(&self.as_context())?;
+        let id = stored.instance_id();
+        ensure!(
+            !self
+                .modules()
+                .iter()
+                .any(|(&loaded_id, loaded)| loaded_id != id && loaded.linked_to.contains(&id)),
+            InstanceInUseSnafu
+        );
+        unsafe { Error::from_ffi(ffi::m3_UnloadModule(self.as_ptr(), raw.as_ptr()))? };
+
+        self.header_mut().modules.remove(&id);
+        self.closures.retain(|(instance_id, _)| *instance_id != id);
+        Ok(())
+    }
+
     /// Looks up a function by the given name in the loaded modules of this runtime.
     /// See [`Module::find_function`] for possible error cases.
     ///
@@ -165,19 +329,16 @@ impl<T> Store<T> {
     pub fn find_function<ARGS, RET>(&self, name: &str) -> Result<Function<ARGS, RET>>
     where
         ARGS: crate::WasmArgs,
-        RET: crate::WasmType,
+        RET: crate::WasmArgs,
     {
-        let mut func_raw: ffi::IM3Function = core::ptr::null_mut();
-        let func_name_cstr = CString::new(name)?;
-        unsafe {
-            Error::from_ffi(ffi::m3_FindFunction(
-                &mut func_raw as *mut ffi::IM3Function,
-                self.as_ptr(),
-                func_name_cstr.as_ptr(),
-            ))?;
-        }
-        let func = NonNull::new(func_raw).ok_or(Error::FunctionNotFound)?;
-        unsafe { Function::from_raw(&self.as_context(), func) }
+        self.as_context().find_function(name)
+    }
+
+    /// Looks up a function by the given name in the loaded modules of this runtime,
+    /// without checking its signature against static types.
+    /// The signature can be inspected through [`DynFunction::ty`] instead.
+    pub fn find_function_dynamic(&self, name: &str) -> Result<DynFunction> {
+        self.as_context().find_function_dynamic(name)
     }
 
     /// Returns the raw memory of this runtime.
@@ -192,82 +353,128 @@ impl<T> Store<T> {
         unsafe { slice::from_raw_parts_mut(data, len as usize) }
     }
 
-    /// Returns a reference to the data associated with this context.
-    pub fn data(&self) -> Ref<'_, T> {
-        self.data.borrow()
+    /// Returns a bounds-checked view into the memory of this runtime.
+    pub fn memory_view(&mut self) -> Memory<'_> {
+        Memory::new(self.memory_mut())
     }
 
-    /// Returns a mutable reference to the data associated with this context.
-    pub fn data_mut(&mut self) -> RefMut<'_, T> {
-        self.data.borrow_mut()
+    /// Returns the size of the memory of this runtime in pages of 64KiB.
+    pub fn memory_pages(&self) -> u32 {
+        self.as_context().memory_pages()
+    }
+
+    /// Returns the number of pages the memory of this runtime may grow to,
+    /// taking both the loaded module and the limit of this runtime into account.
+    pub fn max_memory_pages(&self) -> u32 {
+        self.as_context().max_memory_pages()
+    }
+
+    /// Grows the memory of this runtime by `pages` pages of 64KiB, returning its previous size.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the memory would exceed its maximum size or on memory
+    /// allocation failure.
+    pub fn grow(&mut self, pages: u32) -> Result<u32> {
+        self.as_context_mut().grow(pages)
+    }
+
+    /// Returns the memory the compiled functions of this runtime take up.
+    ///
+    /// Functions are compiled on their first call, or ahead of time through
+    /// [`Instance::compile_all`].
+    pub fn code_memory(&self) -> CodeMemory {
+        let mut memory = CodeMemory::default();
+        unsafe { ffi::m3_GetCodeMemory(self.as_ptr(), &mut memory.allocated, &mut memory.used) };
+        memory
     }
 
-    /// Returns a description of the last error that occurred in this runtime.
-    pub fn take_error_info(&mut self) -> Option<String> {
+    /// Returns a handle that interrupts the calls running in this runtime.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt.clone()
+    }
+
+    /// Returns a reference to the data associated with this runtime.
+    pub fn data(&self) -> &T {
+        unsafe { &(*self.user_data.as_ptr()).data }
+    }
+
+    /// Returns a mutable reference to the data associated with this runtime.
+    pub fn data_mut(&mut self) -> &mut T {
+        unsafe { &mut (*self.user_data.as_ptr()).data }
+    }
+
+    /// Returns details about the last error that occurred in this runtime, clearing them.
+    pub fn take_error_info(&mut self) -> Option<ErrorInfo> {
         let mut info = unsafe { mem::zeroed() };
-        unsafe { ffi::m3_GetErrorInfo(self.as_ptr(), &mut info) };
-        if info.message.is_null() {
-            None
-        } else {
-            let message = unsafe { CStr::from_ptr(info.message).to_string_lossy().to_string() };
-            if message.is_empty() {
-                None
-            } else {
-                Some(message)
-            }
+        unsafe {
+            ffi::m3_GetErrorInfo(self.as_ptr(), &mut info);
+            ErrorInfo::from_raw(&info)
         }
     }
 }
 
 impl<T> Store<T> {
-    pub(crate) fn push_closure(&mut self, closure: PinnedAnyClosure) {
-        self.closures.push(closure);
+    pub(crate) fn push_closure(&mut self, instance_id: usize, closure: AnyClosure) {
+        self.closures.push((instance_id, closure));
     }
 
-    pub(crate) fn as_ptr(&self) -> ffi::IM3Runtime {
-        self.raw.as_ptr()
+    pub(crate) fn modules(&self) -> &BTreeMap<usize, LoadedModule> {
+        unsafe { &(*self.user_data.as_ptr()).header.modules }
+    }
+
+    pub(crate) fn add_instance_link(&mut self, instance_id: usize, linked_id: usize) {
+        if let Some(loaded) = self.header_mut().modules.get_mut(&instance_id) {
+            loaded.linked_to.push(linked_id);
+        }
     }
 
-    pub(crate) fn data_ref(&self) -> Rc<RefCell<T>> {
-        self.data.clone()
+    fn header_mut(&mut self) -> &mut RuntimeHeader {
+        unsafe { &mut (*self.user_data.as_ptr()).header }
+    }
+
+    pub(crate) fn as_ptr(&self) -> ffi::IM3Runtime {
+        self.raw.as_ptr()
     }
 }
 
 impl<T> Drop for Store<T> {
     fn drop(&mut self) {
-        for mut module in mem::take(&mut self.modules) {
-            module.data = Cow::Borrowed(&[]);
-            mem::forget(module);
+        // frees the loaded modules, before their bytes are dropped along with the userdata
+        unsafe {
+            ffi::m3_FreeRuntime(self.raw.as_ptr());
+            drop(Box::from_raw(self.user_data.as_ptr()));
         }
-        unsafe { ffi::m3_FreeRuntime(self.raw.as_ptr()) };
     }
 }
 
 impl<T> AsContext for Store<T> {
     type Data = T;
     fn as_context(&self) -> StoreContext<'_, T> {
-        StoreContext::new(self.raw, self.data.clone())
+        unsafe { StoreContext::from_raw(self.raw) }
     }
 }
 
 impl<T> AsContextMut for Store<T> {
     fn as_context_mut(&mut self) -> StoreContextMut<'_, T> {
-        StoreContextMut::new(self.raw, self.data.clone())
+        unsafe { StoreContextMut::from_raw(self.raw) }
     }
 }
 
 #[derive(Debug)]
 pub struct StoreContextMut<'a, T> {
     raw: NonNull<ffi::M3Runtime>,
-    data: Rc<RefCell<T>>,
-    scope: PhantomData<&'a mut ()>,
+    scope: PhantomData<&'a mut T>,
 }
 
 impl<T> StoreContextMut<'_, T> {
-    pub(crate) fn new(raw: NonNull<ffi::M3Runtime>, data: Rc<RefCell<T>>) -> Self {
+    /// # Safety
+    ///
+    /// `raw` must be the runtime of a [`Store<T>`] that is mutably borrowed for the lifetime of the
+    /// returned context.
+    pub(crate) unsafe fn from_raw(raw: NonNull<ffi::M3Runtime>) -> Self {
         Self {
             raw,
-            data,
             scope: PhantomData,
         }
     }
@@ -279,11 +486,17 @@ impl<T> StoreContextMut<'_, T> {
     pub fn find_function<ARGS, RET>(&self, name: &str) -> Result<Function<ARGS, RET>>
     where
         ARGS: crate::WasmArgs,
-        RET: crate::WasmType,
+        RET: crate::WasmArgs,
     {
         self.as_context().find_function(name)
     }
 
+    /// Looks up a function by the given name in the loaded modules of this runtime,
+    /// without checking its signature against static types.
+    pub fn find_function_dynamic(&self, name: &str) -> Result<DynFunction> {
+        self.as_context().find_function_dynamic(name)
+    }
+
     /// Returns the raw memory of this runtime.
     pub fn memory(&self) -> &[u8] {
         self.as_context().memory()
@@ -296,14 +509,41 @@ impl<T> StoreContextMut<'_, T> {
         unsafe { slice::from_raw_parts_mut(data, len as usize) }
     }
 
+    /// Returns a bounds-checked view into the memory of this runtime.
+    pub fn memory_view(&mut self) -> Memory<'_> {
+        Memory::new(self.memory_mut())
+    }
+
+    /// Returns the size of the memory of this runtime in pages of 64KiB.
+    pub fn memory_pages(&self) -> u32 {
+        self.as_context().memory_pages()
+    }
+
+    /// Returns the number of pages the memory of this runtime may grow to.
+    pub fn max_memory_pages(&self) -> u32 {
+        self.as_context().max_memory_pages()
+    }
+
+    /// Grows the memory of this runtime by `pages` pages of 64KiB, returning its previous size.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the memory would exceed its maximum size or on memory
+    /// allocation failure.
+    pub fn grow(&mut self, pages: u32) -> Result<u32> {
+        let previous = self.memory_pages();
+        unsafe { Error::from_ffi(ffi::m3_GrowMemory(self.as_ptr(), pages))? };
+        Ok(previous)
+    }
+
     /// Returns a reference to the data associated with this context.
-    pub fn data(&self) -> Ref<'_, T> {
-        self.data.borrow()
+    pub fn data(&self) -> &T {
+        unsafe { &*data_ptr(self.raw) }
     }
 
     /// Returns a mutable reference to the data associated with this context.
-    pub fn data_mut(&mut self) -> RefMut<'_, T> {
-        self.data.borrow_mut()
+    pub fn data_mut(&mut self) -> &mut T {
+        unsafe { &mut *data_ptr(self.raw) }
     }
 
     pub(crate) fn as_ptr(&self) -> ffi::IM3Runtime {
@@ -314,27 +554,29 @@ impl<T> StoreContextMut<'_, T> {
 impl<T> AsContext for StoreContextMut<'_, T> {
     type Data = T;
     fn as_context(&self) -> StoreContext<'_, T> {
-        StoreContext::new(self.raw, self.data.clone())
+        unsafe { StoreContext::from_raw(self.raw) }
     }
 }
 
 impl<T> AsContextMut for StoreContextMut<'_, T> {
     fn as_context_mut(&mut self) -> StoreContextMut<'_, T> {
-        Self::new(self.raw, self.data.clone())
+        unsafe { Self::from_raw(self.raw) }
     }
 }
 
 pub struct StoreContext<'a, T> {
     raw: NonNull<ffi::M3Runtime>,
-    data: Rc<RefCell<T>>,
-    scope: PhantomData<&'a ()>,
+    scope: PhantomData<&'a T>,
 }
 
 impl<'a, T> StoreContext<'a, T> {
-    pub(crate) fn new(raw: NonNull<ffi::M3Runtime>, data: Rc<RefCell<T>>) -> Self {
+    /// # Safety
+    ///
+    /// `raw` must be the runtime of a [`Store<T>`] that is borrowed for the lifetime of the
+    /// returned context.
+    pub(crate) unsafe fn from_raw(raw: NonNull<ffi::M3Runtime>) -> Self {
         Self {
             raw,
-            data,
             scope: PhantomData,
         }
     }
@@ -346,19 +588,32 @@ impl<'a, T> StoreContext<'a, T> {
     pub fn find_function<ARGS, RET>(&self, name: &str) -> Result<Function<ARGS, RET>>
     where
         ARGS: crate::WasmArgs,
-        RET: crate::WasmType,
+        RET: crate::WasmArgs,
     {
+        let func = self.find_raw_function(name)?;
+        unsafe { Function::from_raw(self, func) }
+    }
+
+    /// Looks up a function by the given name in the loaded modules of this runtime,
+    /// without checking its signature against static types.
+    pub fn find_function_dynamic(&self, name: &str) -> Result<DynFunction> {
+        let func = self.find_raw_function(name)?;
+        unsafe { DynFunction::from_raw(self, func) }
+    }
+
+    fn find_raw_function(&self, name: &str) -> Result<NonNull<ffi::M3Function>> {
         let mut func_raw: ffi::IM3Function = core::ptr::null_mut();
         let func_name_cstr = CString::new(name)?;
         unsafe {
-            Error::from_ffi(ffi::m3_FindFunction(
+            // the start function of the module is left to the first call, as it may call the host,
+            // which can't be given the store's data through a shared borrow
+            Error::from_ffi(ffi::m3_FindFunctionWithoutStart(
                 &mut func_raw as *mut ffi::IM3Function,
                 self.as_ptr(),
                 func_name_cstr.as_ptr(),
             ))?;
         }
-        let func = NonNull::new(func_raw).ok_or(Error::FunctionNotFound)?;
-        unsafe { Function::from_raw(self, func) }
+        NonNull::new(func_raw).ok_or(Error::FunctionNotFound)
     }
 
     /// Returns the raw memory of this runtime.
@@ -368,9 +623,19 @@ impl<'a, T> StoreContext<'a, T> {
         unsafe { slice::from_raw_parts(data, len as usize) }
     }
 
+    /// Returns the size of the memory of this runtime in pages of 64KiB.
+    pub fn memory_pages(&self) -> u32 {
+        unsafe { ffi::m3_GetMemoryPages(self.as_ptr()) }
+    }
+
+    /// Returns the number of pages the memory of this runtime may grow to.
+    pub fn max_memory_pages(&self) -> u32 {
+        unsafe { ffi::m3_GetMemoryMaxPages(self.as_ptr()) }
+    }
+
     /// Returns a reference to the data associated with this context.
-    pub fn data(&self) -> Ref<'_, T> {
-        self.data.borrow()
+    pub fn data(&self) -> &'a T {
+        unsafe { &*data_ptr(self.raw) }
     }
 
     pub(crate) fn as_ptr(&self) -> ffi::IM3Runtime {
@@ -380,12 +645,16 @@ impl<'a, T> StoreContext<'a, T> {
     pub(crate) fn id(&self) -> usize {
         self.raw.as_ptr() as usize
     }
+
+    pub(crate) fn modules(&self) -> &'a BTreeMap<usize, LoadedModule> {
+        unsafe { loaded_modules(self.raw) }
+    }
 }
 
 impl<T> AsContext for StoreContext<'_, T> {
     type Data = T;
     fn as_context(&self) -> StoreContext<'_, T> {
-        Self::new(self.raw, self.data.clone())
+        unsafe { Self::from_raw(self.raw) }
     }
 }
 
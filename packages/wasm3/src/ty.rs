@@ -1,6 +1,21 @@
 #![allow(missing_docs, clippy::missing_safety_doc)]
 use alloc::vec::Vec;
-use core::ffi::c_char;
+use core::{
+    ffi::c_char,
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    mem,
+    ops::{Deref, DerefMut},
+    ptr::NonNull,
+};
+
+use ffi::M3Function;
+
+use crate::{
+    error::TrappedResult,
+    memory::{self, Memory, Pod},
+};
 
 trait Sealed {}
 
@@ -12,14 +27,39 @@ pub trait WasmType: Sized + Sealed {
     const SIGNATURE: u8;
     unsafe fn pop_from_stack(stack: *mut u64) -> Self;
     unsafe fn push_on_stack(self, stack: *mut u64);
+    /// Checks the types of the values this type is passed as, taking one from `types` per slot.
+    fn validate_value_types(types: &mut impl Iterator<Item = ffi::M3ValueType::Type>) -> bool {
+        (0..Self::SIZE_IN_SLOT_COUNT).all(|_| types.next() == Some(Self::TYPE_INDEX))
+    }
+    fn append_value_signature(buffer: &mut Vec<c_char>) {
+        buffer.extend((0..Self::SIZE_IN_SLOT_COUNT).map(|_| Self::SIGNATURE as c_char));
+    }
 }
 
 /// Trait implemented by types that can be passed to wasm.
 pub trait WasmArg: WasmType {}
 
+/// Trait implemented by the types a [`Global`](crate::Global) can hold, which are the scalars
+/// that fit in a single value.
+///
+/// Types spanning several values, such as [`WasmSlice`], can't be read from a global:
+///
+/// ```compile_fail
+/// # use wasm3::{Instance, Store, WasmSlice};
+/// fn slice_global(store: &Store<()>, instance: &Instance<()>) {
+///     let _ = instance.find_global::<WasmSlice<u8>>(store, "slice");
+/// }
+/// ```
+pub trait GlobalType: WasmType {}
+
 /// Helper trait implemented by tuples to emulate "variadic generics".
+///
+/// This describes both parameter lists and result lists, so a tuple like `(i32, f64)` can be used
+/// as the return type of a function that produces multiple values.
 #[allow(private_bounds)]
 pub trait WasmArgs: Sealed {
+    /// The number of stack slots taken up by all values of this list combined.
+    const SLOT_COUNT: usize;
     unsafe fn push_on_stack(self, stack: *mut u64);
     // required for closure linking
     unsafe fn pop_from_stack(stack: *mut u64) -> Self;
@@ -29,6 +69,7 @@ pub trait WasmArgs: Sealed {
 
 impl Sealed for i32 {}
 impl WasmArg for i32 {}
+impl GlobalType for i32 {}
 impl WasmType for i32 {
     const TYPE_INDEX: ffi::M3ValueType::Type = ffi::M3ValueType::c_m3Type_i32;
     const SIZE_IN_SLOT_COUNT: usize = 1;
@@ -43,6 +84,7 @@ impl WasmType for i32 {
 
 impl Sealed for u32 {}
 impl WasmArg for u32 {}
+impl GlobalType for u32 {}
 impl WasmType for u32 {
     const TYPE_INDEX: ffi::M3ValueType::Type = ffi::M3ValueType::c_m3Type_i32;
     const SIZE_IN_SLOT_COUNT: usize = 1;
@@ -71,6 +113,7 @@ impl WasmType for bool {
 
 impl Sealed for i64 {}
 impl WasmArg for i64 {}
+impl GlobalType for i64 {}
 impl WasmType for i64 {
     const TYPE_INDEX: ffi::M3ValueType::Type = ffi::M3ValueType::c_m3Type_i64;
     const SIZE_IN_SLOT_COUNT: usize = 1;
@@ -85,6 +128,7 @@ impl WasmType for i64 {
 
 impl Sealed for u64 {}
 impl WasmArg for u64 {}
+impl GlobalType for u64 {}
 impl WasmType for u64 {
     const TYPE_INDEX: ffi::M3ValueType::Type = ffi::M3ValueType::c_m3Type_i64;
     const SIZE_IN_SLOT_COUNT: usize = 1;
@@ -99,6 +143,7 @@ impl WasmType for u64 {
 
 impl Sealed for f32 {}
 impl WasmArg for f32 {}
+impl GlobalType for f32 {}
 impl WasmType for f32 {
     const TYPE_INDEX: ffi::M3ValueType::Type = ffi::M3ValueType::c_m3Type_f32;
     const SIZE_IN_SLOT_COUNT: usize = 1;
@@ -113,6 +158,7 @@ impl WasmType for f32 {
 
 impl Sealed for f64 {}
 impl WasmArg for f64 {}
+impl GlobalType for f64 {}
 impl WasmType for f64 {
     const TYPE_INDEX: ffi::M3ValueType::Type = ffi::M3ValueType::c_m3Type_f64;
     const SIZE_IN_SLOT_COUNT: usize = 1;
@@ -135,6 +181,7 @@ impl WasmType for () {
 }
 
 impl WasmArgs for () {
+    const SLOT_COUNT: usize = 0;
     unsafe fn push_on_stack(self, _: *mut u64) {}
     unsafe fn pop_from_stack(_: *mut u64) -> Self {}
     fn validate_types(mut types: impl Iterator<Item = ffi::M3ValueType::Type>) -> bool {
@@ -148,6 +195,7 @@ impl<T> WasmArgs for T
 where
     T: WasmArg,
 {
+    const SLOT_COUNT: usize = <T as WasmType>::SIZE_IN_SLOT_COUNT;
     unsafe fn push_on_stack(self, stack: *mut u64) {
         WasmType::push_on_stack(self, stack);
     }
@@ -155,10 +203,10 @@ where
         WasmType::pop_from_stack(stack)
     }
     fn validate_types(mut types: impl Iterator<Item = ffi::M3ValueType::Type>) -> bool {
-        types.next().map(|ty| ty == T::TYPE_INDEX).unwrap_or(false) && types.next().is_none()
+        T::validate_value_types(&mut types) && types.next().is_none()
     }
     fn append_signature(buffer: &mut Vec<c_char>) {
-        buffer.push(T::SIGNATURE as c_char);
+        T::append_value_signature(buffer);
     }
 }
 
@@ -176,6 +224,7 @@ macro_rules! args_impl {
         #[allow(unused_assignments)]
         impl<$($types,)*> WasmArgs for ($($types,)*)
         where $($types: WasmArg,)* {
+            const SLOT_COUNT: usize = 0 $(+ <$types as WasmType>::SIZE_IN_SLOT_COUNT)*;
                     unsafe fn push_on_stack(self, mut stack: *mut u64) {
                 #[allow(non_snake_case)]
                 let ($($types,)*) = self;
@@ -196,13 +245,13 @@ macro_rules! args_impl {
             }
                     fn validate_types(mut types: impl Iterator<Item=ffi::M3ValueType::Type>) -> bool {
                 $(
-                    types.next().map(|ty| ty == $types::TYPE_INDEX).unwrap_or(false) &&
+                    $types::validate_value_types(&mut types) &&
                 )*
                 types.next().is_none()
             }
                     fn append_signature(buffer: &mut Vec<c_char>) {
                 $(
-                    buffer.push($types::SIGNATURE as c_char);
+                    $types::append_value_signature(buffer);
                 )*
             }
         }
@@ -210,6 +259,456 @@ macro_rules! args_impl {
 }
 args_impl!(A, B, C, D, E, F, G, H, J, K, L, M, N, O, P, Q);
 
+/// A pointer into wasm memory, passed to and from wasm as an `i32`.
+///
+/// The pointee is only accessed through a bounds-checked [`Memory`] view, so an invalid pointer
+/// results in a [`Trap`] rather than a panic.
+#[repr(transparent)]
+pub struct WasmPtr<T> {
+    offset: u32,
+    _pd: PhantomData<fn() -> T>,
+}
+
+impl<T> WasmPtr<T> {
+    /// Creates a pointer to the given address in wasm memory.
+    pub fn new(offset: u32) -> Self {
+        Self {
+            offset,
+            _pd: PhantomData,
+        }
+    }
+
+    /// The address this pointer points to.
+    pub fn offset(self) -> u32 {
+        self.offset
+    }
+
+    /// Whether this is a null pointer.
+    pub fn is_null(self) -> bool {
+        self.offset == 0
+    }
+
+    /// Reinterprets this pointer as pointing to a different type.
+    pub fn cast<U>(self) -> WasmPtr<U> {
+        WasmPtr::new(self.offset)
+    }
+}
+
+impl<T: Pod> WasmPtr<T> {
+    /// Reads the value this pointer points to.
+    pub fn deref(self, memory: &Memory<'_>) -> TrappedResult<T> {
+        memory.read(self.offset)
+    }
+
+    /// Gives mutable access to the value this pointer points to.
+    /// Changes are written back to memory when the returned guard is dropped.
+    pub fn deref_mut<'m>(self, memory: &'m mut Memory<'_>) -> TrappedResult<WasmRefMut<'m, T>> {
+        let bytes = memory.range_mut(self.offset, mem::size_of::<T>())?;
+        Ok(WasmRefMut {
+            val: memory::read_value(bytes),
+            bytes,
+        })
+    }
+}
+
+impl<T> Clone for WasmPtr<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T> Copy for WasmPtr<T> {}
+
+impl<T> PartialEq for WasmPtr<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.offset == other.offset
+    }
+}
+impl<T> Eq for WasmPtr<T> {}
+
+impl<T> Hash for WasmPtr<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.offset.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for WasmPtr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "WasmPtr({:#x})", self.offset)
+    }
+}
+
+impl<T> Sealed for WasmPtr<T> {}
+impl<T> WasmArg for WasmPtr<T> {}
+impl<T> WasmType for WasmPtr<T> {
+    const TYPE_INDEX: ffi::M3ValueType::Type = ffi::M3ValueType::c_m3Type_i32;
+    const SIZE_IN_SLOT_COUNT: usize = 1;
+    const SIGNATURE: u8 = b'i';
+    unsafe fn pop_from_stack(stack: *mut u64) -> Self {
+        Self::new(<u32 as WasmType>::pop_from_stack(stack))
+    }
+    unsafe fn push_on_stack(self, stack: *mut u64) {
+        WasmType::push_on_stack(self.offset, stack);
+    }
+}
+
+/// A pointer and length pair describing consecutive values in wasm memory,
+/// passed to and from wasm as two `i32`s.
+///
+/// Like [`WasmPtr`], the values are only accessed through a bounds-checked [`Memory`] view.
+#[repr(C)]
+pub struct WasmSlice<T> {
+    ptr: WasmPtr<T>,
+    len: u32,
+}
+
+impl<T> WasmSlice<T> {
+    /// Creates a slice of `len` values starting at the given pointer.
+    pub fn new(ptr: WasmPtr<T>, len: u32) -> Self {
+        Self { ptr, len }
+    }
+
+    /// A pointer to the first value of this slice.
+    pub fn ptr(self) -> WasmPtr<T> {
+        self.ptr
+    }
+
+    /// The number of values in this slice.
+    pub fn len(self) -> u32 {
+        self.len
+    }
+
+    /// Whether this slice contains no values.
+    pub fn is_empty(self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<T: Pod> WasmSlice<T> {
+    /// Copies the values of this slice out of memory.
+    pub fn deref(self, memory: &Memory<'_>) -> TrappedResult<Vec<T>> {
+        memory.read_slice(self.ptr.offset, self.len)
+    }
+
+    /// Gives mutable access to the values of this slice.
+    /// Changes are written back to memory when the returned guard is dropped.
+    pub fn deref_mut<'m>(self, memory: &'m mut Memory<'_>) -> TrappedResult<WasmSliceMut<'m, T>> {
+        let len = Memory::byte_len::<T>(self.len)?;
+        let bytes = memory.range_mut(self.ptr.offset, len)?;
+        Ok(WasmSliceMut {
+            vals: memory::read_values(bytes),
+            bytes,
+        })
+    }
+}
+
+impl WasmSlice<u8> {
+    /// Borrows the bytes of this slice directly, as they need no conversion.
+    pub fn bytes<'m>(self, memory: &'m Memory<'_>) -> TrappedResult<&'m [u8]> {
+        memory.bytes(self.ptr.offset, self.len)
+    }
+
+    /// Mutably borrows the bytes of this slice directly, as they need no conversion.
+    pub fn bytes_mut<'m>(self, memory: &'m mut Memory<'_>) -> TrappedResult<&'m mut [u8]> {
+        memory.bytes_mut(self.ptr.offset, self.len)
+    }
+}
+
+impl<T> Clone for WasmSlice<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T> Copy for WasmSlice<T> {}
+
+impl<T> PartialEq for WasmSlice<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr == other.ptr && self.len == other.len
+    }
+}
+impl<T> Eq for WasmSlice<T> {}
+
+impl<T> Hash for WasmSlice<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.ptr.hash(state);
+        self.len.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for WasmSlice<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "WasmSlice({:#x}, {})", self.ptr.offset, self.len)
+    }
+}
+
+impl<T> Sealed for WasmSlice<T> {}
+impl<T> WasmArg for WasmSlice<T> {}
+impl<T> WasmType for WasmSlice<T> {
+    const TYPE_INDEX: ffi::M3ValueType::Type = ffi::M3ValueType::c_m3Type_i32;
+    const SIZE_IN_SLOT_COUNT: usize = 2;
+    const SIGNATURE: u8 = b'i';
+    unsafe fn pop_from_stack(stack: *mut u64) -> Self {
+        Self::new(
+            <WasmPtr<T> as WasmType>::pop_from_stack(stack),
+            <u32 as WasmType>::pop_from_stack(stack.add(1)),
+        )
+    }
+    unsafe fn push_on_stack(self, stack: *mut u64) {
+        WasmType::push_on_stack(self.ptr, stack);
+        WasmType::push_on_stack(self.len, stack.add(1));
+    }
+}
+
+/// Mutable access to a value in wasm memory, see [`WasmPtr::deref_mut`].
+pub struct WasmRefMut<'m, T: Pod> {
+    val: T,
+    bytes: &'m mut [u8],
+}
+
+impl<T: Pod> Deref for WasmRefMut<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.val
+    }
+}
+
+impl<T: Pod> DerefMut for WasmRefMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.val
+    }
+}
+
+impl<T: Pod> Drop for WasmRefMut<'_, T> {
+    fn drop(&mut self) {
+        memory::write_value(self.bytes, self.val);
+    }
+}
+
+/// Mutable access to consecutive values in wasm memory, see [`WasmSlice::deref_mut`].
+pub struct WasmSliceMut<'m, T: Pod> {
+    vals: Vec<T>,
+    bytes: &'m mut [u8],
+}
+
+impl<T: Pod> Deref for WasmSliceMut<'_, T> {
+    type Target = [T];
+    fn deref(&self) -> &[T] {
+        &self.vals
+    }
+}
+
+impl<T: Pod> DerefMut for WasmSliceMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        &mut self.vals
+    }
+}
+
+impl<T: Pod> Drop for WasmSliceMut<'_, T> {
+    fn drop(&mut self) {
+        memory::write_values(self.bytes, &self.vals);
+    }
+}
+
+/// The type of a wasm value, used where a signature is only known at runtime.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ValueType {
+    /// A 32-bit integer.
+    I32,
+    /// A 64-bit integer.
+    I64,
+    /// A 32-bit float.
+    F32,
+    /// A 64-bit float.
+    F64,
+}
+
+impl ValueType {
+    pub(crate) fn from_raw(raw: ffi::M3ValueType::Type) -> Option<Self> {
+        match raw {
+            ffi::M3ValueType::c_m3Type_i32 => Some(ValueType::I32),
+            ffi::M3ValueType::c_m3Type_i64 => Some(ValueType::I64),
+            ffi::M3ValueType::c_m3Type_f32 => Some(ValueType::F32),
+            ffi::M3ValueType::c_m3Type_f64 => Some(ValueType::F64),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn signature(self) -> u8 {
+        match self {
+            ValueType::I32 => i32::SIGNATURE,
+            ValueType::I64 => i64::SIGNATURE,
+            ValueType::F32 => f32::SIGNATURE,
+            ValueType::F64 => f64::SIGNATURE,
+        }
+    }
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ValueType::I32 => "i32",
+            ValueType::I64 => "i64",
+            ValueType::F32 => "f32",
+            ValueType::F64 => "f64",
+        })
+    }
+}
+
+/// A wasm value whose type is only known at runtime.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Value {
+    /// A 32-bit integer.
+    I32(i32),
+    /// A 64-bit integer.
+    I64(i64),
+    /// A 32-bit float.
+    F32(f32),
+    /// A 64-bit float.
+    F64(f64),
+}
+
+impl Value {
+    /// The zero value of the given type.
+    pub fn zero(ty: ValueType) -> Self {
+        match ty {
+            ValueType::I32 => Value::I32(0),
+            ValueType::I64 => Value::I64(0),
+            ValueType::F32 => Value::F32(0.0),
+            ValueType::F64 => Value::F64(0.0),
+        }
+    }
+
+    /// The type of this value.
+    pub fn ty(&self) -> ValueType {
+        match self {
+            Value::I32(_) => ValueType::I32,
+            Value::I64(_) => ValueType::I64,
+            Value::F32(_) => ValueType::F32,
+            Value::F64(_) => ValueType::F64,
+        }
+    }
+
+    pub(crate) unsafe fn pop_from_stack(ty: ValueType, stack: *mut u64) -> Self {
+        match ty {
+            ValueType::I32 => Value::I32(WasmType::pop_from_stack(stack)),
+            ValueType::I64 => Value::I64(WasmType::pop_from_stack(stack)),
+            ValueType::F32 => Value::F32(WasmType::pop_from_stack(stack)),
+            ValueType::F64 => Value::F64(WasmType::pop_from_stack(stack)),
+        }
+    }
+
+    pub(crate) unsafe fn push_on_stack(self, stack: *mut u64) {
+        match self {
+            Value::I32(val) => WasmType::push_on_stack(val, stack),
+            Value::I64(val) => WasmType::push_on_stack(val, stack),
+            Value::F32(val) => WasmType::push_on_stack(val, stack),
+            Value::F64(val) => WasmType::push_on_stack(val, stack),
+        }
+    }
+}
+
+impl From<i32> for Value {
+    fn from(val: i32) -> Self {
+        Value::I32(val)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(val: i64) -> Self {
+        Value::I64(val)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(val: f32) -> Self {
+        Value::F32(val)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(val: f64) -> Self {
+        Value::F64(val)
+    }
+}
+
+/// The signature of a wasm function, used where it is only known at runtime.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FuncType {
+    params: Vec<ValueType>,
+    results: Vec<ValueType>,
+}
+
+impl FuncType {
+    /// Creates a function type from its parameter and result types.
+    pub fn new(
+        params: impl IntoIterator<Item = ValueType>,
+        results: impl IntoIterator<Item = ValueType>,
+    ) -> Self {
+        Self {
+            params: params.into_iter().collect(),
+            results: results.into_iter().collect(),
+        }
+    }
+
+    /// The parameter types of this function.
+    pub fn params(&self) -> &[ValueType] {
+        &self.params
+    }
+
+    /// The result types of this function.
+    pub fn results(&self) -> &[ValueType] {
+        &self.results
+    }
+
+    /// Reads the signature of a wasm3 function.
+    /// Returns `None` if it uses a type that has no [`ValueType`].
+    pub(crate) fn from_raw(raw: NonNull<M3Function>) -> Option<Self> {
+        let num_args = unsafe { ffi::m3_GetArgCount(raw.as_ptr()) };
+        let num_rets = unsafe { ffi::m3_GetRetCount(raw.as_ptr()) };
+        Some(Self {
+            params: (0..num_args)
+                .map(|i| ValueType::from_raw(unsafe { ffi::m3_GetArgType(raw.as_ptr(), i) }))
+                .collect::<Option<_>>()?,
+            results: (0..num_rets)
+                .map(|i| ValueType::from_raw(unsafe { ffi::m3_GetRetType(raw.as_ptr(), i) }))
+                .collect::<Option<_>>()?,
+        })
+    }
+
+    /// The wasm3 signature string of this function, e.g. `F(ii)`.
+    pub(crate) fn signature(&self) -> Vec<c_char> {
+        let mut signature = <Vec<c_char>>::new();
+        if self.results.is_empty() {
+            signature.push(b'v' as c_char);
+        }
+        signature.extend(self.results.iter().map(|ty| ty.signature() as c_char));
+        signature.push(b'(' as c_char);
+        signature.extend(self.params.iter().map(|ty| ty.signature() as c_char));
+        signature.push(b')' as c_char);
+        signature.push(b'\0' as c_char);
+        signature
+    }
+}
+
+/// Formats the type like `(i32, f64) -> (i64)`.
+impl fmt::Display for FuncType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn list(f: &mut fmt::Formatter<'_>, types: &[ValueType]) -> fmt::Result {
+            f.write_str("(")?;
+            for (i, ty) in types.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "{}", ty)?;
+            }
+            f.write_str(")")
+        }
+
+        list(f, &self.params)?;
+        f.write_str(" -> ")?;
+        list(f, &self.results)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -266,6 +765,40 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_slot_count() {
+        assert_eq!(<()>::SLOT_COUNT, 0);
+        assert_eq!(<i64 as WasmArgs>::SLOT_COUNT, 1);
+        assert_eq!(<(i32, f64)>::SLOT_COUNT, 2);
+    }
+
+    #[test]
+    fn test_func_type_signature() {
+        let ty = FuncType::new([ValueType::I32, ValueType::F32], [ValueType::F64]);
+        let signature: Vec<u8> = ty.signature().into_iter().map(|c| c as u8).collect();
+        assert_eq!(signature, b"F(if)\0");
+
+        let ty = FuncType::new([], []);
+        let signature: Vec<u8> = ty.signature().into_iter().map(|c| c as u8).collect();
+        assert_eq!(signature, b"v()\0");
+    }
+
+    #[test]
+    fn test_value_stack_roundtrip() {
+        let mut slot = 0u64;
+        for val in [
+            Value::I32(-7),
+            Value::I64(1 << 40),
+            Value::F32(1.5),
+            Value::F64(-2.25),
+        ] {
+            unsafe {
+                val.push_on_stack(&mut slot);
+                assert_eq!(Value::pop_from_stack(val.ty(), &mut slot), val);
+            }
+        }
+    }
+
     #[test]
     fn test_validate_types_quintuple_fail() {
         assert!(!<(f64, u32, i32, i64, f32)>::validate_types(
@@ -280,4 +813,46 @@ mod tests {
             .cloned()
         ));
     }
+
+    #[test]
+    fn test_wasm_slice_types() {
+        type Args = (u32, WasmSlice<u8>, WasmPtr<f64>);
+        assert_eq!(Args::SLOT_COUNT, 4);
+        assert!(Args::validate_types(
+            [ffi::M3ValueType::c_m3Type_i32; 4].iter().cloned()
+        ));
+        assert!(!Args::validate_types(
+            [ffi::M3ValueType::c_m3Type_i32; 3].iter().cloned()
+        ));
+
+        let mut signature = Vec::new();
+        Args::append_signature(&mut signature);
+        let signature: Vec<u8> = signature.into_iter().map(|c| c as u8).collect();
+        assert_eq!(signature, b"iiii");
+
+        let mut slots = [0u64; 4];
+        let args: Args = (7, WasmSlice::new(WasmPtr::new(16), 3), WasmPtr::new(32));
+        unsafe {
+            args.push_on_stack(slots.as_mut_ptr());
+            assert_eq!(Args::pop_from_stack(slots.as_mut_ptr()), args);
+        }
+    }
+
+    #[test]
+    fn test_wasm_ptr_deref() {
+        let mut data = [0u8; 16];
+        let mut memory = Memory::new(&mut data);
+        let ptr = WasmPtr::<u32>::new(3);
+        *ptr.deref_mut(&mut memory).unwrap() = 0xdead_beef;
+        assert_eq!(ptr.deref(&memory), Ok(0xdead_beef));
+        assert_eq!(
+            WasmPtr::<u32>::new(13).deref(&memory),
+            Err(crate::error::Trap::OutOfBoundsMemoryAccess)
+        );
+
+        let slice = WasmSlice::new(ptr.cast::<u16>(), 2);
+        slice.deref_mut(&mut memory).unwrap()[1] += 1;
+        assert_eq!(slice.deref(&memory), Ok(alloc::vec![0xbeef, 0xdeae]));
+        assert!(WasmSlice::new(ptr.cast::<u8>(), 14).bytes(&memory).is_err());
+    }
 }
@@ -0,0 +1,1341 @@
+//! A `no_std` implementation of [WASI preview 1], so that guests built for `wasm32-wasip1` run.
+//!
+//! [`add_to_linker`] defines the `wasi_snapshot_preview1` functions on a [`Linker`], which keep
+//! their state in a [`WasiCtx`] inside the store's data. The guest can only reach what the host
+//! puts into the context: its arguments and environment, a [`Clock`], [`Output`]s for stdout and
+//! stderr, and a [`FileSystem`] preopened as `/`. There are no sockets, and `poll_oneoff` only
+//! waits for clocks, through [`Clock::wait`].
+//!
+//! [WASI preview 1]: https://github.com/WebAssembly/WASI/blob/main/legacy/preview1/docs.md
+use alloc::{boxed::Box, collections::BTreeMap, string::String, vec::Vec};
+use core::{
+    convert::{TryFrom, TryInto},
+    fmt, str,
+};
+
+use crate::{
+    error::{self, HostError},
+    memory::Memory,
+    Linker,
+};
+
+mod fs;
+mod types;
+
+pub use self::{
+    fs::{File, FileSystem, MemoryFs},
+    types::{ClockId, DirEntry, Errno, FileStat, FileType, OpenOptions, SeekFrom},
+};
+
+/// The module guests import the functions from.
+pub const MODULE: &str = "wasi_snapshot_preview1";
+
+const FD_PREOPEN: u32 = 3;
+// the guest opens `/foo` relative to the preopened directory named `/`
+const PREOPEN_NAME: &str = "/";
+
+const OFLAGS_CREAT: u32 = 1 << 0;
+const OFLAGS_DIRECTORY: u32 = 1 << 1;
+const OFLAGS_EXCL: u32 = 1 << 2;
+const OFLAGS_TRUNC: u32 = 1 << 3;
+const FDFLAGS_APPEND: u32 = 1 << 0;
+const RIGHTS_FD_READ: u64 = 1 << 1;
+const RIGHTS_FD_WRITE: u64 = 1 << 6;
+// rights aren't enforced, so every descriptor has all of them
+const RIGHTS_ALL: u64 = (1 << 29) - 1;
+const SUBCLOCKFLAGS_ABSTIME: u16 = 1 << 0;
+const EVENTTYPE_CLOCK: u8 = 0;
+// how often `poll_oneoff` reads the clocks without any of them advancing before it gives up
+const POLL_MAX_STALLED: u32 = 1 << 16;
+
+/// A source of time for the guest.
+pub trait Clock {
+    /// Returns the current time of the clock `id` in nanoseconds, or `None` if it isn't available.
+    fn now(&mut self, id: ClockId) -> Option<u64>;
+
+    /// Returns the resolution of the clock `id` in nanoseconds.
+    fn resolution(&mut self, id: ClockId) -> Option<u64> {
+        self.now(id).map(|_| 1)
+    }
+
+    /// Called by `poll_oneoff` between reads of the clocks while the guest waits for the clock
+    /// `id` to reach `deadline`, to sleep or to let the host do other work in the meantime.
+    ///
+    /// It may return before the deadline. By default, it returns right away, so that
+    /// `poll_oneoff` busy-waits.
+    fn wait(&mut self, id: ClockId, deadline: u64) {
+        let _ = (id, deadline);
+    }
+}
+
+/// Where the guest's stdout or stderr go.
+pub trait Output {
+    /// Writes everything in `bytes`.
+    fn write(&mut self, bytes: &[u8]);
+}
+
+impl<F: FnMut(&[u8])> Output for F {
+    fn write(&mut self, bytes: &[u8]) {
+        self(bytes)
+    }
+}
+
+/// The guest exited through `proc_exit`.
+///
+/// The call into the guest fails with an [`Error::Host`] carrying this, see
+/// [`HostError::downcast_ref`].
+///
+/// [`Error::Host`]: crate::error::Error::Host
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Exit(pub u32);
+
+impl fmt::Display for Exit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "exited with code {}", self.0)
+    }
+}
+
+type Random = dyn FnMut(&mut [u8]);
+
+enum Descriptor {
+    File { file: Box<dyn File>, append: bool },
+    Dir { path: String, preopen: bool },
+}
+
+/// Reads nothing and discards writes, like `/dev/null`.
+struct Null;
+
+impl File for Null {
+    fn read(&mut self, _buf: &mut [u8]) -> Result<usize, Errno> {
+        Ok(0)
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Errno> {
+        Ok(buf.len())
+    }
+}
+
+struct OutputFile(Box<dyn Output>);
+
+impl File for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Errno> {
+        self.0.write(buf);
+        Ok(buf.len())
+    }
+}
+
+/// The state of WASI for one store, configured by the host.
+///
+/// Without further configuration, the guest has no arguments or environment, stdin is empty,
+/// stdout and stderr are discarded, clocks and the file system are unavailable, and random numbers
+/// come from a fixed seed.
+pub struct WasiCtx {
+    args: Vec<String>,
+    env: Vec<String>,
+    clock: Option<Box<dyn Clock>>,
+    random: Box<Random>,
+    fs: Option<Box<dyn FileSystem>>,
+    fds: BTreeMap<u32, Descriptor>,
+}
+
+impl WasiCtx {
+    /// Creates a context with nothing configured.
+    pub fn new() -> Self {
+        let mut fds = BTreeMap::new();
+        for fd in 0..3 {
+            fds.insert(
+                fd,
+                Descriptor::File {
+                    file: Box::new(Null),
+                    append: false,
+                },
+            );
+        }
+        WasiCtx {
+            args: Vec::new(),
+            env: Vec::new(),
+            clock: None,
+            random: Box::new(split_mix(0)),
+            fs: None,
+            fds,
+        }
+    }
+
+    /// Appends an argument. The first one is conventionally the name of the program.
+    pub fn arg(&mut self, arg: impl Into<String>) -> &mut Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Appends several arguments.
+    pub fn args<I, S>(&mut self, args: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Sets an environment variable.
+    pub fn env(&mut self, key: &str, value: &str) -> &mut Self {
+        let prefix = [key, "="].concat();
+        self.env.retain(|var| !var.starts_with(&prefix));
+        self.env.push([&prefix, value].concat());
+        self
+    }
+
+    /// Sets what the guest reads from stdin.
+    pub fn stdin(&mut self, stdin: impl File + 'static) -> &mut Self {
+        self.set_stdio(0, Box::new(stdin))
+    }
+
+    /// Sets where the guest's stdout goes.
+    pub fn stdout(&mut self, stdout: impl Output + 'static) -> &mut Self {
+        self.set_stdio(1, Box::new(OutputFile(Box::new(stdout))))
+    }
+
+    /// Sets where the guest's stderr goes.
+    pub fn stderr(&mut self, stderr: impl Output + 'static) -> &mut Self {
+        self.set_stdio(2, Box::new(OutputFile(Box::new(stderr))))
+    }
+
+    /// Sets the clock the guest reads.
+    pub fn clock(&mut self, clock: impl Clock + 'static) -> &mut Self {
+        self.clock = Some(Box::new(clock));
+        self
+    }
+
+    /// Sets the source of `random_get`, which fills the buffer it is passed.
+    ///
+    /// The default generator is seeded with a constant, so it is neither random between runs nor
+    /// suitable for cryptography.
+    pub fn random(&mut self, random: impl FnMut(&mut [u8]) + 'static) -> &mut Self {
+        self.random = Box::new(random);
+        self
+    }
+
+    /// Gives the guest access to `fs` as its root directory `/`.
+    pub fn file_system(&mut self, fs: impl FileSystem + 'static) -> &mut Self {
+        self.fs = Some(Box::new(fs));
+        self.fds.insert(
+            FD_PREOPEN,
+            Descriptor::Dir {
+                path: String::new(),
+                preopen: true,
+            },
+        );
+        self
+    }
+
+    fn set_stdio(&mut self, fd: u32, file: Box<dyn File>) -> &mut Self {
+        self.fds.insert(
+            fd,
+            Descriptor::File {
+                file,
+                append: false,
+            },
+        );
+        self
+    }
+
+    fn descriptor(&mut self, fd: u32) -> Result<&mut Descriptor, Errno> {
+        self.fds.get_mut(&fd).ok_or(Errno::Badf)
+    }
+
+    fn file(&mut self, fd: u32) -> Result<(&mut dyn File, bool), Errno> {
+        match self.descriptor(fd)? {
+            Descriptor::File { file, append } => Ok((&mut **file, *append)),
+            Descriptor::Dir { .. } => Err(Errno::Isdir),
+        }
+    }
+
+    fn insert(&mut self, descriptor: Descriptor) -> Result<u32, Errno> {
+        let fd = (0..=u32::MAX)
+            .find(|fd| !self.fds.contains_key(fd))
+            .ok_or(Errno::Mfile)?;
+        self.fds.insert(fd, descriptor);
+        Ok(fd)
+    }
+
+    /// Reads a path relative to the directory `fd` and resolves it to a path of the file system.
+    fn path(
+        &mut self,
+        memory: &Memory<'_>,
+        fd: u32,
+        path: u32,
+        path_len: u32,
+    ) -> Result<(&mut dyn FileSystem, String), Errno> {
+        let path = str::from_utf8(memory.bytes(path, path_len)?).map_err(|_| Errno::Inval)?;
+        let base = match self.descriptor(fd)? {
+            Descriptor::Dir { path, .. } => path,
+            Descriptor::File { .. } => return Err(Errno::Notdir),
+        };
+        let path = resolve(base, path)?;
+        let fs = self.fs.as_deref_mut().ok_or(Errno::NotCapable)?;
+        Ok((fs, path))
+    }
+
+    fn args_get(&mut self, memory: &mut Memory<'_>, argv: u32, argv_buf: u32) -> Result<(), Errno> {
+        write_strings(memory, &self.args, argv, argv_buf)
+    }
+
+    fn args_sizes_get(
+        &mut self,
+        memory: &mut Memory<'_>,
+        argc: u32,
+        argv_buf_size: u32,
+    ) -> Result<(), Errno> {
+        write_sizes(memory, &self.args, argc, argv_buf_size)
+    }
+
+    fn environ_get(
+        &mut self,
+        memory: &mut Memory<'_>,
+        environ: u32,
+        environ_buf: u32,
+    ) -> Result<(), Errno> {
+        write_strings(memory, &self.env, environ, environ_buf)
+    }
+
+    fn environ_sizes_get(
+        &mut self,
+        memory: &mut Memory<'_>,
+        environc: u32,
+        environ_buf_size: u32,
+    ) -> Result<(), Errno> {
+        write_sizes(memory, &self.env, environc, environ_buf_size)
+    }
+
+    fn clock_res_get(&mut self, memory: &mut Memory<'_>, id: u32, res: u32) -> Result<(), Errno> {
+        let id = ClockId::from_raw(id).ok_or(Errno::Inval)?;
+        let clock = self.clock.as_mut().ok_or(Errno::Notsup)?;
+        memory.write(res, clock.resolution(id).ok_or(Errno::Notsup)?)?;
+        Ok(())
+    }
+
+    fn clock_time_get(
+        &mut self,
+        memory: &mut Memory<'_>,
+        id: u32,
+        _precision: u64,
+        time: u32,
+    ) -> Result<(), Errno> {
+        let id = ClockId::from_raw(id).ok_or(Errno::Inval)?;
+        let clock = self.clock.as_mut().ok_or(Errno::Notsup)?;
+        memory.write(time, clock.now(id).ok_or(Errno::Notsup)?)?;
+        Ok(())
+    }
+
+    fn fd_advise(
+        &mut self,
+        _memory: &mut Memory<'_>,
+        fd: u32,
+        _offset: u64,
+        _len: u64,
+        _advice: u32,
+    ) -> Result<(), Errno> {
+        self.descriptor(fd)?;
+        Ok(())
+    }
+
+    fn fd_allocate(
+        &mut self,
+        _memory: &mut Memory<'_>,
+        fd: u32,
+        _offset: u64,
+        _len: u64,
+    ) -> Result<(), Errno> {
+        self.descriptor(fd)?;
+        Err(Errno::Notsup)
+    }
+
+    fn fd_close(&mut self, _memory: &mut Memory<'_>, fd: u32) -> Result<(), Errno> {
+        self.fds.remove(&fd).ok_or(Errno::Badf)?;
+        Ok(())
+    }
+
+    fn fd_datasync(&mut self, memory: &mut Memory<'_>, fd: u32) -> Result<(), Errno> {
+        self.fd_sync(memory, fd)
+    }
+
+    fn fd_fdstat_get(&mut self, memory: &mut Memory<'_>, fd: u32, buf: u32) -> Result<(), Errno> {
+        let (filetype, flags) = match self.descriptor(fd)? {
+            Descriptor::File { file, append } => (file.stat()?.filetype, *append as u16),
+            Descriptor::Dir { .. } => (FileType::Directory, 0),
+        };
+        let mut fdstat = [0u8; 24];
+        fdstat[0] = filetype as u8;
+        fdstat[2..4].copy_from_slice(&flags.to_le_bytes());
+        fdstat[8..16].copy_from_slice(&RIGHTS_ALL.to_le_bytes());
+        fdstat[16..24].copy_from_slice(&RIGHTS_ALL.to_le_bytes());
+        memory.write(buf, fdstat)?;
+        Ok(())
+    }
+
+    fn fd_fdstat_set_flags(
+        &mut self,
+        _memory: &mut Memory<'_>,
+        fd: u32,
+        flags: u32,
+    ) -> Result<(), Errno> {
+        match self.descriptor(fd)? {
+            _ if flags & !FDFLAGS_APPEND != 0 => Err(Errno::Notsup),
+            Descriptor::File { append, .. } => {
+                *append = flags & FDFLAGS_APPEND != 0;
+                Ok(())
+            }
+            Descriptor::Dir { .. } => Err(Errno::Badf),
+        }
+    }
+
+    fn fd_fdstat_set_rights(
+        &mut self,
+        _memory: &mut Memory<'_>,
+        fd: u32,
+        _rights_base: u64,
+        _rights_inheriting: u64,
+    ) -> Result<(), Errno> {
+        self.descriptor(fd)?;
+        Ok(())
+    }
+
+    fn fd_filestat_get(&mut self, memory: &mut Memory<'_>, fd: u32, buf: u32) -> Result<(), Errno> {
+        let stat = match self.descriptor(fd)? {
+            Descriptor::File { file, .. } => file.stat()?,
+            Descriptor::Dir { path, .. } => {
+                let path = path.clone();
+                self.fs.as_mut().ok_or(Errno::Badf)?.stat(&path)?
+            }
+        };
+        write_filestat(memory, buf, &stat)
+    }
+
+    fn fd_filestat_set_size(
+        &mut self,
+        _memory: &mut Memory<'_>,
+        fd: u32,
+        size: u64,
+    ) -> Result<(), Errno> {
+        self.file(fd)?.0.set_size(size)
+    }
+
+    fn fd_filestat_set_times(
+        &mut self,
+        _memory: &mut Memory<'_>,
+        fd: u32,
+        _atim: u64,
+        _mtim: u64,
+        _fst_flags: u32,
+    ) -> Result<(), Errno> {
+        self.descriptor(fd)?;
+        Err(Errno::Notsup)
+    }
+
+    fn fd_pread(
+        &mut self,
+        memory: &mut Memory<'_>,
+        fd: u32,
+        iovs: u32,
+        iovs_len: u32,
+        offset: u64,
+        nread: u32,
+    ) -> Result<(), Errno> {
+        let (file, _) = self.file(fd)?;
+        let len = at_offset(file, offset, |file| {
+            read_vectored(file, memory, iovs, iovs_len)
+        })?;
+        memory.write(nread, len)?;
+        Ok(())
+    }
+
+    fn fd_prestat_get(&mut self, memory: &mut Memory<'_>, fd: u32, buf: u32) -> Result<(), Errno> {
+        match self.descriptor(fd)? {
+            Descriptor::Dir { preopen: true, .. } => {
+                // the tag of a directory is zero
+                let mut prestat = [0u8; 8];
+                prestat[4..8].copy_from_slice(&(PREOPEN_NAME.len() as u32).to_le_bytes());
+                memory.write(buf, prestat)?;
+                Ok(())
+            }
+            _ => Err(Errno::Badf),
+        }
+    }
+
+    fn fd_prestat_dir_name(
+        &mut self,
+        memory: &mut Memory<'_>,
+        fd: u32,
+        path: u32,
+        path_len: u32,
+    ) -> Result<(), Errno> {
+        match self.descriptor(fd)? {
+            Descriptor::Dir { preopen: true, .. } if path_len as usize >= PREOPEN_NAME.len() => {
+                memory.write_slice(path, PREOPEN_NAME.as_bytes())?;
+                Ok(())
+            }
+            Descriptor::Dir { preopen: true, .. } => Err(Errno::NameTooLong),
+            _ => Err(Errno::Badf),
+        }
+    }
+
+    fn fd_pwrite(
+        &mut self,
+        memory: &mut Memory<'_>,
+        fd: u32,
+        iovs: u32,
+        iovs_len: u32,
+        offset: u64,
+        nwritten: u32,
+    ) -> Result<(), Errno> {
+        let (file, _) = self.file(fd)?;
+        let len = at_offset(file, offset, |file| {
+            write_vectored(file, memory, iovs, iovs_len)
+        })?;
+        memory.write(nwritten, len)?;
+        Ok(())
+    }
+
+    fn fd_read(
+        &mut self,
+        memory: &mut Memory<'_>,
+        fd: u32,
+        iovs: u32,
+        iovs_len: u32,
+        nread: u32,
+    ) -> Result<(), Errno> {
+        let (file, _) = self.file(fd)?;
+        let len = read_vectored(file, memory, iovs, iovs_len)?;
+        memory.write(nread, len)?;
+        Ok(())
+    }
+
+    fn fd_readdir(
+        &mut self,
+        memory: &mut Memory<'_>,
+        fd: u32,
+        buf: u32,
+        buf_len: u32,
+        cookie: u64,
+        bufused: u32,
+    ) -> Result<(), Errno> {
+        let path = match self.descriptor(fd)? {
+            Descriptor::Dir { path, .. } => path.clone(),
+            Descriptor::File { .. } => return Err(Errno::Notdir),
+        };
+        let entries = self.fs.as_mut().ok_or(Errno::Badf)?.read_dir(&path)?;
+
+        // entries past the end of the buffer are cut off, which tells the guest to read again
+        // starting at the cookie of the last entry it got completely
+        let mut dirents = Vec::new();
+        let start = usize::try_from(cookie).unwrap_or(usize::MAX);
+        for (i, entry) in entries.iter().enumerate().skip(start) {
+            if dirents.len() >= buf_len as usize {
+                break;
+            }
+            let mut dirent = [0u8; 24];
+            dirent[0..8].copy_from_slice(&(i as u64 + 1).to_le_bytes());
+            dirent[16..20].copy_from_slice(&(entry.name.len() as u32).to_le_bytes());
+            dirent[20] = entry.filetype as u8;
+            dirents.extend_from_slice(&dirent);
+            dirents.extend_from_slice(entry.name.as_bytes());
+        }
+        dirents.truncate(buf_len as usize);
+        memory.write_slice(buf, &dirents)?;
+        memory.write(bufused, dirents.len() as u32)?;
+        Ok(())
+    }
+
+    fn fd_renumber(&mut self, _memory: &mut Memory<'_>, fd: u32, to: u32) -> Result<(), Errno> {
+        self.descriptor(to)?;
+        let descriptor = self.fds.remove(&fd).ok_or(Errno::Badf)?;
+        self.fds.insert(to, descriptor);
+        Ok(())
+    }
+
+    fn fd_seek(
+        &mut self,
+        memory: &mut Memory<'_>,
+        fd: u32,
+        offset: i64,
+        whence: u32,
+        newoffset: u32,
+    ) -> Result<(), Errno> {
+        let pos = match whence {
+            0 => SeekFrom::Start(u64::try_from(offset).map_err(|_| Errno::Inval)?),
+            1 => SeekFrom::Current(offset),
+            2 => SeekFrom::End(offset),
+            _ => return Err(Errno::Inval),
+        };
+        let pos = self.file(fd)?.0.seek(pos)?;
+        memory.write(newoffset, pos)?;
+        Ok(())
+    }
+
+    fn fd_sync(&mut self, _memory: &mut Memory<'_>, fd: u32) -> Result<(), Errno> {
+        match self.descriptor(fd)? {
+            Descriptor::File { file, .. } => file.sync(),
+            Descriptor::Dir { .. } => Ok(()),
+        }
+    }
+
+    fn fd_tell(&mut self, memory: &mut Memory<'_>, fd: u32, offset: u32) -> Result<(), Errno> {
+        let pos = self.file(fd)?.0.seek(SeekFrom::Current(0))?;
+        memory.write(offset, pos)?;
+        Ok(())
+    }
+
+    fn fd_write(
+        &mut self,
+        memory: &mut Memory<'_>,
+        fd: u32,
+        iovs: u32,
+        iovs_len: u32,
+        nwritten: u32,
+    ) -> Result<(), Errno> {
+        let (file, append) = self.file(fd)?;
+        if append {
+            file.seek(SeekFrom::End(0))?;
+        }
+        let len = write_vectored(file, memory, iovs, iovs_len)?;
+        memory.write(nwritten, len)?;
+        Ok(())
+    }
+
+    fn path_create_directory(
+        &mut self,
+        memory: &mut Memory<'_>,
+        fd: u32,
+        path: u32,
+        path_len: u32,
+    ) -> Result<(), Errno> {
+        let (fs, path) = self.path(memory, fd, path, path_len)?;
+        fs.create_dir(&path)
+    }
+
+    fn path_filestat_get(
+        &mut self,
+        memory: &mut Memory<'_>,
+        fd: u32,
+        _flags: u32,
+        path: u32,
+        path_len: u32,
+        buf: u32,
+    ) -> Result<(), Errno> {
+        let (fs, path) = self.path(memory, fd, path, path_len)?;
+        let stat = fs.stat(&path)?;
+        write_filestat(memory, buf, &stat)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn path_filestat_set_times(
+        &mut self,
+        memory: &mut Memory<'_>,
+        fd: u32,
+        _flags: u32,
+        path: u32,
+        path_len: u32,
+        _atim: u64,
+        _mtim: u64,
+        _fst_flags: u32,
+    ) -> Result<(), Errno> {
+        self.path(memory, fd, path, path_len)?;
+        Err(Errno::Notsup)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn path_link(
+        &mut self,
+        memory: &mut Memory<'_>,
+        fd: u32,
+        _flags: u32,
+        path: u32,
+        path_len: u32,
+        _new_fd: u32,
+        _new_path: u32,
+        _new_path_len: u32,
+    ) -> Result<(), Errno> {
+        self.path(memory, fd, path, path_len)?;
+        Err(Errno::Notsup)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn path_open(
+        &mut self,
+        memory: &mut Memory<'_>,
+        fd: u32,
+        _dirflags: u32,
+        path: u32,
+        path_len: u32,
+        oflags: u32,
+        rights_base: u64,
+        _rights_inheriting: u64,
+        fdflags: u32,
+        opened_fd: u32,
+    ) -> Result<(), Errno> {
+        let (fs, path) = self.path(memory, fd, path, path_len)?;
+        let options = OpenOptions {
+            read: rights_base & RIGHTS_FD_READ != 0,
+            write: rights_base & RIGHTS_FD_WRITE != 0,
+            create: oflags & OFLAGS_CREAT != 0,
+            exclusive: oflags & OFLAGS_EXCL != 0,
+            truncate: oflags & OFLAGS_TRUNC != 0,
+            append: fdflags & FDFLAGS_APPEND != 0,
+        };
+
+        let is_dir = matches!(fs.stat(&path), Ok(stat) if stat.filetype == FileType::Directory);
+        let descriptor = if is_dir {
+            if options.create && options.exclusive {
+                return Err(Errno::Exist);
+            }
+            if options.truncate {
+                return Err(Errno::Isdir);
+            }
+            Descriptor::Dir {
+                path,
+                preopen: false,
+            }
+        } else if oflags & OFLAGS_DIRECTORY != 0 {
+            fs.stat(&path)?;
+            return Err(Errno::Notdir);
+        } else {
+            Descriptor::File {
+                file: fs.open(&path, options)?,
+                append: options.append,
+            }
+        };
+        let fd = self.insert(descriptor)?;
+        memory.write(opened_fd, fd)?;
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn path_readlink(
+        &mut self,
+        memory: &mut Memory<'_>,
+        fd: u32,
+        path: u32,
+        path_len: u32,
+        _buf: u32,
+        _buf_len: u32,
+        _bufused: u32,
+    ) -> Result<(), Errno> {
+        // there are no symbolic links
+        let (fs, path) = self.path(memory, fd, path, path_len)?;
+        fs.stat(&path)?;
+        Err(Errno::Inval)
+    }
+
+    fn path_remove_directory(
+        &mut self,
+        memory: &mut Memory<'_>,
+        fd: u32,
+        path: u32,
+        path_len: u32,
+    ) -> Result<(), Errno> {
+        let (fs, path) = self.path(memory, fd, path, path_len)?;
+        fs.remove_dir(&path)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn path_rename(
+        &mut self,
+        memory: &mut Memory<'_>,
+        fd: u32,
+        old_path: u32,
+        old_path_len: u32,
+        new_fd: u32,
+        new_path: u32,
+        new_path_len: u32,
+    ) -> Result<(), Errno> {
+        let (_, new_path) = self.path(memory, new_fd, new_path, new_path_len)?;
+        let (fs, old_path) = self.path(memory, fd, old_path, old_path_len)?;
+        fs.rename(&old_path, &new_path)
+    }
+
+    fn path_symlink(
+        &mut self,
+        memory: &mut Memory<'_>,
+        _old_path: u32,
+        _old_path_len: u32,
+        fd: u32,
+        new_path: u32,
+        new_path_len: u32,
+    ) -> Result<(), Errno> {
+        self.path(memory, fd, new_path, new_path_len)?;
+        Err(Errno::Notsup)
+    }
+
+    fn path_unlink_file(
+        &mut self,
+        memory: &mut Memory<'_>,
+        fd: u32,
+        path: u32,
+        path_len: u32,
+    ) -> Result<(), Errno> {
+        let (fs, path) = self.path(memory, fd, path, path_len)?;
+        fs.remove_file(&path)
+    }
+
+    fn poll_oneoff(
+        &mut self,
+        memory: &mut Memory<'_>,
+        subscriptions: u32,
+        events: u32,
+        nsubscriptions: u32,
+        nevents: u32,
+    ) -> Result<(), Errno> {
+        if nsubscriptions == 0 {
+            return Err(Errno::Inval);
+        }
+        let subscriptions = memory.read_slice::<[u8; 48]>(subscriptions, nsubscriptions)?;
+        let clock = self.clock.as_mut().ok_or(Errno::Notsup)?;
+
+        let mut timeouts = Vec::new();
+        for subscription in &subscriptions {
+            let userdata = u64::from_le_bytes(subscription[0..8].try_into().unwrap());
+            if subscription[8] != EVENTTYPE_CLOCK {
+                return Err(Errno::Notsup);
+            }
+            let id = u32::from_le_bytes(subscription[16..20].try_into().unwrap());
+            let id = ClockId::from_raw(id).ok_or(Errno::Inval)?;
+            let timeout = u64::from_le_bytes(subscription[24..32].try_into().unwrap());
+            let flags = u16::from_le_bytes(subscription[40..42].try_into().unwrap());
+            let deadline = if flags & SUBCLOCKFLAGS_ABSTIME != 0 {
+                timeout
+            } else {
+                clock.now(id).ok_or(Errno::Notsup)?.saturating_add(timeout)
+            };
+            timeouts.push((userdata, id, deadline));
+        }
+
+        // there is nothing else to wait for, so this blocks until the first timeout passes, unless
+        // the clocks stop advancing
+        let mut readings = alloc::vec![None; timeouts.len()];
+        let mut stalled = 0;
+        let expired = loop {
+            let mut expired = Vec::new();
+            let mut next: Option<(u64, ClockId, u64)> = None;
+            let mut advanced = false;
+            for (i, &(userdata, id, deadline)) in timeouts.iter().enumerate() {
+                let now = clock.now(id).ok_or(Errno::Notsup)?;
+                if readings[i] != Some(now) {
+                    advanced = true;
+                    readings[i] = Some(now);
+                }
+                if now >= deadline {
+                    expired.push(userdata);
+                } else if next.is_none_or(|(remaining, ..)| deadline - now < remaining) {
+                    next = Some((deadline - now, id, deadline));
+                }
+            }
+            if !expired.is_empty() {
+                break expired;
+            }
+
+            stalled = if advanced { 0 } else { stalled + 1 };
+            if stalled >= POLL_MAX_STALLED {
+                return Err(Errno::Again);
+            }
+            if let Some((_, id, deadline)) = next {
+                clock.wait(id, deadline);
+            }
+        };
+
+        for (i, userdata) in expired.iter().enumerate() {
+            let mut event = [0u8; 32];
+            event[0..8].copy_from_slice(&userdata.to_le_bytes());
+            event[10] = EVENTTYPE_CLOCK;
+            memory.write(events.wrapping_add(i as u32 * 32), event)?;
+        }
+        memory.write(nevents, expired.len() as u32)?;
+        Ok(())
+    }
+
+    fn proc_raise(&mut self, _memory: &mut Memory<'_>, _sig: u32) -> Result<(), Errno> {
+        Err(Errno::Nosys)
+    }
+
+    fn random_get(&mut self, memory: &mut Memory<'_>, buf: u32, buf_len: u32) -> Result<(), Errno> {
+        (self.random)(memory.bytes_mut(buf, buf_len)?);
+        Ok(())
+    }
+
+    fn sched_yield(&mut self, _memory: &mut Memory<'_>) -> Result<(), Errno> {
+        Ok(())
+    }
+
+    fn sock_accept(
+        &mut self,
+        _memory: &mut Memory<'_>,
+        fd: u32,
+        _flags: u32,
+        _ro_fd: u32,
+    ) -> Result<(), Errno> {
+        self.descriptor(fd)?;
+        Err(Errno::Notsup)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn sock_recv(
+        &mut self,
+        _memory: &mut Memory<'_>,
+        fd: u32,
+        _ri_data: u32,
+        _ri_data_len: u32,
+        _ri_flags: u32,
+        _ro_datalen: u32,
+        _ro_flags: u32,
+    ) -> Result<(), Errno> {
+        self.descriptor(fd)?;
+        Err(Errno::Notsup)
+    }
+
+    fn sock_send(
+        &mut self,
+        _memory: &mut Memory<'_>,
+        fd: u32,
+        _si_data: u32,
+        _si_data_len: u32,
+        _si_flags: u32,
+        _so_datalen: u32,
+    ) -> Result<(), Errno> {
+        self.descriptor(fd)?;
+        Err(Errno::Notsup)
+    }
+
+    fn sock_shutdown(&mut self, _memory: &mut Memory<'_>, fd: u32, _how: u32) -> Result<(), Errno> {
+        self.descriptor(fd)?;
+        Err(Errno::Notsup)
+    }
+}
+
+impl Default for WasiCtx {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for WasiCtx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WasiCtx")
+            .field("args", &self.args)
+            .field("env", &self.env)
+            .field("fds", &self.fds.keys())
+            .finish_non_exhaustive()
+    }
+}
+
+macro_rules! link_wasi {
+    ($linker:ident, $get:ident, { $(fn $name:ident($($arg:ident: $ty:ty),* $(,)?);)* }) => {
+        $(
+            $linker.func_wrap(MODULE, stringify!($name), move |mut ctx, ($($arg,)*): ($($ty,)*)| {
+                let (mut memory, data) = ctx.memory_and_data_mut();
+                let result = $get(data).$name(&mut memory, $($arg),*);
+                Ok(result.map_or_else(|errno| errno.code() as u32, |()| 0))
+            })?;
+        )*
+    };
+}
+
+/// Defines the functions of WASI preview 1 on `linker`, which find the [`WasiCtx`] of a store
+/// through `get`.
+///
+/// # Errors
+///
+/// This function will return an error if one of the functions is already defined and shadowing
+/// isn't allowed.
+pub fn add_to_linker<T: 'static>(
+    linker: &mut Linker<T>,
+    get: fn(&mut T) -> &mut WasiCtx,
+) -> error::Result<()> {
+    link_wasi!(linker, get, {
+        fn args_get(argv: u32, argv_buf: u32);
+        fn args_sizes_get(argc: u32, argv_buf_size: u32);
+        fn environ_get(environ: u32, environ_buf: u32);
+        fn environ_sizes_get(environc: u32, environ_buf_size: u32);
+        fn clock_res_get(id: u32, res: u32);
+        fn clock_time_get(id: u32, precision: u64, time: u32);
+        fn fd_advise(fd: u32, offset: u64, len: u64, advice: u32);
+        fn fd_allocate(fd: u32, offset: u64, len: u64);
+        fn fd_close(fd: u32);
+        fn fd_datasync(fd: u32);
+        fn fd_fdstat_get(fd: u32, buf: u32);
+        fn fd_fdstat_set_flags(fd: u32, flags: u32);
+        fn fd_fdstat_set_rights(fd: u32, rights_base: u64, rights_inheriting: u64);
+        fn fd_filestat_get(fd: u32, buf: u32);
+        fn fd_filestat_set_size(fd: u32, size: u64);
+        fn fd_filestat_set_times(fd: u32, atim: u64, mtim: u64, fst_flags: u32);
+        fn fd_pread(fd: u32, iovs: u32, iovs_len: u32, offset: u64, nread: u32);
+        fn fd_prestat_get(fd: u32, buf: u32);
+        fn fd_prestat_dir_name(fd: u32, path: u32, path_len: u32);
+        fn fd_pwrite(fd: u32, iovs: u32, iovs_len: u32, offset: u64, nwritten: u32);
+        fn fd_read(fd: u32, iovs: u32, iovs_len: u32, nread: u32);
+        fn fd_readdir(fd: u32, buf: u32, buf_len: u32, cookie: u64, bufused: u32);
+        fn fd_renumber(fd: u32, to: u32);
+        fn fd_seek(fd: u32, offset: i64, whence: u32, newoffset: u32);
+        fn fd_sync(fd: u32);
+        fn fd_tell(fd: u32, offset: u32);
+        fn fd_write(fd: u32, iovs: u32, iovs_len: u32, nwritten: u32);
+        fn path_create_directory(fd: u32, path: u32, path_len: u32);
+        fn path_filestat_get(fd: u32, flags: u32, path: u32, path_len: u32, buf: u32);
+        fn path_filestat_set_times(
+            fd: u32,
+            flags: u32,
+            path: u32,
+            path_len: u32,
+            atim: u64,
+            mtim: u64,
+            fst_flags: u32,
+        );
+        fn path_link(
+            fd: u32,
+            flags: u32,
+            path: u32,
+            path_len: u32,
+            new_fd: u32,
+            new_path: u32,
+            new_path_len: u32,
+        );
+        fn path_open(
+            fd: u32,
+            dirflags: u32,
+            path: u32,
+            path_len: u32,
+            oflags: u32,
+            rights_base: u64,
+            rights_inheriting: u64,
+            fdflags: u32,
+            opened_fd: u32,
+        );
+        fn path_readlink(fd: u32, path: u32, path_len: u32, buf: u32, buf_len: u32, bufused: u32);
+        fn path_remove_directory(fd: u32, path: u32, path_len: u32);
+        fn path_rename(
+            fd: u32,
+            old_path: u32,
+            old_path_len: u32,
+            new_fd: u32,
+            new_path: u32,
+            new_path_len: u32,
+        );
+        fn path_symlink(
+            old_path: u32,
+            old_path_len: u32,
+            fd: u32,
+            new_path: u32,
+            new_path_len: u32,
+        );
+        fn path_unlink_file(fd: u32, path: u32, path_len: u32);
+        fn poll_oneoff(subscriptions: u32, events: u32, nsubscriptions: u32, nevents: u32);
+        fn proc_raise(sig: u32);
+        fn random_get(buf: u32, buf_len: u32);
+        fn sched_yield();
+        fn sock_accept(fd: u32, flags: u32, ro_fd: u32);
+        fn sock_recv(
+            fd: u32,
+            ri_data: u32,
+            ri_data_len: u32,
+            ri_flags: u32,
+            ro_datalen: u32,
+            ro_flags: u32,
+        );
+        fn sock_send(fd: u32, si_data: u32, si_data_len: u32, si_flags: u32, so_datalen: u32);
+        fn sock_shutdown(fd: u32, how: u32);
+    });
+    // the only function that doesn't return an errno
+    linker.func_wrap(MODULE, "proc_exit", |_ctx, code: u32| {
+        Err::<(), _>(HostError::new(Exit(code)))
+    })?;
+    Ok(())
+}
+
+/// Resolves `path` relative to the directory `base`, which it may not escape.
+fn resolve(base: &str, path: &str) -> Result<String, Errno> {
+    if path.starts_with('/') {
+        return Err(Errno::NotCapable);
+    }
+    let mut components: Vec<&str> = base.split('/').filter(|c| !c.is_empty()).collect();
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop().ok_or(Errno::NotCapable)?;
+            }
+            component => components.push(component),
+        }
+    }
+    Ok(components.join("/"))
+}
+
+/// Runs `f` with the position of `file` at `offset`, restoring it afterwards.
+fn at_offset<R>(
+    file: &mut dyn File,
+    offset: u64,
+    f: impl FnOnce(&mut dyn File) -> Result<R, Errno>,
+) -> Result<R, Errno> {
+    let pos = file.seek(SeekFrom::Current(0))?;
+    file.seek(SeekFrom::Start(offset))?;
+    let result = f(file);
+    file.seek(SeekFrom::Start(pos))?;
+    result
+}
+
+fn read_vectored(
+    file: &mut dyn File,
+    memory: &mut Memory<'_>,
+    iovs: u32,
+    iovs_len: u32,
+) -> Result<u32, Errno> {
+    let mut total = 0u32;
+    for [buf, buf_len] in memory.read_slice::<[u32; 2]>(iovs, iovs_len)? {
+        let len = file.read(memory.bytes_mut(buf, buf_len)?)?;
+        total = total.saturating_add(len as u32);
+        if len < buf_len as usize {
+            break;
+        }
+    }
+    Ok(total)
+}
+
+fn write_vectored(
+    file: &mut dyn File,
+    memory: &mut Memory<'_>,
+    iovs: u32,
+    iovs_len: u32,
+) -> Result<u32, Errno> {
+    let mut total = 0u32;
+    for [buf, buf_len] in memory.read_slice::<[u32; 2]>(iovs, iovs_len)? {
+        let len = file.write(memory.bytes(buf, buf_len)?)?;
+        total = total.saturating_add(len as u32);
+        if len < buf_len as usize {
+            break;
+        }
+    }
+    Ok(total)
+}
+
+/// Writes nul-terminated `strings` to `buf` and pointers to them to `ptrs`.
+fn write_strings(
+    memory: &mut Memory<'_>,
+    strings: &[String],
+    ptrs: u32,
+    mut buf: u32,
+) -> Result<(), Errno> {
+    for (i, string) in strings.iter().enumerate() {
+        memory.write(ptrs.wrapping_add(i as u32 * 4), buf)?;
+        memory.write_slice(buf, string.as_bytes())?;
+        buf = buf.wrapping_add(string.len() as u32);
+        memory.write(buf, 0u8)?;
+        buf = buf.wrapping_add(1);
+    }
+    Ok(())
+}
+
+fn write_sizes(
+    memory: &mut Memory<'_>,
+    strings: &[String],
+    count: u32,
+    buf_size: u32,
+) -> Result<(), Errno> {
+    let size: usize = strings.iter().map(|string| string.len() + 1).sum();
+    memory.write(count, strings.len() as u32)?;
+    memory.write(buf_size, u32::try_from(size).map_err(|_| Errno::TooBig)?)?;
+    Ok(())
+}
+
+fn write_filestat(memory: &mut Memory<'_>, buf: u32, stat: &FileStat) -> Result<(), Errno> {
+    let mut filestat = [0u8; 64];
+    filestat[16] = stat.filetype as u8;
+    // nlink
+    filestat[24..32].copy_from_slice(&1u64.to_le_bytes());
+    filestat[32..40].copy_from_slice(&stat.size.to_le_bytes());
+    filestat[40..48].copy_from_slice(&stat.atim.to_le_bytes());
+    filestat[48..56].copy_from_slice(&stat.mtim.to_le_bytes());
+    filestat[56..64].copy_from_slice(&stat.ctim.to_le_bytes());
+    memory.write(buf, filestat)?;
+    Ok(())
+}
+
+/// A [SplitMix64](https://prng.di.unimi.it/splitmix64.c) generator.
+fn split_mix(mut state: u64) -> impl FnMut(&mut [u8]) {
+    move |buf| {
+        for chunk in buf.chunks_mut(8) {
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^= z >> 31;
+            chunk.copy_from_slice(&z.to_le_bytes()[..chunk.len()]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::rc::Rc;
+    use core::cell::RefCell;
+
+    use super::*;
+
+    #[test]
+    fn resolve_paths() {
+        assert_eq!(resolve("", "a/./b//c"), Ok("a/b/c".into()));
+        assert_eq!(resolve("a/b", "../c"), Ok("a/c".into()));
+        assert_eq!(resolve("a", ".."), Ok("".into()));
+        assert_eq!(resolve("a", "../.."), Err(Errno::NotCapable));
+        assert_eq!(resolve("", "/etc"), Err(Errno::NotCapable));
+    }
+
+    #[test]
+    fn stdout_and_args() {
+        let output = Rc::new(RefCell::new(Vec::new()));
+        let mut wasi = WasiCtx::new();
+        let stdout = output.clone();
+        wasi.args(["prog", "-v"])
+            .stdout(move |bytes: &[u8]| stdout.borrow_mut().extend_from_slice(bytes));
+
+        let mut data = [0u8; 64];
+        let mut memory = Memory::new(&mut data);
+        memory.write_slice(0, b"hello world").unwrap();
+        memory.write_slice(16, &[0u32, 6, 6, 5]).unwrap();
+        wasi.fd_write(&mut memory, 1, 16, 2, 32).unwrap();
+        assert_eq!(*output.borrow(), b"hello world");
+        assert_eq!(memory.read::<u32>(32), Ok(11));
+        assert_eq!(wasi.fd_write(&mut memory, 7, 16, 2, 32), Err(Errno::Badf));
+        assert_eq!(wasi.fd_write(&mut memory, 1, 60, 2, 32), Err(Errno::Fault));
+
+        wasi.args_sizes_get(&mut memory, 0, 4).unwrap();
+        assert_eq!(memory.read_slice::<u32>(0, 2), Ok(alloc::vec![2, 8]));
+        wasi.args_get(&mut memory, 8, 16).unwrap();
+        assert_eq!(memory.read_slice::<u32>(8, 2), Ok(alloc::vec![16, 21]));
+        assert_eq!(memory.bytes(16, 8), Ok(&b"prog\0-v\0"[..]));
+    }
+
+    #[test]
+    fn files() {
+        let fs = MemoryFs::new();
+        fs.insert_file("data/in.txt", "contents").unwrap();
+        let mut wasi = WasiCtx::new();
+        wasi.file_system(fs.clone());
+
+        let mut data = [0u8; 128];
+        let mut memory = Memory::new(&mut data);
+        wasi.fd_prestat_get(&mut memory, FD_PREOPEN, 0).unwrap();
+        assert_eq!(memory.read::<[u32; 2]>(0), Ok([0, 1]));
+
+        // open `data/../data/in.txt` for reading and read it through a single iovec
+        memory.write_slice(0, b"data/../data/in.txt").unwrap();
+        wasi.path_open(
+            &mut memory,
+            FD_PREOPEN,
+            0,
+            0,
+            19,
+            0,
+            RIGHTS_FD_READ,
+            0,
+            0,
+            32,
+        )
+        .unwrap();
+        let fd = memory.read::<u32>(32).unwrap();
+        assert_eq!(fd, 4);
+        memory.write_slice(40, &[64u32, 32]).unwrap();
+        wasi.fd_read(&mut memory, fd, 40, 1, 48).unwrap();
+        assert_eq!(memory.read::<u32>(48), Ok(8));
+        assert_eq!(memory.bytes(64, 8), Ok(&b"contents"[..]));
+
+        // create `out.txt` and append what was read to it twice
+        memory.write_slice(0, b"out.txt").unwrap();
+        memory.write_slice(40, &[64u32, 8]).unwrap();
+        let oflags = OFLAGS_CREAT | OFLAGS_EXCL;
+        let rights = RIGHTS_FD_WRITE;
+        wasi.path_open(
+            &mut memory,
+            FD_PREOPEN,
+            0,
+            0,
+            7,
+            oflags,
+            rights,
+            0,
+            FDFLAGS_APPEND,
+            32,
+        )
+        .unwrap();
+        let out = memory.read::<u32>(32).unwrap();
+        wasi.fd_write(&mut memory, out, 40, 1, 48).unwrap();
+        wasi.fd_write(&mut memory, out, 40, 1, 48).unwrap();
+        assert_eq!(fs.read_file("out.txt"), Ok(b"contentscontents".to_vec()));
+        assert_eq!(
+            wasi.path_open(&mut memory, FD_PREOPEN, 0, 0, 7, oflags, rights, 0, 0, 32),
+            Err(Errno::Exist)
+        );
+
+        wasi.fd_close(&mut memory, out).unwrap();
+        assert_eq!(wasi.fd_close(&mut memory, out), Err(Errno::Badf));
+        assert_eq!(
+            wasi.fd_read(&mut memory, FD_PREOPEN, 40, 1, 48),
+            Err(Errno::Isdir)
+        );
+    }
+
+    /// Advances by `step` nanoseconds every time `poll_oneoff` waits for it.
+    struct FakeClock {
+        now: Rc<RefCell<u64>>,
+        step: u64,
+    }
+
+    impl Clock for FakeClock {
+        fn now(&mut self, _id: ClockId) -> Option<u64> {
+            Some(*self.now.borrow())
+        }
+
+        fn wait(&mut self, _id: ClockId, deadline: u64) {
+            let mut now = self.now.borrow_mut();
+            *now = deadline.min(*now + self.step);
+        }
+    }
+
+    fn write_subscription(memory: &mut Memory<'_>, ptr: u32, userdata: u64, timeout: u64) {
+        let mut subscription = [0u8; 48];
+        subscription[0..8].copy_from_slice(&userdata.to_le_bytes());
+        subscription[8] = EVENTTYPE_CLOCK;
+        subscription[24..32].copy_from_slice(&timeout.to_le_bytes());
+        memory.write(ptr, subscription).unwrap();
+    }
+
+    #[test]
+    fn poll_oneoff_waits_for_the_clock() {
+        let now = Rc::new(RefCell::new(0));
+        let mut wasi = WasiCtx::new();
+        wasi.clock(FakeClock {
+            now: now.clone(),
+            step: 10,
+        });
+
+        let mut data = [0u8; 256];
+        let mut memory = Memory::new(&mut data);
+        write_subscription(&mut memory, 0, 1, 1000);
+        write_subscription(&mut memory, 48, 2, 25);
+        wasi.poll_oneoff(&mut memory, 0, 96, 2, 160).unwrap();
+        assert_eq!(*now.borrow(), 25);
+        assert_eq!(memory.read::<u32>(160), Ok(1));
+        assert_eq!(memory.read::<u64>(96), Ok(2));
+    }
+
+    #[test]
+    fn poll_oneoff_gives_up_on_stalled_clocks() {
+        let now = Rc::new(RefCell::new(0));
+        let mut wasi = WasiCtx::new();
+        wasi.clock(FakeClock { now, step: 0 });
+
+        let mut data = [0u8; 128];
+        let mut memory = Memory::new(&mut data);
+        write_subscription(&mut memory, 0, 1, 1000);
+        assert_eq!(
+            wasi.poll_oneoff(&mut memory, 0, 48, 1, 80),
+            Err(Errno::Again)
+        );
+    }
+}
@@ -0,0 +1,446 @@
+//! The file system a guest is given access to.
+use alloc::{
+    boxed::Box,
+    collections::BTreeMap,
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{cell::RefCell, convert::TryFrom};
+
+use super::{DirEntry, Errno, FileStat, FileType, OpenOptions, SeekFrom};
+
+/// An open file, or a stream like stdin.
+///
+/// Every operation fails by default, so streams only implement what they support.
+pub trait File {
+    /// Reads into `buf` from the current position, returning how many bytes were read.
+    /// Zero means the end of the file was reached.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Errno> {
+        let _ = buf;
+        Err(Errno::Badf)
+    }
+
+    /// Writes `buf` at the current position, returning how many bytes were written.
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Errno> {
+        let _ = buf;
+        Err(Errno::Badf)
+    }
+
+    /// Moves the current position, returning the new position from the start of the file.
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Errno> {
+        let _ = pos;
+        Err(Errno::Spipe)
+    }
+
+    /// Returns the attributes of the file.
+    fn stat(&mut self) -> Result<FileStat, Errno> {
+        Ok(FileStat {
+            filetype: FileType::CharacterDevice,
+            ..FileStat::default()
+        })
+    }
+
+    /// Truncates or extends the file to `size` bytes.
+    fn set_size(&mut self, size: u64) -> Result<(), Errno> {
+        let _ = size;
+        Err(Errno::Inval)
+    }
+
+    /// Writes pending changes to the underlying storage.
+    fn sync(&mut self) -> Result<(), Errno> {
+        Ok(())
+    }
+}
+
+/// A file system, which the guest sees as its root directory `/`.
+///
+/// Paths are relative to the root, separated by `/` and never contain `.` or `..` components.
+/// The root itself is the empty path. Modifying the file system fails with [`Errno::Notsup`]
+/// unless implemented.
+pub trait FileSystem {
+    /// Opens the file at `path`.
+    fn open(&mut self, path: &str, options: OpenOptions) -> Result<Box<dyn File>, Errno>;
+
+    /// Returns the attributes of the file or directory at `path`.
+    fn stat(&mut self, path: &str) -> Result<FileStat, Errno>;
+
+    /// Lists the entries of the directory at `path`.
+    fn read_dir(&mut self, path: &str) -> Result<Vec<DirEntry>, Errno>;
+
+    /// Creates an empty directory at `path`.
+    fn create_dir(&mut self, path: &str) -> Result<(), Errno> {
+        let _ = path;
+        Err(Errno::Notsup)
+    }
+
+    /// Removes the empty directory at `path`.
+    fn remove_dir(&mut self, path: &str) -> Result<(), Errno> {
+        let _ = path;
+        Err(Errno::Notsup)
+    }
+
+    /// Removes the file at `path`.
+    fn remove_file(&mut self, path: &str) -> Result<(), Errno> {
+        let _ = path;
+        Err(Errno::Notsup)
+    }
+
+    /// Moves the file or directory at `from` to `to`, replacing the file at `to` if there is one.
+    fn rename(&mut self, from: &str, to: &str) -> Result<(), Errno> {
+        let _ = (from, to);
+        Err(Errno::Notsup)
+    }
+}
+
+type Dir = BTreeMap<String, Node>;
+
+#[derive(Debug, Clone)]
+enum Node {
+    File(Rc<RefCell<Vec<u8>>>),
+    Dir(Dir),
+}
+
+/// A file system held in memory, e.g. to test guests on the host.
+///
+/// Clones share their contents, so a clone kept by the host sees what the guest wrote.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryFs {
+    root: Rc<RefCell<Dir>>,
+}
+
+impl MemoryFs {
+    /// Creates an empty file system.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates or replaces the file at `path`, along with the directories leading to it.
+    pub fn insert_file(&self, path: &str, contents: impl Into<Vec<u8>>) -> Result<(), Errno> {
+        let (parent, name) = split(path);
+        let mut root = self.root.borrow_mut();
+        let mut dir = &mut *root;
+        for component in components(parent) {
+            let node = dir
+                .entry(component.to_string())
+                .or_insert_with(|| Node::Dir(Dir::new()));
+            dir = match node {
+                Node::Dir(dir) => dir,
+                Node::File(_) => return Err(Errno::Notdir),
+            };
+        }
+        if let Some(Node::Dir(_)) = dir.get(name) {
+            return Err(Errno::Isdir);
+        }
+        let contents = Rc::new(RefCell::new(contents.into()));
+        dir.insert(name.to_string(), Node::File(contents));
+        Ok(())
+    }
+
+    /// Returns the contents of the file at `path`.
+    pub fn read_file(&self, path: &str) -> Result<Vec<u8>, Errno> {
+        let (parent, name) = split(path);
+        match find_dir(&self.root.borrow(), parent)?.get(name) {
+            Some(Node::File(contents)) => Ok(contents.borrow().clone()),
+            Some(Node::Dir(_)) => Err(Errno::Isdir),
+            None => Err(Errno::Noent),
+        }
+    }
+}
+
+impl FileSystem for MemoryFs {
+    fn open(&mut self, path: &str, options: OpenOptions) -> Result<Box<dyn File>, Errno> {
+        let (parent, name) = split(path);
+        let mut root = self.root.borrow_mut();
+        let dir = find_dir_mut(&mut root, parent)?;
+        let contents = match dir.get(name) {
+            _ if name.is_empty() => return Err(Errno::Isdir),
+            Some(Node::Dir(_)) => return Err(Errno::Isdir),
+            Some(Node::File(_)) if options.create && options.exclusive => return Err(Errno::Exist),
+            Some(Node::File(contents)) => contents.clone(),
+            None if options.create => {
+                let contents = Rc::new(RefCell::new(Vec::new()));
+                dir.insert(name.to_string(), Node::File(contents.clone()));
+                contents
+            }
+            None => return Err(Errno::Noent),
+        };
+        if options.truncate {
+            contents.borrow_mut().clear();
+        }
+        Ok(Box::new(MemoryFile {
+            contents,
+            pos: 0,
+            options,
+        }))
+    }
+
+    fn stat(&mut self, path: &str) -> Result<FileStat, Errno> {
+        let (parent, name) = split(path);
+        let root = self.root.borrow();
+        let filetype = match find_dir(&root, parent)?.get(name) {
+            _ if name.is_empty() => FileType::Directory,
+            Some(Node::Dir(_)) => FileType::Directory,
+            Some(Node::File(contents)) => {
+                return Ok(FileStat {
+                    filetype: FileType::RegularFile,
+                    size: contents.borrow().len() as u64,
+                    ..FileStat::default()
+                })
+            }
+            None => return Err(Errno::Noent),
+        };
+        Ok(FileStat {
+            filetype,
+            ..FileStat::default()
+        })
+    }
+
+    fn read_dir(&mut self, path: &str) -> Result<Vec<DirEntry>, Errno> {
+        let root = self.root.borrow();
+        let entries = find_dir(&root, path)?
+            .iter()
+            .map(|(name, node)| DirEntry {
+                name: name.clone(),
+                filetype: match node {
+                    Node::File(_) => FileType::RegularFile,
+                    Node::Dir(_) => FileType::Directory,
+                },
+            })
+            .collect();
+        Ok(entries)
+    }
+
+    fn create_dir(&mut self, path: &str) -> Result<(), Errno> {
+        let (parent, name) = split(path);
+        let mut root = self.root.borrow_mut();
+        let dir = find_dir_mut(&mut root, parent)?;
+        if name.is_empty() || dir.contains_key(name) {
+            return Err(Errno::Exist);
+        }
+        dir.insert(name.to_string(), Node::Dir(Dir::new()));
+        Ok(())
+    }
+
+    fn remove_dir(&mut self, path: &str) -> Result<(), Errno> {
+        let (parent, name) = split(path);
+        let mut root = self.root.borrow_mut();
+        let dir = find_dir_mut(&mut root, parent)?;
+        match dir.get(name) {
+            _ if name.is_empty() => return Err(Errno::Busy),
+            Some(Node::Dir(entries)) if !entries.is_empty() => return Err(Errno::NotEmpty),
+            Some(Node::Dir(_)) => {}
+            Some(Node::File(_)) => return Err(Errno::Notdir),
+            None => return Err(Errno::Noent),
+        }
+        dir.remove(name);
+        Ok(())
+    }
+
+    fn remove_file(&mut self, path: &str) -> Result<(), Errno> {
+        let (parent, name) = split(path);
+        let mut root = self.root.borrow_mut();
+        let dir = find_dir_mut(&mut root, parent)?;
+        match dir.get(name) {
+            Some(Node::File(_)) => {}
+            Some(Node::Dir(_)) => return Err(Errno::Isdir),
+            None if name.is_empty() => return Err(Errno::Isdir),
+            None => return Err(Errno::Noent),
+        }
+        dir.remove(name);
+        Ok(())
+    }
+
+    fn rename(&mut self, from: &str, to: &str) -> Result<(), Errno> {
+        let (from_parent, from_name) = split(from);
+        let (to_parent, to_name) = split(to);
+        if from_name.is_empty() || to_name.is_empty() {
+            return Err(Errno::Busy);
+        }
+        // a directory can't be moved into itself
+        if to
+            .strip_prefix(from)
+            .is_some_and(|rest| rest.starts_with('/'))
+        {
+            return Err(Errno::Inval);
+        }
+
+        let mut root = self.root.borrow_mut();
+        let source_is_dir = match find_dir(&root, from_parent)?.get(from_name) {
+            Some(node) => matches!(node, Node::Dir(_)),
+            None => return Err(Errno::Noent),
+        };
+        match find_dir(&root, to_parent)?.get(to_name) {
+            Some(Node::Dir(_)) if !source_is_dir => return Err(Errno::Isdir),
+            Some(Node::Dir(entries)) if !entries.is_empty() => return Err(Errno::NotEmpty),
+            Some(Node::File(_)) if source_is_dir => return Err(Errno::Notdir),
+            _ => {}
+        }
+
+        let node = find_dir_mut(&mut root, from_parent)?
+            .remove(from_name)
+            .ok_or(Errno::Noent)?;
+        find_dir_mut(&mut root, to_parent)?.insert(to_name.to_string(), node);
+        Ok(())
+    }
+}
+
+struct MemoryFile {
+    contents: Rc<RefCell<Vec<u8>>>,
+    pos: u64,
+    options: OpenOptions,
+}
+
+impl File for MemoryFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Errno> {
+        if !self.options.read {
+            return Err(Errno::Badf);
+        }
+        let contents = self.contents.borrow();
+        let start = usize::try_from(self.pos)
+            .unwrap_or(usize::MAX)
+            .min(contents.len());
+        let len = buf.len().min(contents.len() - start);
+        buf[..len].copy_from_slice(&contents[start..start + len]);
+        self.pos += len as u64;
+        Ok(len)
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Errno> {
+        if !self.options.write {
+            return Err(Errno::Badf);
+        }
+        let mut contents = self.contents.borrow_mut();
+        let start = usize::try_from(self.pos).map_err(|_| Errno::Fbig)?;
+        let end = start.checked_add(buf.len()).ok_or(Errno::Fbig)?;
+        if end > contents.len() {
+            contents.resize(end, 0);
+        }
+        contents[start..end].copy_from_slice(buf);
+        self.pos = end as u64;
+        Ok(buf.len())
+    }
+
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Errno> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(offset) => {
+                self.pos = offset;
+                return Ok(offset);
+            }
+            SeekFrom::Current(offset) => (self.pos, offset),
+            SeekFrom::End(offset) => (self.contents.borrow().len() as u64, offset),
+        };
+        self.pos = base.checked_add_signed(offset).ok_or(Errno::Inval)?;
+        Ok(self.pos)
+    }
+
+    fn stat(&mut self) -> Result<FileStat, Errno> {
+        Ok(FileStat {
+            filetype: FileType::RegularFile,
+            size: self.contents.borrow().len() as u64,
+            ..FileStat::default()
+        })
+    }
+
+    fn set_size(&mut self, size: u64) -> Result<(), Errno> {
+        if !self.options.write {
+            return Err(Errno::Badf);
+        }
+        let size = usize::try_from(size).map_err(|_| Errno::Fbig)?;
+        self.contents.borrow_mut().resize(size, 0);
+        Ok(())
+    }
+}
+
+fn components(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|component| !component.is_empty())
+}
+
+/// Splits `path` into the path of its parent directory and its name.
+fn split(path: &str) -> (&str, &str) {
+    path.rsplit_once('/').unwrap_or(("", path))
+}
+
+fn find_dir<'a>(root: &'a Dir, path: &str) -> Result<&'a Dir, Errno> {
+    components(path).try_fold(root, |dir, component| match dir.get(component) {
+        Some(Node::Dir(dir)) => Ok(dir),
+        Some(Node::File(_)) => Err(Errno::Notdir),
+        None => Err(Errno::Noent),
+    })
+}
+
+fn find_dir_mut<'a>(root: &'a mut Dir, path: &str) -> Result<&'a mut Dir, Errno> {
+    components(path).try_fold(root, |dir, component| match dir.get_mut(component) {
+        Some(Node::Dir(dir)) => Ok(dir),
+        Some(Node::File(_)) => Err(Errno::Notdir),
+        None => Err(Errno::Noent),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_all(file: &mut dyn File) -> Vec<u8> {
+        let mut buf = [0; 64];
+        let len = file.read(&mut buf).unwrap();
+        buf[..len].to_vec()
+    }
+
+    #[test]
+    fn files() {
+        let mut fs = MemoryFs::new();
+        fs.insert_file("a/b.txt", "hello").unwrap();
+        let read = OpenOptions {
+            read: true,
+            ..OpenOptions::default()
+        };
+        let mut file = fs.open("a/b.txt", read).unwrap();
+        assert_eq!(read_all(&mut *file), b"hello");
+        assert_eq!(file.write(b"x"), Err(Errno::Badf));
+
+        let write = OpenOptions {
+            write: true,
+            create: true,
+            ..OpenOptions::default()
+        };
+        let mut file = fs.open("a/c.txt", write).unwrap();
+        file.seek(SeekFrom::Start(2)).unwrap();
+        file.write(b"yo").unwrap();
+        assert_eq!(fs.clone().read_file("a/c.txt"), Ok(b"\0\0yo".to_vec()));
+        assert_eq!(file.seek(SeekFrom::Current(-5)), Err(Errno::Inval));
+
+        let exclusive = OpenOptions {
+            exclusive: true,
+            ..write
+        };
+        assert!(matches!(fs.open("a/c.txt", exclusive), Err(Errno::Exist)));
+        assert!(matches!(fs.open("a", read), Err(Errno::Isdir)));
+        assert!(matches!(fs.open("a/b.txt/c", read), Err(Errno::Notdir)));
+        assert!(matches!(fs.open("d.txt", read), Err(Errno::Noent)));
+    }
+
+    #[test]
+    fn directories() {
+        let mut fs = MemoryFs::new();
+        fs.insert_file("a/b.txt", "hello").unwrap();
+        fs.create_dir("c").unwrap();
+        assert_eq!(fs.create_dir("c"), Err(Errno::Exist));
+        assert_eq!(fs.stat("").unwrap().filetype, FileType::Directory);
+        assert_eq!(fs.stat("a/b.txt").unwrap().size, 5);
+
+        assert_eq!(fs.rename("a", "a/d"), Err(Errno::Inval));
+        fs.rename("a/b.txt", "c/b.txt").unwrap();
+        assert_eq!(
+            fs.read_dir("c"),
+            Ok(alloc::vec![DirEntry {
+                name: "b.txt".into(),
+                filetype: FileType::RegularFile,
+            }])
+        );
+        assert_eq!(fs.remove_dir("c"), Err(Errno::NotEmpty));
+        fs.remove_file("c/b.txt").unwrap();
+        fs.remove_dir("c").unwrap();
+        assert_eq!(fs.read_dir("").unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,184 @@
+//! Values exchanged between guests, the WASI implementation and its host backends.
+use alloc::string::String;
+use core::fmt;
+
+use crate::error::Trap;
+
+/// An error code returned to the guest.
+///
+/// Only the codes that host backends are likely to report are listed, see the [WASI
+/// documentation](https://github.com/WebAssembly/WASI/blob/main/legacy/preview1/docs.md#errno)
+/// for their meaning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u16)]
+#[non_exhaustive]
+pub enum Errno {
+    /// Argument list too long.
+    TooBig = 1,
+    /// Permission denied.
+    Access = 2,
+    /// Resource unavailable, or operation would block.
+    Again = 6,
+    /// Bad file descriptor.
+    Badf = 8,
+    /// Device or resource busy.
+    Busy = 10,
+    /// File exists.
+    Exist = 20,
+    /// Bad address.
+    Fault = 21,
+    /// File too large.
+    Fbig = 22,
+    /// Invalid argument.
+    Inval = 28,
+    /// I/O error.
+    Io = 29,
+    /// Is a directory.
+    Isdir = 31,
+    /// Too many open files.
+    Mfile = 33,
+    /// Filename too long.
+    NameTooLong = 37,
+    /// No such file or directory.
+    Noent = 44,
+    /// Not enough space.
+    Nomem = 48,
+    /// No space left on device.
+    Nospc = 51,
+    /// Function not supported.
+    Nosys = 52,
+    /// Not a directory or a symbolic link to a directory.
+    Notdir = 54,
+    /// Directory not empty.
+    NotEmpty = 55,
+    /// Not supported, or operation not supported on socket.
+    Notsup = 58,
+    /// Value too large to be stored in data type.
+    Overflow = 61,
+    /// Operation not permitted.
+    Perm = 63,
+    /// Read-only file system.
+    Rofs = 69,
+    /// Invalid seek.
+    Spipe = 70,
+    /// Extension: Capabilities insufficient.
+    NotCapable = 76,
+}
+
+impl Errno {
+    /// The code passed to the guest.
+    pub fn code(self) -> u16 {
+        self as u16
+    }
+}
+
+impl fmt::Display for Errno {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?} (errno {})", self.code())
+    }
+}
+
+/// Guest pointers outside of the memory are reported as [`Errno::Fault`].
+impl From<Trap> for Errno {
+    fn from(_: Trap) -> Self {
+        Errno::Fault
+    }
+}
+
+/// The clocks a guest can read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClockId {
+    /// The time since the unix epoch.
+    Realtime,
+    /// A clock that only ever moves forward, from an arbitrary starting point.
+    Monotonic,
+    /// The CPU time consumed by the process.
+    ProcessCputime,
+    /// The CPU time consumed by the thread.
+    ThreadCputime,
+}
+
+impl ClockId {
+    pub(crate) fn from_raw(id: u32) -> Option<Self> {
+        match id {
+            0 => Some(ClockId::Realtime),
+            1 => Some(ClockId::Monotonic),
+            2 => Some(ClockId::ProcessCputime),
+            3 => Some(ClockId::ThreadCputime),
+            _ => None,
+        }
+    }
+}
+
+/// The type of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[repr(u8)]
+pub enum FileType {
+    /// The type is unknown or none of the others.
+    #[default]
+    Unknown = 0,
+    /// A block device.
+    BlockDevice = 1,
+    /// A character device, like stdout.
+    CharacterDevice = 2,
+    /// A directory.
+    Directory = 3,
+    /// A regular file.
+    RegularFile = 4,
+    /// A symbolic link.
+    SymbolicLink = 7,
+}
+
+/// The attributes of a file.
+///
+/// Timestamps are in nanoseconds since the unix epoch, or zero if unknown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct FileStat {
+    /// The type of the file.
+    pub filetype: FileType,
+    /// The size of the file in bytes.
+    pub size: u64,
+    /// The time of the last access.
+    pub atim: u64,
+    /// The time of the last modification.
+    pub mtim: u64,
+    /// The time of the last status change.
+    pub ctim: u64,
+}
+
+/// How a file is opened, see [`FileSystem::open`](super::FileSystem::open).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct OpenOptions {
+    /// The file is read from.
+    pub read: bool,
+    /// The file is written to.
+    pub write: bool,
+    /// The file is created if it doesn't exist.
+    pub create: bool,
+    /// Opening fails with [`Errno::Exist`] if the file exists, only set along with `create`.
+    pub exclusive: bool,
+    /// The file is truncated to a size of zero.
+    pub truncate: bool,
+    /// The file is only written to at its end, as long as the guest doesn't turn this off.
+    pub append: bool,
+}
+
+/// Where [`File::seek`](super::File::seek) moves to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SeekFrom {
+    /// An offset from the start of the file.
+    Start(u64),
+    /// An offset from the current position.
+    Current(i64),
+    /// An offset from the end of the file.
+    End(i64),
+}
+
+/// An entry of a directory.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DirEntry {
+    /// The name of the entry, without the path of the directory.
+    pub name: String,
+    /// The type of the entry.
+    pub filetype: FileType,
+}
@@ -0,0 +1,40 @@
+//! Helpers shared by the integration tests.
+// every test only uses some of them
+#![allow(dead_code)]
+
+use wasm3::{Environment, Instance, Linker, Module, Store};
+use wast::{
+    parser::{self, ParseBuffer},
+    Wat,
+};
+
+/// The stack size of the stores the tests create.
+pub const STACK_SIZE: u32 = 1024 * 60;
+
+/// Parses a module written in the text format.
+pub fn parse(env: &Environment, source: &str) -> Module {
+    let buffer = ParseBuffer::new(source).expect("Unable to lex module");
+    let mut wat = parser::parse::<Wat>(&buffer).expect("Unable to parse module");
+    let bytes = wat.encode().expect("Unable to encode module");
+    env.parse_module(bytes).expect("Unable to parse module")
+}
+
+/// Creates a store holding `data`.
+pub fn store<T>(env: &Environment, data: T) -> Store<T> {
+    env.create_store(STACK_SIZE, data)
+        .expect("Unable to create runtime")
+}
+
+/// Creates a store holding `data` and instantiates the module written in `source` in it.
+pub fn instantiate<T>(
+    env: &Environment,
+    linker: &Linker<T>,
+    source: &str,
+    data: T,
+) -> (Store<T>, Instance<T>) {
+    let mut store = store(env, data);
+    let instance = linker
+        .instantiate(&mut store, parse(env, source))
+        .expect("Unable to load module");
+    (store, instance)
+}
@@ -0,0 +1,48 @@
+use wasm3::Environment;
+
+mod common;
+
+const PROGRAM: &str = r#"
+(module $robot
+  (@custom "hydrozoa.api" "\01\00")
+  (@custom "hydrozoa.team" (after data) "1234A")
+  (import "vex" "vexDisplayErase" (func $erase))
+  (func $opcontrol (export "opcontrol")
+    (call $erase))
+  (func $autonomous)
+)
+"#;
+
+fn module(env: &Environment) -> wasm3::Module {
+    common::parse(env, PROGRAM)
+}
+
+#[test]
+fn custom_sections() {
+    let env = Environment::new().expect("Unable to create environment");
+    let module = module(&env);
+
+    let api = module
+        .custom_sections()
+        .find(|section| section.name() == "hydrozoa.api")
+        .expect("Missing api section");
+    assert_eq!(api.data(), [1, 0]);
+    let team = module
+        .custom_sections()
+        .find(|section| section.name() == "hydrozoa.team")
+        .expect("Missing team section");
+    assert_eq!(team.data(), b"1234A");
+}
+
+#[test]
+fn function_names() {
+    let env = Environment::new().expect("Unable to create environment");
+    let module = module(&env);
+
+    let names = module.names().expect("Missing name section");
+    assert_eq!(names.module(), Some("robot"));
+    assert_eq!(names.function(0), Some("erase"));
+    assert_eq!(names.function(1), Some("opcontrol"));
+    assert_eq!(names.function(2), Some("autonomous"));
+    assert_eq!(names.function(3), None);
+}
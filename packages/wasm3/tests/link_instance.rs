@@ -0,0 +1,128 @@
+use common::parse;
+use wasm3::{error::Error, Environment, Instance, Store};
+
+mod common;
+
+const LIBRARY: &str = r#"
+(module
+  (import "host" "gain" (func $gain (result i32)))
+  (global $integral (mut i32) (i32.const 0))
+  (global $started (mut i32) (i32.const 0))
+  (func $start
+    (global.set $started (i32.const 1)))
+  (start $start)
+  (func (export "pid") (param $error i32) (result i32)
+    (global.set $integral (i32.add (global.get $integral) (local.get $error)))
+    (i32.add
+      (i32.mul (local.get $error) (call $gain))
+      (global.get $integral)))
+  (func (export "started") (result i32)
+    (global.get $started))
+)
+"#;
+
+const PROGRAM: &str = r#"
+(module
+  (import "robot" "pid" (func $pid (param i32) (result i32)))
+  (import "robot" "started" (func $started (result i32)))
+  (func (export "step") (param i32) (result i32)
+    (call $pid (local.get 0)))
+  (func (export "library_started") (result i32)
+    (call $started))
+)
+"#;
+
+fn load(env: &Environment, store: &mut Store<()>) -> (Instance<()>, Instance<()>) {
+    let mut library = store
+        .instantiate(parse(env, LIBRARY))
+        .expect("Unable to load library");
+    library
+        .link_closure(store, "host", "gain", |_, ()| Ok(2))
+        .unwrap();
+    let program = store
+        .instantiate(parse(env, PROGRAM))
+        .expect("Unable to load program");
+    (library, program)
+}
+
+#[test]
+fn calls_reach_the_other_instance() {
+    let env = Environment::new().expect("Unable to create environment");
+    let mut store = common::store(&env, ());
+    let (library, mut program) = load(&env, &mut store);
+    program
+        .link_instance(&mut store, "robot", &library)
+        .unwrap();
+    assert!(program.unresolved_imports(&store).unwrap().is_empty());
+
+    let step = program.find_function::<i32, i32>(&store, "step").unwrap();
+    let library_started = program
+        .find_function::<(), i32>(&store, "library_started")
+        .unwrap();
+    assert_eq!(library_started.call(&mut store), Ok(1));
+    assert_eq!(step.call(&mut store, 3), Ok(9));
+    assert_eq!(step.call(&mut store, 1), Ok(6));
+
+    // both share the state of the library
+    let pid = library.find_function::<i32, i32>(&store, "pid").unwrap();
+    assert_eq!(pid.call(&mut store, 0), Ok(4));
+}
+
+#[test]
+fn missing_exports_link_nothing() {
+    let env = Environment::new().expect("Unable to create environment");
+    let mut store = common::store(&env, ());
+    let (library, _) = load(&env, &mut store);
+    let mut program = store
+        .instantiate(parse(
+            &env,
+            r#"(module
+                 (import "robot" "pid" (func (param i32) (result i32)))
+                 (import "robot" "odometry" (func (result f64))))"#,
+        ))
+        .unwrap();
+
+    match program.link_instance(&mut store, "robot", &library) {
+        Err(Error::UnresolvedImports { imports }) => {
+            assert_eq!(imports.len(), 1);
+            assert_eq!(imports[0].name, "odometry");
+        }
+        result => panic!("unexpected result {:?}", result),
+    }
+    assert_eq!(program.unresolved_imports(&store).unwrap().len(), 2);
+}
+
+#[test]
+fn signatures_must_match() {
+    let env = Environment::new().expect("Unable to create environment");
+    let mut store = common::store(&env, ());
+    let (library, _) = load(&env, &mut store);
+    let mut program = store
+        .instantiate(parse(
+            &env,
+            r#"(module (import "robot" "pid" (func (param i64) (result i32))))"#,
+        ))
+        .unwrap();
+
+    match program.link_instance(&mut store, "robot", &library) {
+        Err(Error::LinkImport { name, source, .. }) => {
+            assert_eq!(name, "pid");
+            assert_eq!(*source, Error::InvalidFunctionSignature);
+        }
+        result => panic!("unexpected result {:?}", result),
+    }
+}
+
+#[test]
+fn linked_instances_unload_in_order() {
+    let env = Environment::new().expect("Unable to create environment");
+    let mut store = common::store(&env, ());
+    let (library, mut program) = load(&env, &mut store);
+    program
+        .link_instance(&mut store, "robot", &library)
+        .unwrap();
+
+    assert_eq!(store.unload(library.clone()), Err(Error::InstanceInUse));
+    store.unload(program).unwrap();
+    store.unload(library).unwrap();
+}
@@ -0,0 +1,62 @@
+use wasm3::{error::Error, Environment, Linker, Store};
+
+mod common;
+
+const GUEST: &str = r#"
+(module
+  (import "host" "tick" (func (param i64) (result i32)))
+  (memory 1)
+)
+"#;
+
+/// Whether `store` is as empty as a fresh one, without any modules or memory.
+fn assert_empty(env: &Environment, store: &Store<()>) {
+    assert_eq!(store.memory_pages(), 0);
+    assert_eq!(
+        store.snapshot().modules_hash(),
+        common::store(env, ()).snapshot().modules_hash()
+    );
+}
+
+#[test]
+fn failed_links_unload_the_instance() {
+    let env = Environment::new().expect("Unable to create environment");
+    let mut linker = Linker::new();
+    linker
+        .func_wrap("host", "tick", |_ctx, val: i32| Ok(val))
+        .unwrap();
+    let mut store = common::store(&env, ());
+
+    let result = linker.instantiate(&mut store, common::parse(&env, GUEST));
+    assert!(
+        matches!(result, Err(Error::LinkImport { .. })),
+        "unexpected result {:?}",
+        result
+    );
+    assert_empty(&env, &store);
+}
+
+#[test]
+fn strict_failures_unload_the_instance() {
+    let env = Environment::new().expect("Unable to create environment");
+    let mut linker = Linker::new();
+    linker.strict(true);
+    let mut store = common::store(&env, ());
+
+    let result = linker.instantiate(&mut store, common::parse(&env, GUEST));
+    match result {
+        Err(Error::UnresolvedImports { imports }) => {
+            assert_eq!(imports.len(), 1);
+            assert_eq!(imports[0].name, "tick");
+        }
+        result => panic!("unexpected result {:?}", result),
+    }
+    assert_empty(&env, &store);
+
+    // the store is still usable afterwards
+    linker.optional("host", "tick");
+    linker
+        .instantiate(&mut store, common::parse(&env, GUEST))
+        .unwrap();
+    assert_eq!(store.memory_pages(), 1);
+}
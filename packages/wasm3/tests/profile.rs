@@ -0,0 +1,80 @@
+#![cfg(feature = "profiling")]
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use wasm3::{
+    profile::{op_profile, reset_op_profile},
+    Environment, Linker,
+};
+
+mod common;
+
+const GUEST: &str = r#"
+(module
+  (func $inner (param i32) (result i32)
+    (i32.add (local.get 0) (i32.const 1)))
+  (func (export "outer") (param i32) (result i32)
+    (call $inner (call $inner (local.get 0))))
+)
+"#;
+
+const TICK: u64 = 10;
+
+static NOW: AtomicU64 = AtomicU64::new(0);
+
+/// Advances by one tick every time it is read.
+extern "C" fn fake_clock() -> u64 {
+    NOW.fetch_add(TICK, Ordering::Relaxed)
+}
+
+#[test]
+fn profile_counts_calls_and_time() {
+    let env = Environment::new().expect("Unable to create environment");
+    let (mut store, instance) = common::instantiate(&env, &Linker::new(), GUEST, ());
+    store.set_profile_clock(fake_clock);
+
+    let outer = instance
+        .find_function::<i32, i32>(&store, "outer")
+        .expect("Unable to find function");
+    for i in 0..3 {
+        assert_eq!(outer.call(&mut store, i), Ok(i + 2));
+    }
+
+    // every call reads the clock when it starts and returns, and `outer` includes both calls of
+    // `inner` between its own reads
+    let profile = store.profile();
+    assert_eq!(profile.len(), 2);
+    assert_eq!(profile[0].name.as_deref(), Some("outer"));
+    assert_eq!(profile[0].function_index, 1);
+    assert_eq!(profile[0].calls, 3);
+    assert_eq!(profile[0].time, 3 * 5 * TICK);
+    assert_eq!(profile[1].function_index, 0);
+    assert_eq!(profile[1].calls, 6);
+    assert_eq!(profile[1].time, 6 * TICK);
+
+    store.reset_profile();
+    assert!(store.profile().is_empty());
+    outer.call(&mut store, 0).unwrap();
+    assert_eq!(store.profile()[0].time, 5 * TICK);
+}
+
+#[test]
+fn op_profile_counts_operations() {
+    let env = Environment::new().expect("Unable to create environment");
+    let (mut store, instance) = common::instantiate(&env, &Linker::new(), GUEST, ());
+    let outer = instance
+        .find_function::<i32, i32>(&store, "outer")
+        .expect("Unable to find function");
+    for i in 0..100 {
+        outer.call(&mut store, i).unwrap();
+    }
+
+    // the counts are shared with the other test, which runs concurrently but only a few operations
+    let profile = op_profile();
+    assert!(profile.iter().all(|op| op.name.starts_with("op_")));
+    assert!(profile.windows(2).all(|ops| ops[0].hits >= ops[1].hits));
+    assert!(profile[0].hits >= 100);
+
+    reset_op_profile();
+    assert!(op_profile().iter().all(|op| op.hits < 100));
+}
@@ -0,0 +1,192 @@
+use wasm3::{error::Error, Environment, Function, Instance, Linker, Store};
+
+mod common;
+
+const PAGE_SIZE: usize = 64 * 1024;
+
+const GUEST: &str = r#"
+(module
+  (import "host" "grow" (func $host_grow (param i32) (result i32)))
+  (import "host" "countdown" (func $host_countdown (param i32) (result i32)))
+  (import "host" "reenter" (func $host_reenter (result i32)))
+  (import "host" "swallow_trap" (func $host_swallow_trap))
+  (memory 1)
+  (func (export "grow") (param i32) (result i32)
+    (memory.grow (local.get 0)))
+  (func (export "load") (param i32) (result i32)
+    (i32.load (local.get 0)))
+  ;; keeps using memory in the frame that called the host after it grew
+  (func (export "grow_through_host") (param i32) (result i32)
+    (drop (call $host_grow (local.get 0)))
+    (i32.store (i32.const 65536) (i32.const 42))
+    (i32.load (i32.const 65536)))
+  (func (export "countdown") (param i32) (result i32)
+    (call $host_countdown (local.get 0)))
+  ;; the argument and local have to survive the nested call the host makes
+  (func (export "add_reentered") (param i32) (result i32) (local i32)
+    (local.set 1 (i32.const 1000))
+    (i32.add
+      (i32.add (local.get 0) (local.get 1))
+      (call $host_reenter)))
+  (func $trap (export "trap")
+    (unreachable))
+  ;; traps after a nested call trapped in another function
+  (func $trap_after_nested (export "trap_after_nested")
+    (call $host_swallow_trap)
+    (unreachable))
+  (func (export "clobber") (param i32 i32 i32 i32) (result i32) (local i32 i32)
+    (local.set 4 (i32.add (local.get 0) (local.get 1)))
+    (local.set 5 (i32.add (local.get 2) (local.get 3)))
+    (i32.add (local.get 4) (local.get 5)))
+)
+"#;
+
+#[derive(Default)]
+struct Guest {
+    grow: Option<Function<i32, i32>>,
+    countdown: Option<Function<i32, i32>>,
+    clobber: Option<Function<(i32, i32, i32, i32), i32>>,
+    trap: Option<Function<(), ()>>,
+}
+
+fn instantiate(linker: &Linker<Guest>) -> (Store<Guest>, Instance<Guest>) {
+    let env = Environment::new().expect("Unable to create environment");
+    common::instantiate(&env, linker, GUEST, Guest::default())
+}
+
+/// Hands the guest's functions to the host functions. Looking them up compiles them, so this has
+/// to happen after all imports are linked.
+fn init(store: &mut Store<Guest>, instance: &Instance<Guest>) {
+    let grow = instance.find_function(store, "grow").unwrap();
+    let countdown = instance.find_function(store, "countdown").unwrap();
+    let clobber = instance.find_function(store, "clobber").unwrap();
+    let trap = instance.find_function(store, "trap").unwrap();
+    *store.data_mut() = Guest {
+        grow: Some(grow),
+        countdown: Some(countdown),
+        clobber: Some(clobber),
+        trap: Some(trap),
+    };
+}
+
+fn linker() -> Linker<Guest> {
+    let mut linker = Linker::<Guest>::new();
+    linker
+        .func_wrap("host", "grow", |mut ctx, pages: i32| {
+            ctx.memory_view().write(8, 7u32)?;
+            let grow = ctx.data().grow.unwrap();
+            let previous = grow.call(&mut ctx, pages)?;
+
+            let mut memory = ctx.memory_view();
+            assert_eq!(memory.len(), (previous + pages) as usize * PAGE_SIZE);
+            assert_eq!(memory.read::<u32>(8)?, 7);
+            memory.write(PAGE_SIZE as u32 + 4, 9u32)?;
+            Ok(previous)
+        })
+        .unwrap()
+        .func_wrap("host", "countdown", |mut ctx, n: i32| {
+            if n == 0 {
+                return Ok(0);
+            }
+            let countdown = ctx.data().countdown.unwrap();
+            Ok(countdown.call(&mut ctx, n - 1)? + 1)
+        })
+        .unwrap()
+        .func_wrap("host", "reenter", |mut ctx, ()| {
+            let clobber = ctx.data().clobber.unwrap();
+            Ok(clobber.call(&mut ctx, 1, 2, 3, 4)?)
+        })
+        .unwrap()
+        .func_wrap("host", "swallow_trap", |mut ctx, ()| {
+            let trap = ctx.data().trap.unwrap();
+            assert!(trap.call(&mut ctx).is_err());
+            Ok(())
+        })
+        .unwrap();
+    linker
+}
+
+#[test]
+fn memory_grows_during_nested_call() {
+    let (mut store, instance) = instantiate(&linker());
+    init(&mut store, &instance);
+    let grow_through_host = instance
+        .find_function::<i32, i32>(&store, "grow_through_host")
+        .unwrap();
+    assert_eq!(grow_through_host.call(&mut store, 1), Ok(42));
+    assert_eq!(store.memory().len(), 2 * PAGE_SIZE);
+
+    let load = instance.find_function::<i32, i32>(&store, "load").unwrap();
+    assert_eq!(load.call(&mut store, 8), Ok(7));
+    assert_eq!(load.call(&mut store, PAGE_SIZE as i32 + 4), Ok(9));
+}
+
+#[test]
+fn linker_functions_are_reentrant() {
+    let (mut store, instance) = instantiate(&linker());
+    init(&mut store, &instance);
+    let countdown = instance
+        .find_function::<i32, i32>(&store, "countdown")
+        .unwrap();
+    assert_eq!(countdown.call(&mut store, 10), Ok(10));
+}
+
+#[test]
+fn nested_calls_keep_the_callers_frame() {
+    let (mut store, instance) = instantiate(&linker());
+    init(&mut store, &instance);
+    let add_reentered = instance
+        .find_function::<i32, i32>(&store, "add_reentered")
+        .unwrap();
+    assert_eq!(add_reentered.call(&mut store, 100), Ok(1110));
+
+    // and the stack is back where it was for the next call
+    assert_eq!(add_reentered.call(&mut store, 200), Ok(1210));
+}
+
+#[cfg(feature = "record-backtraces")]
+#[test]
+fn nested_traps_keep_their_backtraces_apart() {
+    let (mut store, instance) = instantiate(&linker());
+    init(&mut store, &instance);
+    let trap_after_nested = instance
+        .find_function::<(), ()>(&store, "trap_after_nested")
+        .unwrap();
+    match trap_after_nested.call(&mut store) {
+        Err(Error::Trap { backtrace, .. }) => {
+            let names: Vec<_> = backtrace
+                .iter()
+                .map(|frame| frame.name.as_deref())
+                .collect();
+            assert_eq!(names, [Some("trap_after_nested")]);
+        }
+        result => panic!("unexpected result {:?}", result),
+    }
+}
+
+#[test]
+fn closures_reject_reentry() {
+    let (mut store, mut instance) = instantiate(&Linker::new());
+    instance
+        .link_closure(&mut store, "host", "countdown", |mut ctx, n: i32| {
+            if n == 0 {
+                return Ok(0);
+            }
+            let countdown = ctx.data().countdown.unwrap();
+            Ok(countdown.call(&mut ctx, n - 1)? + 1)
+        })
+        .unwrap();
+    init(&mut store, &instance);
+
+    let countdown = instance
+        .find_function::<i32, i32>(&store, "countdown")
+        .unwrap();
+    assert_eq!(countdown.call(&mut store, 0), Ok(0));
+    match countdown.call(&mut store, 1) {
+        Err(Error::Host { error }) => assert_eq!(
+            error.downcast_ref::<Error>(),
+            Some(&Error::HostFunctionReentered)
+        ),
+        result => panic!("unexpected result {:?}", result),
+    }
+}
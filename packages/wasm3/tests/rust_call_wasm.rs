@@ -1,83 +1,88 @@
 use wasm3::{Environment, Instance, Store};
 
-fn runtime() -> Store {
-    Environment::new()
-        .expect("Unable to create environment")
-        .create_store(1024 * 60)
-        .expect("Unable to create runtime")
-}
-
-fn module(rt: &Store) -> Instance {
-    rt.parse_and_instantiate(&include_bytes!("wasm_test_bins/wasm_test_bins.wasm")[..])
-        .expect("Unable to load module")
+fn instance() -> (Store<()>, Instance<()>) {
+    let env = Environment::new().expect("Unable to create environment");
+    let mut store = env
+        .create_store(1024 * 60, ())
+        .expect("Unable to create runtime");
+    let module = env
+        .parse_module(&include_bytes!("wasm_test_bins/wasm_test_bins.wasm")[..])
+        .expect("Unable to parse module");
+    let instance = store.instantiate(module).expect("Unable to load module");
+    (store, instance)
 }
 
 #[test]
 fn test_add_u64() {
-    let rt = runtime();
-    let module = module(&rt);
-    let func = module
-        .find_function::<(u64, u64), u64>("add_u64")
+    let (mut store, instance) = instance();
+    let func = instance
+        .find_function::<(u64, u64), u64>(&store, "add_u64")
         .expect("Unable to find function");
-    assert_eq!(func.call(124, 612), Ok(736));
+    assert_eq!(func.call(&mut store, 124, 612), Ok(736));
 }
 
 #[test]
 fn test_add_u32() {
-    let rt = runtime();
-    let module = module(&rt);
-    let func = module
-        .find_function::<(u32, u32), u32>("add_u32")
+    let (mut store, instance) = instance();
+    let func = instance
+        .find_function::<(u32, u32), u32>(&store, "add_u32")
         .expect("Unable to find function");
-    assert_eq!(func.call(124, 612), Ok(736));
+    assert_eq!(func.call(&mut store, 124, 612), Ok(736));
 }
 
 #[test]
 fn test_unary_func() {
-    let rt = runtime();
-    let module = module(&rt);
-    let func = module
-        .find_function::<u64, u64>("invert")
+    let (mut store, instance) = instance();
+    let func = instance
+        .find_function::<u64, u64>(&store, "invert")
         .expect("Unable to find function");
-    assert_eq!(func.call(736), Ok(!736));
+    assert_eq!(func.call(&mut store, 736), Ok(!736));
 }
 
 #[test]
 fn test_no_return_func() {
-    let rt = runtime();
-    let module = module(&rt);
-    let func = module
-        .find_function::<u64, ()>("no_return")
+    let (mut store, instance) = instance();
+    let func = instance
+        .find_function::<u64, ()>(&store, "no_return")
         .expect("Unable to find function");
-    assert_eq!(func.call(736), Ok(()));
+    assert_eq!(func.call(&mut store, 736), Ok(()));
 }
 
 #[test]
 fn test_no_args_func() {
-    let rt = runtime();
-    let module = module(&rt);
-    let func = module
-        .find_function::<(), u64>("constant")
+    let (mut store, instance) = instance();
+    let func = instance
+        .find_function::<(), u64>(&store, "constant")
         .expect("Unable to find function");
-    assert_eq!(func.call(), Ok(0xDEAD_BEEF_0000_FFFF));
+    assert_eq!(func.call(&mut store), Ok(0xDEAD_BEEF_0000_FFFF));
 }
 
 #[test]
 fn test_no_args_u32_func() {
-    let rt = runtime();
-    let module = module(&rt);
-    let func = module
-        .find_function::<(), u32>("u32")
+    let (mut store, instance) = instance();
+    let func = instance
+        .find_function::<(), u32>(&store, "u32")
         .expect("Unable to find function");
-    assert_eq!(func.call(), Ok(0xDEAD_BEEF));
+    assert_eq!(func.call(&mut store), Ok(0xDEAD_BEEF));
 }
 
 #[test]
 fn test_no_args_no_ret_func() {
-    let rt = runtime();
-    let module = module(&rt);
-    let func = module
-        .find_function::<(), ()>("empty")
+    let (mut store, instance) = instance();
+    let func = instance
+        .find_function::<(), ()>(&store, "empty")
         .expect("Unable to find function");
-    assert_eq!(func.call(), Ok(()));
+    assert_eq!(func.call(&mut store), Ok(()));
+}
+
+#[test]
+fn test_compile_all() {
+    let (mut store, instance) = instance();
+    let before = store.code_memory();
+    instance
+        .compile_all(&mut store)
+        .expect("Unable to compile module");
+    let after = store.code_memory();
+    assert!(after.used > before.used);
+    assert!(after.allocated >= after.used);
 }
@@ -0,0 +1,154 @@
+use wasm3::{error::Error, Environment, Instance, Linker, Snapshot, Store};
+
+mod common;
+
+const PAGE_SIZE: usize = 64 * 1024;
+
+const GUEST: &str = r#"
+(module
+  (memory 1 4)
+  (global $counter (mut i32) (i32.const 0))
+  (global $scale (export "scale") (mut f64) (f64.const 1.5))
+  (global $seed i64 (i64.const 7))
+  (func (export "step") (result i32)
+    (global.set $counter (i32.add (global.get $counter) (i32.const 1)))
+    (global.set $scale (f64.mul (global.get $scale) (f64.const 2)))
+    (i32.store (i32.const 16) (global.get $counter))
+    (global.get $counter))
+  (func (export "grow") (param i32) (result i32)
+    (memory.grow (local.get 0)))
+)
+"#;
+
+fn instantiate(env: &Environment, source: &str) -> (Store<()>, Instance<()>) {
+    common::instantiate(env, &Linker::new(), source, ())
+}
+
+fn step(store: &mut Store<()>, instance: &Instance<()>) -> i32 {
+    let step = instance
+        .find_function::<(), i32>(store, "step")
+        .expect("Unable to find function");
+    step.call(store).unwrap()
+}
+
+#[test]
+fn restore_rewinds_memory_and_globals() {
+    let env = Environment::new().expect("Unable to create environment");
+    let (mut store, instance) = instantiate(&env, GUEST);
+    assert_eq!(step(&mut store, &instance), 1);
+    let snapshot = store.snapshot();
+    assert_eq!(snapshot.memory_pages(), 1);
+
+    let grow = instance
+        .find_function::<i32, i32>(&store, "grow")
+        .expect("Unable to find function");
+    assert_eq!(grow.call(&mut store, 2), Ok(1));
+    assert_eq!(step(&mut store, &instance), 2);
+    store.memory_mut()[2 * PAGE_SIZE] = 1;
+
+    store.restore(&snapshot).unwrap();
+    assert_eq!(store.memory_pages(), 1);
+    assert_eq!(store.memory()[16], 1);
+    let scale = instance.find_global::<f64>(&store, "scale").unwrap();
+    assert_eq!(scale.get(&store), Ok(3.0));
+    assert_eq!(step(&mut store, &instance), 2);
+
+    // the memory that grows again is zeroed
+    assert_eq!(grow.call(&mut store, 2), Ok(1));
+    assert_eq!(store.memory()[2 * PAGE_SIZE], 0);
+}
+
+#[test]
+fn restore_from_bytes_into_another_store() {
+    let env = Environment::new().expect("Unable to create environment");
+    let (mut store, instance) = instantiate(&env, GUEST);
+    for _ in 0..3 {
+        step(&mut store, &instance);
+    }
+    let bytes = store.snapshot().to_bytes();
+
+    let (mut other, other_instance) = instantiate(&env, GUEST);
+    other
+        .restore(&Snapshot::from_bytes_for(&bytes, &other).unwrap())
+        .unwrap();
+    assert_eq!(other.memory(), store.memory());
+    assert_eq!(step(&mut other, &other_instance), 4);
+}
+
+#[test]
+fn restore_keeps_start_from_running_again() {
+    const STARTED: &str = r#"
+(module
+  (global $starts (export "starts") (mut i32) (i32.const 0))
+  (func $start
+    (global.set $starts (i32.add (global.get $starts) (i32.const 1))))
+  (start $start)
+  (func (export "starts_get") (result i32)
+    (global.get $starts))
+)
+"#;
+    let starts = |store: &mut Store<()>, instance: &Instance<()>| {
+        let get = instance
+            .find_function::<(), i32>(store, "starts_get")
+            .expect("Unable to find function");
+        get.call(store).unwrap()
+    };
+
+    let env = Environment::new().expect("Unable to create environment");
+    let (mut store, instance) = instantiate(&env, STARTED);
+    assert_eq!(starts(&mut store, &instance), 1);
+    let bytes = store.snapshot().to_bytes();
+
+    let (mut other, other_instance) = instantiate(&env, STARTED);
+    other
+        .restore(&Snapshot::from_bytes_for(&bytes, &other).unwrap())
+        .unwrap();
+    assert_eq!(starts(&mut other, &other_instance), 1);
+}
+
+#[test]
+fn restore_rejects_other_globals() {
+    let env = Environment::new().expect("Unable to create environment");
+    let (store, _) = instantiate(&env, GUEST);
+    let snapshot = store.snapshot();
+
+    let (mut other, _) = instantiate(
+        &env,
+        r#"(module (memory 1) (global (mut i64) (i64.const 0)) (global (mut f64) (f64.const 0)))"#,
+    );
+    assert_eq!(other.restore(&snapshot), Err(Error::SnapshotMismatch));
+}
+
+#[test]
+fn restore_rejects_other_modules() {
+    let env = Environment::new().expect("Unable to create environment");
+    let (store, _) = instantiate(&env, GUEST);
+    let snapshot = store.snapshot();
+    let bytes = snapshot.to_bytes();
+
+    // the same globals, but the memory is laid out differently
+    let (mut other, _) = instantiate(&env, &GUEST.replace("(i32.const 16)", "(i32.const 32)"));
+    assert_ne!(other.snapshot().modules_hash(), snapshot.modules_hash());
+    assert_eq!(other.restore(&snapshot), Err(Error::SnapshotMismatch));
+    assert_eq!(
+        Snapshot::from_bytes_for(&bytes, &other),
+        Err(Error::SnapshotMismatch)
+    );
+}
+
+#[test]
+fn restore_respects_memory_limit() {
+    let env = Environment::new().expect("Unable to create environment");
+    let (mut store, instance) = instantiate(&env, GUEST);
+    let grow = instance
+        .find_function::<i32, i32>(&store, "grow")
+        .expect("Unable to find function");
+    assert_eq!(grow.call(&mut store, 3), Ok(1));
+    let snapshot = store.snapshot();
+
+    let mut limited = Store::with_memory_limit(&env, common::STACK_SIZE, 2, ()).unwrap();
+    limited
+        .instantiate(common::parse(&env, GUEST))
+        .expect("Unable to load module");
+    assert_eq!(limited.restore(&snapshot), Err(Error::MemoryLimitExceeded));
+}
@@ -0,0 +1,446 @@
+//! Runs the official WebAssembly spec tests and reports how many assertions pass per proposal.
+//!
+//! The suite isn't vendored, check it out next to this file and run the harness on the host:
+//!
+//! ```sh
+//! git clone https://github.com/WebAssembly/testsuite packages/wasm3/tests/testsuite
+//! cargo test -p wasm3 --target x86_64-unknown-linux-gnu --test spec
+//! ```
+//!
+//! `WASM_SPEC_TESTSUITE` points the harness at a checkout elsewhere and `WASM_SPEC_FILTER` only
+//! runs the files whose path contains it. Failed assertions are listed but don't fail the run, as
+//! wasm3 doesn't implement every proposal. Directives the harness can't run are counted as skipped
+//! and summed up by kind at the end.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    env,
+    error::Error as StdError,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use wasm3::{error::Error, function::DynHostFunction, Environment, Instance, Module, Store, Value};
+use wast::{
+    core::{NanPattern, WastArgCore, WastRetCore},
+    parser::{self, ParseBuffer},
+    token::Id,
+    QuoteWat, Wast, WastArg, WastDirective, WastExecute, WastInvoke, WastRet,
+};
+
+type Result<T> = std::result::Result<T, Box<dyn StdError>>;
+
+const STACK_SIZE: u32 = 64 * 1024;
+
+fn main() {
+    let root = env::var_os("WASM_SPEC_TESTSUITE")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/testsuite"));
+    if !root.is_dir() {
+        println!("spec tests not found at {}, skipping", root.display());
+        return;
+    }
+    let filter = env::var("WASM_SPEC_FILTER").ok();
+
+    let mut files = Vec::new();
+    collect_wast(&root, &mut files);
+    files.sort();
+
+    let mut tallies = BTreeMap::<String, Tally>::new();
+    let mut skipped = BTreeMap::<&'static str, usize>::new();
+    for path in &files {
+        let relative = path.strip_prefix(&root).unwrap_or(path);
+        if let Some(filter) = &filter {
+            if !relative.to_string_lossy().contains(filter.as_str()) {
+                continue;
+            }
+        }
+        let tally = tallies.entry(proposal(relative)).or_default();
+        run_file(path, relative, tally, &mut skipped);
+    }
+
+    println!();
+    println!(
+        "{:<32} {:>8} {:>8} {:>8} {:>8}",
+        "proposal", "passed", "total", "rate", "skipped"
+    );
+    for (proposal, tally) in &tallies {
+        let total = tally.passed + tally.failed;
+        let rate = if total == 0 {
+            100.0
+        } else {
+            tally.passed as f64 * 100.0 / total as f64
+        };
+        println!(
+            "{:<32} {:>8} {:>8} {:>7.1}% {:>8}",
+            proposal, tally.passed, total, rate, tally.skipped
+        );
+    }
+
+    if !skipped.is_empty() {
+        println!();
+        println!("{:<32} {:>8}", "skipped", "count");
+        for (kind, count) in &skipped {
+            println!("{:<32} {:>8}", kind, count);
+        }
+    }
+}
+
+#[derive(Default)]
+struct Tally {
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+}
+
+/// The proposal a test file belongs to, `core` for the files at the top of the suite.
+fn proposal(relative: &Path) -> String {
+    let dir = relative.parent().unwrap_or_else(|| Path::new(""));
+    let dir = dir.strip_prefix("proposals").unwrap_or(dir);
+    if dir.as_os_str().is_empty() {
+        "core".into()
+    } else {
+        dir.display().to_string()
+    }
+}
+
+fn collect_wast(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_wast(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "wast") {
+            files.push(path);
+        }
+    }
+}
+
+fn run_file(
+    path: &Path,
+    relative: &Path,
+    tally: &mut Tally,
+    skipped: &mut BTreeMap<&'static str, usize>,
+) {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) => return println!("{}: unreadable: {err}", relative.display()),
+    };
+    let buffer = match ParseBuffer::new(&contents) {
+        Ok(buffer) => buffer,
+        Err(err) => return println!("{}: unparsable: {err}", relative.display()),
+    };
+    let wast = match parser::parse::<Wast>(&buffer) {
+        Ok(wast) => wast,
+        Err(err) => return println!("{}: unparsable: {err}", relative.display()),
+    };
+
+    let mut runner = match Runner::new() {
+        Ok(runner) => runner,
+        Err(err) => return println!("{}: {err}", relative.display()),
+    };
+    for directive in wast.directives {
+        let (line, _) = directive.span().linecol_in(&contents);
+        match runner.run(directive) {
+            Outcome::Checked(Ok(())) => tally.passed += 1,
+            Outcome::Checked(Err(err)) => {
+                tally.failed += 1;
+                println!("{}:{}: {err}", relative.display(), line + 1);
+            }
+            Outcome::Skipped(kind) => {
+                tally.skipped += 1;
+                *skipped.entry(kind).or_default() += 1;
+            }
+        }
+    }
+}
+
+/// What running a directive came to.
+enum Outcome {
+    Checked(Result<()>),
+    /// The directive of the given kind can't be run by this harness.
+    Skipped(&'static str),
+}
+
+/// The instantiated modules of a file.
+///
+/// Every module gets a store of its own so that it has its own memory, except for modules that
+/// import a [registered](WastDirective::Register) module, which have to be instantiated in its
+/// store to be linked to it.
+struct Runner {
+    env: Environment,
+    stores: Vec<Store<()>>,
+    /// The instances with the index of their store.
+    instances: Vec<(usize, Instance<()>)>,
+    current: Option<usize>,
+    names: HashMap<String, usize>,
+    registered: HashMap<String, usize>,
+}
+
+impl Runner {
+    fn new() -> Result<Self> {
+        Ok(Runner {
+            env: Environment::new()?,
+            stores: Vec::new(),
+            instances: Vec::new(),
+            current: None,
+            names: HashMap::new(),
+            registered: HashMap::new(),
+        })
+    }
+
+    fn run(&mut self, directive: WastDirective<'_>) -> Outcome {
+        let outcome = match directive {
+            WastDirective::Module(mut module) => {
+                let name = module.name().map(|id| id.name().to_string());
+                self.current = None;
+                module
+                    .encode()
+                    .map_err(Into::into)
+                    .and_then(|bytes| self.instantiate(bytes))
+                    .map(|index| {
+                        self.current = Some(index);
+                        if let Some(name) = name {
+                            self.names.insert(name, index);
+                        }
+                    })
+            }
+            WastDirective::Register { name, module, .. } => {
+                self.instance_index(module).map(|index| {
+                    self.registered.insert(name.to_string(), index);
+                })
+            }
+            WastDirective::Invoke(invoke) => self.invoke(&invoke).map(drop),
+            WastDirective::AssertReturn { exec, results, .. } => {
+                self.execute(exec).and_then(|values| {
+                    let equal = values.len() == results.len()
+                        && values
+                            .iter()
+                            .zip(&results)
+                            .all(|(value, expected)| matches(value, expected));
+                    if equal {
+                        Ok(())
+                    } else {
+                        Err(format!("assert_return: got {values:?}").into())
+                    }
+                })
+            }
+            WastDirective::AssertTrap { exec, message, .. } => match self.execute(exec) {
+                Err(err) if is_trap(&*err) => Ok(()),
+                Err(err) => Err(format!("assert_trap: expected `{message}`, got {err}").into()),
+                Ok(values) => {
+                    Err(format!("assert_trap: expected `{message}`, got {values:?}").into())
+                }
+            },
+            WastDirective::AssertExhaustion { call, message, .. } => match self.invoke(&call) {
+                Err(err) if is_trap(&*err) => Ok(()),
+                Err(err) => {
+                    Err(format!("assert_exhaustion: expected `{message}`, got {err}").into())
+                }
+                Ok(values) => {
+                    Err(format!("assert_exhaustion: expected `{message}`, got {values:?}").into())
+                }
+            },
+            WastDirective::AssertInvalid {
+                module, message, ..
+            } => match validate(module) {
+                // the text itself is rejected, nothing wasm3 could be tested on
+                None => return Outcome::Skipped("assert_invalid (text)"),
+                Some(Ok(())) => {
+                    Err(format!("assert_invalid: expected `{message}`, but it loaded").into())
+                }
+                Some(Err(_)) => Ok(()),
+            },
+            WastDirective::AssertMalformed {
+                module, message, ..
+            } => match validate(module) {
+                None => return Outcome::Skipped("assert_malformed (text)"),
+                Some(Ok(())) => {
+                    Err(format!("assert_malformed: expected `{message}`, but it loaded").into())
+                }
+                Some(Err(_)) => Ok(()),
+            },
+            WastDirective::AssertUnlinkable {
+                mut module,
+                message,
+                ..
+            } => match module.encode() {
+                Err(_) => return Outcome::Skipped("assert_unlinkable (text)"),
+                Ok(bytes) => match self.instantiate(bytes) {
+                    Ok(_) => Err(
+                        format!("assert_unlinkable: expected `{message}`, but it linked").into(),
+                    ),
+                    Err(_) => Ok(()),
+                },
+            },
+            WastDirective::ModuleDefinition(_) => return Outcome::Skipped("module definition"),
+            WastDirective::ModuleInstance { .. } => return Outcome::Skipped("module instance"),
+            WastDirective::AssertException { .. } => return Outcome::Skipped("assert_exception"),
+            WastDirective::AssertSuspension { .. } => return Outcome::Skipped("assert_suspension"),
+            WastDirective::Thread(_) => return Outcome::Skipped("thread"),
+            WastDirective::Wait { .. } => return Outcome::Skipped("wait"),
+        };
+        Outcome::Checked(outcome)
+    }
+
+    /// Instantiates a module with the `spectest` functions and the registered modules it imports
+    /// linked, and runs its start function.
+    fn instantiate(&mut self, bytes: Vec<u8>) -> Result<usize> {
+        let module = Module::parse(&self.env, bytes)?;
+        let mut imported: Vec<(String, usize)> = Vec::new();
+        for import in module.imports() {
+            let Some(&index) = self.registered.get(import.module()) else {
+                continue;
+            };
+            if !imported.iter().any(|(name, _)| name == import.module()) {
+                imported.push((import.module().to_string(), index));
+            }
+        }
+
+        let store_index = match imported.first() {
+            Some(&(_, index)) => self.instances[index].0,
+            None => {
+                self.stores.push(Store::new(&self.env, STACK_SIZE, ())?);
+                self.stores.len() - 1
+            }
+        };
+        if imported
+            .iter()
+            .any(|&(_, index)| self.instances[index].0 != store_index)
+        {
+            return Err("imports modules that were instantiated in separate stores".into());
+        }
+
+        let instances = &self.instances;
+        let store = &mut self.stores[store_index];
+        let mut instance = store.instantiate(module)?;
+        let linked = (|| -> Result<()> {
+            for (name, index) in &imported {
+                instance.link_instance(store, name, &instances[*index].1)?;
+            }
+            instance.link_unresolved(store, spectest)?;
+            let imports = instance.unresolved_imports(&*store)?;
+            if !imports.is_empty() {
+                return Err(Error::UnresolvedImports { imports }.into());
+            }
+            Ok(instance.run_start(&mut *store)?)
+        })();
+        if let Err(err) = linked {
+            // it would keep the instances it has been linked to in use
+            let _ = store.unload(instance);
+            return Err(err);
+        }
+        self.instances.push((store_index, instance));
+        Ok(self.instances.len() - 1)
+    }
+
+    fn instance_index(&self, name: Option<Id<'_>>) -> Result<usize> {
+        let index = match name {
+            Some(name) => self.names.get(name.name()).copied(),
+            None => self.current,
+        };
+        Ok(index.ok_or("module isn't loaded")?)
+    }
+
+    fn invoke(&mut self, invoke: &WastInvoke<'_>) -> Result<Vec<Value>> {
+        let args = invoke
+            .args
+            .iter()
+            .map(arg)
+            .collect::<Option<Vec<_>>>()
+            .ok_or("unsupported argument")?;
+        let (store_index, instance) = &self.instances[self.instance_index(invoke.module)?];
+        let store = &mut self.stores[*store_index];
+        let function = instance.find_function_dynamic(&*store, invoke.name)?;
+        Ok(function.call(store, &args)?)
+    }
+
+    fn execute(&mut self, exec: WastExecute<'_>) -> Result<Vec<Value>> {
+        match exec {
+            WastExecute::Invoke(invoke) => self.invoke(&invoke),
+            WastExecute::Wat(mut module) => {
+                self.instantiate(module.encode()?)?;
+                Ok(Vec::new())
+            }
+            _ => Err("unsupported action".into()),
+        }
+    }
+}
+
+/// Instantiates a module in a new store with the `spectest` functions linked, compiles all its
+/// functions and runs its start function.
+fn load(bytes: Vec<u8>) -> Result<Store<()>> {
+    let env = Environment::new()?;
+    let module = Module::parse(&env, bytes)?;
+
+    let mut store = Store::new(&env, STACK_SIZE, ())?;
+    let mut instance = store.instantiate(module)?;
+    instance.link_unresolved(&mut store, spectest)?;
+    instance.compile_all(&mut store)?;
+    instance.run_start(&mut store)?;
+    Ok(store)
+}
+
+/// Links the functions of the `spectest` module, which only print their arguments.
+fn spectest(module: &str, name: &str, _ty: &wasm3::FuncType) -> Option<DynHostFunction<()>> {
+    if module == "spectest" && name.starts_with("print") {
+        Some(Box::new(|_ctx, _params, _results| Ok(())))
+    } else {
+        None
+    }
+}
+
+/// Whether wasm3 rejects a module, or `None` if the test's text doesn't encode.
+fn validate(mut module: QuoteWat<'_>) -> Option<Result<()>> {
+    let bytes = module.encode().ok()?;
+    Some(load(bytes).map(drop))
+}
+
+fn is_trap(err: &(dyn StdError + 'static)) -> bool {
+    err.downcast_ref::<Error>()
+        .is_some_and(|err| err.as_trap().is_some())
+}
+
+fn arg(arg: &WastArg<'_>) -> Option<Value> {
+    match arg {
+        WastArg::Core(WastArgCore::I32(value)) => Some(Value::I32(*value)),
+        WastArg::Core(WastArgCore::I64(value)) => Some(Value::I64(*value)),
+        WastArg::Core(WastArgCore::F32(value)) => Some(Value::F32(f32::from_bits(value.bits))),
+        WastArg::Core(WastArgCore::F64(value)) => Some(Value::F64(f64::from_bits(value.bits))),
+        _ => None,
+    }
+}
+
+fn matches(value: &Value, expected: &WastRet<'_>) -> bool {
+    match expected {
+        WastRet::Core(expected) => matches_core(value, expected),
+        _ => false,
+    }
+}
+
+fn matches_core(value: &Value, expected: &WastRetCore<'_>) -> bool {
+    match (value, expected) {
+        (Value::I32(value), WastRetCore::I32(expected)) => value == expected,
+        (Value::I64(value), WastRetCore::I64(expected)) => value == expected,
+        (Value::F32(value), WastRetCore::F32(expected)) => match expected {
+            NanPattern::CanonicalNan => value.to_bits() & 0x7fff_ffff == 0x7fc0_0000,
+            NanPattern::ArithmeticNan => value.is_nan() && value.to_bits() & 0x0040_0000 != 0,
+            NanPattern::Value(expected) => value.to_bits() == expected.bits,
+        },
+        (Value::F64(value), WastRetCore::F64(expected)) => match expected {
+            NanPattern::CanonicalNan => {
+                value.to_bits() & 0x7fff_ffff_ffff_ffff == 0x7ff8_0000_0000_0000
+            }
+            NanPattern::ArithmeticNan => {
+                value.is_nan() && value.to_bits() & 0x0008_0000_0000_0000 != 0
+            }
+            NanPattern::Value(expected) => value.to_bits() == expected.bits,
+        },
+        (value, WastRetCore::Either(options)) => {
+            options.iter().any(|option| matches_core(value, option))
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,56 @@
+use wasm3::{Environment, Linker};
+
+mod common;
+
+const GUEST: &str = r#"
+(module
+  (import "host" "bump" (func $bump (param i32)))
+  (func $start
+    (call $bump (i32.const 100)))
+  (start $start)
+  (func (export "bump") (param i32)
+    (call $bump (local.get 0)))
+)
+"#;
+
+fn linker() -> Linker<Vec<i32>> {
+    let mut linker = Linker::<Vec<i32>>::new();
+    linker
+        .func_wrap("host", "bump", |mut ctx, n: i32| {
+            ctx.data_mut().push(n);
+            Ok(())
+        })
+        .unwrap();
+    linker
+}
+
+#[test]
+fn start_function_runs_on_first_call() {
+    let env = Environment::new().expect("Unable to create environment");
+    let (mut store, instance) = common::instantiate(&env, &linker(), GUEST, Vec::new());
+
+    let bump = instance
+        .find_function::<i32, ()>(&store, "bump")
+        .expect("Unable to find function");
+    assert!(store.data().is_empty());
+
+    bump.call(&mut store, 1).unwrap();
+    bump.call(&mut store, 2).unwrap();
+    assert_eq!(store.data(), &[100, 1, 2]);
+}
+
+#[test]
+fn run_start_runs_once() {
+    let env = Environment::new().expect("Unable to create environment");
+    let (mut store, instance) = common::instantiate(&env, &linker(), GUEST, Vec::new());
+
+    instance.run_start(&mut store).unwrap();
+    instance.run_start(&mut store).unwrap();
+    store.data_mut().push(0);
+
+    let bump = instance
+        .find_function::<i32, ()>(&store, "bump")
+        .expect("Unable to find function");
+    bump.call(&mut store, 1).unwrap();
+    assert_eq!(store.data(), &[100, 0, 1]);
+}
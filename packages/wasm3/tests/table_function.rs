@@ -0,0 +1,95 @@
+use wasm3::{
+    error::{Error, Trap},
+    Environment, Instance, Linker, Store,
+};
+
+mod common;
+
+const GUEST: &str = r#"
+(module
+  (type $binary (func (param i32 i32) (result i32)))
+  (table 3 funcref)
+  (elem (i32.const 0) $add $mul)
+  (func $add (type $binary)
+    (i32.add (local.get 0) (local.get 1)))
+  (func $mul (type $binary)
+    (i32.mul (local.get 0) (local.get 1)))
+  ;; calls a function pointer the way the guest's own code does
+  (func (export "apply") (param i32 i32 i32) (result i32)
+    (call_indirect (type $binary) (local.get 1) (local.get 2) (local.get 0)))
+)
+"#;
+
+fn instantiate(env: &Environment) -> (Store<()>, Instance<()>) {
+    common::instantiate(env, &Linker::new(), GUEST, ())
+}
+
+#[test]
+fn table_function_calls_the_element() {
+    let env = Environment::new().expect("Unable to create environment");
+    let (mut store, instance) = instantiate(&env);
+
+    let mul = instance
+        .table_function::<(i32, i32), i32>(&store, 1)
+        .expect("Unable to find function");
+    assert_eq!(mul.call(&mut store, 6, 7), Ok(42));
+
+    let apply = instance
+        .find_function::<(i32, i32, i32), i32>(&store, "apply")
+        .expect("Unable to find function");
+    assert_eq!(apply.call(&mut store, 0, 6, 7), Ok(13));
+    assert_eq!(apply.call(&mut store, 1, 6, 7), Ok(42));
+}
+
+#[test]
+fn table_function_rejects_null_elements() {
+    let env = Environment::new().expect("Unable to create environment");
+    let (store, instance) = instantiate(&env);
+
+    let result = instance.table_function::<(i32, i32), i32>(&store, 2);
+    assert!(
+        matches!(
+            result,
+            Err(Error::Trap {
+                trap: Trap::TableElementIsNull,
+                ..
+            })
+        ),
+        "unexpected result {:?}",
+        result
+    );
+}
+
+#[test]
+fn table_function_rejects_indices_out_of_range() {
+    let env = Environment::new().expect("Unable to create environment");
+    let (store, instance) = instantiate(&env);
+
+    for index in [3, u32::MAX] {
+        let result = instance.table_function::<(i32, i32), i32>(&store, index);
+        assert!(
+            matches!(
+                result,
+                Err(Error::Trap {
+                    trap: Trap::TableIndexOutOfRange,
+                    ..
+                })
+            ),
+            "unexpected result {:?}",
+            result
+        );
+    }
+}
+
+#[test]
+fn table_function_rejects_other_signatures() {
+    let env = Environment::new().expect("Unable to create environment");
+    let (store, instance) = instantiate(&env);
+
+    let result = instance.table_function::<i32, i32>(&store, 0);
+    assert!(
+        matches!(result, Err(Error::InvalidFunctionSignature)),
+        "unexpected result {:?}",
+        result
+    );
+}
@@ -0,0 +1,137 @@
+use wasm3::{error::Error, Environment, Module, Store};
+
+mod common;
+
+const GUEST: &str = r#"
+(module
+  (import "host" "tick" (func $tick (result i32)))
+  (memory 1)
+  (global $calls (export "calls") (mut i32) (i32.const 0))
+  (func (export "bump") (result i32)
+    (global.set $calls (i32.add (global.get $calls) (call $tick)))
+    (i32.store (i32.const 0) (global.get $calls))
+    (global.get $calls))
+)
+"#;
+
+fn module(env: &Environment) -> Module {
+    common::parse(env, GUEST)
+}
+
+fn bump(store: &mut Store<()>, instance: &wasm3::Instance<()>) -> wasm3::error::Result<i32> {
+    let bump = instance.find_function::<(), i32>(store, "bump")?;
+    bump.call(store)
+}
+
+#[test]
+fn module_instantiates_repeatedly() {
+    let env = Environment::new().expect("Unable to create environment");
+    let module = module(&env);
+    let mut store = common::store(&env, ());
+
+    for _ in 0..3 {
+        let mut instance = store.instantiate(module.clone()).unwrap();
+        instance
+            .link_closure(&mut store, "host", "tick", |_, ()| Ok(2))
+            .unwrap();
+        assert_eq!(bump(&mut store, &instance), Ok(2));
+        assert_eq!(store.memory()[0], 2);
+        store.unload(instance).unwrap();
+    }
+    // the last clone takes the module parsed up front
+    store.instantiate(module).unwrap();
+}
+
+#[test]
+fn instances_of_one_module_are_separate() {
+    let env = Environment::new().expect("Unable to create environment");
+    let module = module(&env);
+    let mut store = common::store(&env, ());
+
+    let mut first = store.instantiate(module.clone()).unwrap();
+    first
+        .link_closure(&mut store, "host", "tick", |_, ()| Ok(1))
+        .unwrap();
+    let mut second = store.instantiate(module).unwrap();
+    second
+        .link_closure(&mut store, "host", "tick", |_, ()| Ok(10))
+        .unwrap();
+
+    assert_eq!(bump(&mut store, &first), Ok(1));
+    assert_eq!(bump(&mut store, &second), Ok(10));
+    assert_eq!(bump(&mut store, &first), Ok(2));
+
+    // the memory is shared and survives unloading one of them
+    store.unload(second).unwrap();
+    assert_eq!(store.memory()[0], 2);
+    assert_eq!(bump(&mut store, &first), Ok(3));
+}
+
+#[test]
+fn handles_of_unloaded_instances_fail() {
+    let env = Environment::new().expect("Unable to create environment");
+    let mut store = common::store(&env, ());
+    let mut instance = store.instantiate(module(&env)).unwrap();
+    instance
+        .link_closure(&mut store, "host", "tick", |_, ()| Ok(1))
+        .unwrap();
+    let bump = instance.find_function::<(), i32>(&store, "bump").unwrap();
+    let calls = instance.find_global::<i32>(&store, "calls").unwrap();
+    assert_eq!(bump.call(&mut store), Ok(1));
+
+    store.unload(instance.clone()).unwrap();
+    assert_eq!(bump.call(&mut store), Err(Error::InstanceUnloaded));
+    assert_eq!(calls.get(&store), Err(Error::InstanceUnloaded));
+    assert_eq!(store.unload(instance.clone()), Err(Error::InstanceUnloaded));
+
+    // the last instance took the memory with it
+    assert_eq!(store.memory_pages(), 0);
+    let instance = store.instantiate(module(&env)).unwrap();
+    assert_eq!(store.memory(), &[0; 64 * 1024][..]);
+    assert_eq!(bump.call(&mut store), Err(Error::InstanceUnloaded));
+    let calls = instance.find_global::<i32>(&store, "calls").unwrap();
+    assert_eq!(calls.get(&store), Ok(0));
+}
+
+#[test]
+fn unload_rejects_other_stores() {
+    let env = Environment::new().expect("Unable to create environment");
+    let mut store = common::store(&env, ());
+    let mut other = common::store(&env, ());
+    let instance = store.instantiate(module(&env)).unwrap();
+    assert_eq!(other.unload(instance), Err(Error::StoreMismatch));
+}
+
+#[cfg(feature = "record-backtraces")]
+#[test]
+fn unload_clears_the_backtrace() {
+    let env = Environment::new().expect("Unable to create environment");
+    let mut store = common::store(&env, ());
+    let instance = store
+        .instantiate(common::parse(
+            &env,
+            r#"(module (func $trap (export "trap") (unreachable)))"#,
+        ))
+        .unwrap();
+    let trap = instance.find_function::<(), ()>(&store, "trap").unwrap();
+    assert!(matches!(trap.call(&mut store), Err(Error::Trap { .. })));
+    store.unload(instance).unwrap();
+
+    // the next trap doesn't list the frames of the unloaded module
+    let instance = store
+        .instantiate(common::parse(
+            &env,
+            r#"(module (func $start (unreachable)) (start $start))"#,
+        ))
+        .unwrap();
+    match instance.run_start(&mut store) {
+        Err(Error::Trap { backtrace, .. }) => {
+            let names: Vec<_> = backtrace
+                .iter()
+                .map(|frame| frame.name.as_deref())
+                .collect();
+            assert_eq!(names, [Some("start")]);
+        }
+        result => panic!("unexpected result {:?}", result),
+    }
+}